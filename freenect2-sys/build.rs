@@ -1,13 +1,16 @@
 fn main() {
-	println!("cargo:rustc-link-lib=freenect2");
 	println!("cargo:rerun-if-changed=src/wrapper.hpp");
 	println!("cargo:rerun-if-changed=src/wrapper.cpp");
 
-	cc::Build::new()
-		.file("src/wrapper.cpp")
-		.cpp(true)
-		.include("src")
-		.compile("wrapper");
+	// the mock implements the wrapper in Rust, so neither it nor libfreenect2 is needed
+	if std::env::var_os("CARGO_FEATURE_MOCK").is_none() {
+		println!("cargo:rustc-link-lib=freenect2");
+		cc::Build::new()
+			.file("src/wrapper.cpp")
+			.cpp(true)
+			.include("src")
+			.compile("wrapper");
+	}
 
 	let bindings = bindgen::builder()
 		.header("src/wrapper.hpp")
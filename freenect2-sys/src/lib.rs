@@ -1,9 +1,14 @@
 //! # libfreenect2-sys
 //!
 //! Unsafe C-style bindings to [libfreenect2](https://github.com/OpenKinect/libfreenect2).
+//!
+//! With the `mock` feature, the bindings are implemented by fake devices instead; see [`mock`].
 
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 #![allow(non_upper_case_globals)]
 
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+#[cfg(feature = "mock")]
+pub mod mock;
@@ -0,0 +1,1084 @@
+//! A fake libfreenect2, for testing without a Kinect.
+//!
+//! With the `mock` feature, libfreenect2 is not linked; instead, this module implements every `fn2_*` function.
+//! The fake devices can be configured with [`set_devices`], or with environment variables:
+//!
+//! - `FREENECT2_MOCK_DEVICES`: comma-separated serial numbers of the devices. Defaults to one device, [`DEFAULT_SERIAL`].
+//! - `FREENECT2_MOCK_COLOR`, `FREENECT2_MOCK_IR` and `FREENECT2_MOCK_DEPTH`: files of raw frames for every device to play back, as described by [`MockFrames::Raw`].
+//!
+//! Frames are emitted on a timer from a separate thread, like libfreenect2 does.
+
+use std::collections::HashSet;
+use std::os::raw::{c_int, c_void};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::*;
+
+/// The serial number of the fake device that exists if none are configured.
+pub const DEFAULT_SERIAL: &str = "000000000000";
+
+const DEPTH_WIDTH: usize = 512;
+const DEPTH_HEIGHT: usize = 424;
+const COLOR_WIDTH: usize = 1920;
+const COLOR_HEIGHT: usize = 1080;
+
+/// Where the frames of a [`MockDevice`] come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockFrames {
+	/// Generated frames of a flat background with a round object in front of it, moving in a circle.
+	Synthetic,
+	/// Frames read from files of consecutive raw frames, which are played back in a loop.
+	///
+	/// Color frames are 1920 by 1080 pixels in the Bgrx format; IR and depth frames are 512 by 424 pixels in the Float format.
+	/// Streams without a file are generated as in [`Synthetic`](Self::Synthetic).
+	Raw {
+		/// The color frames.
+		color: Option<PathBuf>,
+		/// The IR frames.
+		ir: Option<PathBuf>,
+		/// The depth frames.
+		depth: Option<PathBuf>,
+	},
+}
+
+/// A fake device.
+#[derive(Debug, Clone)]
+pub struct MockDevice {
+	/// The serial number.
+	pub serial: String,
+	/// The firmware version.
+	pub firmware_version: String,
+	/// The parameters of the color camera.
+	pub color_params: Fn2ColorCameraParams,
+	/// The parameters of the IR camera.
+	pub ir_params: Fn2IrCameraParams,
+	/// Where the frames come from.
+	pub frames: MockFrames,
+	/// The time between frames of each stream.
+	pub frame_interval: Duration,
+	/// Every this many frames, the frames of each stream fail to decode and have a non-zero status, as if their USB packets were corrupted; 0 for never.
+	pub error_interval: u32,
+	/// Every this many frames, the frames of each stream are dropped, leaving a gap in the sequence numbers, as if their USB packets were lost; 0 for never.
+	pub drop_interval: u32,
+}
+
+impl MockDevice {
+	/// A device with synthetic frames at 30 frames per second.
+	///
+	/// Its camera parameters are typical of a Kinect, except that the lenses have no distortion.
+	#[must_use]
+	pub fn new(serial: impl Into<String>) -> Self {
+		Self {
+			serial: serial.into(),
+			firmware_version: "4.0.3916.0".to_owned(),
+			color_params: Fn2ColorCameraParams {
+				fx: 1081.372,
+				fy: 1081.372,
+				cx: 959.5,
+				cy: 539.5,
+				shift_d: 863.0,
+				shift_m: 52.0,
+				mx_x3y0: 0.0,
+				mx_x0y3: 0.0,
+				mx_x2y1: 0.0,
+				mx_x1y2: 0.0,
+				mx_x2y0: 0.0,
+				mx_x0y2: 0.0,
+				mx_x1y1: 0.0,
+				mx_x1y0: 0.650_677,
+				mx_x0y1: 0.0,
+				mx_x0y0: 0.143_283,
+				my_x3y0: 0.0,
+				my_x0y3: 0.0,
+				my_x2y1: 0.0,
+				my_x1y2: 0.0,
+				my_x2y0: 0.0,
+				my_x0y2: 0.0,
+				my_x1y1: 0.0,
+				my_x1y0: 0.0,
+				my_x0y1: 0.650_677,
+				my_x0y0: 0.0,
+			},
+			ir_params: Fn2IrCameraParams {
+				fx: 365.456,
+				fy: 365.456,
+				cx: 254.878,
+				cy: 205.395,
+				k1: 0.0,
+				k2: 0.0,
+				k3: 0.0,
+				p1: 0.0,
+				p2: 0.0,
+			},
+			frames: MockFrames::Synthetic,
+			frame_interval: Duration::from_micros(33_333),
+			error_interval: 0,
+			drop_interval: 0,
+		}
+	}
+}
+
+/// The configured devices, or `None` to use the environment variables.
+static DEVICES: Mutex<Option<Vec<MockDevice>>> = Mutex::new(None);
+
+/// The serial numbers of the devices that are open.
+static OPEN: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+	mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Replace the fake devices.
+///
+/// The devices are discovered by the next enumeration; devices that are already open are not affected.
+pub fn set_devices(devices: Vec<MockDevice>) {
+	*lock(&DEVICES) = Some(devices);
+}
+
+/// The fake devices that the next enumeration will discover.
+#[must_use]
+pub fn devices() -> Vec<MockDevice> {
+	if let Some(devices) = &*lock(&DEVICES) {
+		return devices.clone();
+	}
+
+	let var = |name| std::env::var_os(name).map(PathBuf::from);
+	let frames = match (
+		var("FREENECT2_MOCK_COLOR"),
+		var("FREENECT2_MOCK_IR"),
+		var("FREENECT2_MOCK_DEPTH"),
+	) {
+		(None, None, None) => MockFrames::Synthetic,
+		(color, ir, depth) => MockFrames::Raw { color, ir, depth },
+	};
+	let serials =
+		std::env::var("FREENECT2_MOCK_DEVICES").unwrap_or_else(|_| DEFAULT_SERIAL.to_owned());
+	serials
+		.split(',')
+		.map(str::trim)
+		.filter(|serial| !serial.is_empty())
+		.map(|serial| MockDevice {
+			frames: frames.clone(),
+			..MockDevice::new(serial)
+		})
+		.collect()
+}
+
+/// A raw pointer that is passed to another thread, along with the obligations of using it there.
+struct SendPtr(*mut c_void);
+
+unsafe impl Send for SendPtr {}
+
+struct Logger {
+	vtable: Fn2LoggerVTable,
+	user_data: SendPtr,
+}
+
+static LOGGER: Mutex<Option<Logger>> = Mutex::new(None);
+
+/// Log a message through the logger set with [`fn2_set_logger`], like libfreenect2 does.
+fn log(level: Fn2LogLevel, message: &str) {
+	let logger = lock(&LOGGER);
+	let Some(Logger { vtable, user_data }) = &*logger else {
+		return;
+	};
+	let (Some(max_level), Some(log)) = (vtable.level, vtable.log) else {
+		return;
+	};
+	unsafe {
+		if level != Fn2LogLevel_None && level <= max_level(user_data.0) {
+			log(user_data.0, level, borrow(message));
+		}
+	}
+}
+
+fn borrow(string: &str) -> Fn2RustyBorrowedString {
+	Fn2RustyBorrowedString {
+		data: string.as_ptr(),
+		len: string.len(),
+	}
+}
+
+unsafe fn call_string_callback(
+	string: &str,
+	callback: Fn2StringCallback,
+	callback_data: *mut c_void,
+) {
+	if let Some(callback) = callback {
+		callback(callback_data, borrow(string));
+	}
+}
+
+/// The data of a frame, which `Fn2Frame::native` points to.
+///
+/// Like the data of libfreenect2's frames, it is aligned to 8 bytes, so that it can be viewed as pixels of any type.
+struct NativeFrame {
+	data: Vec<u64>,
+}
+
+fn new_frame(
+	width: usize,
+	height: usize,
+	bytes_per_pixel: usize,
+	format: Fn2FrameFormat,
+	data: Vec<u8>,
+) -> Fn2Frame {
+	let mut aligned = vec![0_u64; data.len().div_ceil(8)];
+	// SAFETY: `aligned` has room for at least `data.len()` bytes, and is a separate allocation
+	unsafe {
+		std::ptr::copy_nonoverlapping(data.as_ptr(), aligned.as_mut_ptr().cast(), data.len());
+	}
+	let mut native = Box::new(NativeFrame { data: aligned });
+	let data = native.data.as_mut_ptr().cast();
+	Fn2Frame {
+		width,
+		height,
+		bytes_per_pixel,
+		data,
+		timestamp: 0,
+		sequence: 0,
+		exposure: 0.0,
+		gain: 0.0,
+		gamma: 0.0,
+		status: 0,
+		format,
+		native: Box::into_raw(native).cast(),
+	}
+}
+
+struct Context {
+	/// The devices discovered by the last enumeration, or `None` if the devices have not been enumerated.
+	devices: Option<Vec<MockDevice>>,
+}
+
+impl Context {
+	fn devices(&mut self) -> &[MockDevice] {
+		self.devices.get_or_insert_with(devices)
+	}
+}
+
+struct Listener {
+	callback: Fn2FrameCallback,
+	user_data: SendPtr,
+	drop_user_data: Option<unsafe extern "C" fn(*mut c_void)>,
+}
+
+impl Drop for Listener {
+	fn drop(&mut self) {
+		if let Some(drop_user_data) = self.drop_user_data {
+			unsafe { drop_user_data(self.user_data.0) }
+		}
+	}
+}
+
+struct Streaming {
+	stop: Arc<AtomicBool>,
+	thread: JoinHandle<()>,
+}
+
+struct Device {
+	device: MockDevice,
+	config: Arc<Mutex<Fn2DeviceConfig>>,
+	listener: Arc<Mutex<Option<Listener>>>,
+	streaming: Option<Streaming>,
+	open: bool,
+}
+
+impl Device {
+	fn start(&mut self, rgb: bool, depth: bool) -> bool {
+		if !self.open || self.streaming.is_some() {
+			return false;
+		}
+
+		let stop = Arc::new(AtomicBool::new(false));
+		let source = match FrameSource::new(&self.device.frames) {
+			Ok(source) => source,
+			Err(error) => {
+				log(
+					Fn2LogLevel_Error,
+					&format!("[MockDevice] could not read frames: {error}"),
+				);
+				return false;
+			}
+		};
+		let emitter = Emitter {
+			source,
+			registration: Registration {
+				ir: self.device.ir_params,
+				color: self.device.color_params,
+			},
+			interval: self.device.frame_interval,
+			error_interval: self.device.error_interval,
+			drop_interval: self.device.drop_interval,
+			rgb,
+			depth,
+			config: Arc::clone(&self.config),
+			listener: Arc::clone(&self.listener),
+			stop: Arc::clone(&stop),
+		};
+		self.streaming = Some(Streaming {
+			stop,
+			thread: std::thread::spawn(move || emitter.run()),
+		});
+		log(Fn2LogLevel_Info, "[MockDevice] started");
+		true
+	}
+
+	fn stop(&mut self) -> bool {
+		let Some(streaming) = self.streaming.take() else {
+			return false;
+		};
+		streaming.stop.store(true, Ordering::Relaxed);
+		let _ = streaming.thread.join();
+		log(Fn2LogLevel_Info, "[MockDevice] stopped");
+		true
+	}
+
+	fn close(&mut self) {
+		self.stop();
+		if self.open {
+			self.open = false;
+			if let Some(open) = &mut *lock(&OPEN) {
+				open.remove(&self.device.serial);
+			}
+			log(Fn2LogLevel_Info, "[MockDevice] closed");
+		}
+	}
+}
+
+/// Where the frames of a stream come from.
+enum Stream {
+	Synthetic,
+	/// Consecutive raw frames, and the index of the next one.
+	Raw(Vec<u8>, usize),
+}
+
+impl Stream {
+	fn new(path: Option<&PathBuf>, frame_len: usize) -> std::io::Result<Self> {
+		let Some(path) = path else {
+			return Ok(Self::Synthetic);
+		};
+		let data = std::fs::read(path)?;
+		if data.len() < frame_len || data.len() % frame_len != 0 {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"{} is not a whole number of {frame_len}-byte frames",
+					path.display()
+				),
+			));
+		}
+		Ok(Self::Raw(data, 0))
+	}
+
+	/// The next raw frame, or `None` if the frame should be generated.
+	fn next(&mut self, frame_len: usize) -> Option<Vec<u8>> {
+		match self {
+			Self::Synthetic => None,
+			Self::Raw(data, index) => {
+				let frames = data.len() / frame_len;
+				let frame = data[*index * frame_len..][..frame_len].to_vec();
+				*index = (*index + 1) % frames;
+				Some(frame)
+			}
+		}
+	}
+}
+
+struct FrameSource {
+	color: Stream,
+	ir: Stream,
+	depth: Stream,
+}
+
+impl FrameSource {
+	fn new(frames: &MockFrames) -> std::io::Result<Self> {
+		match frames {
+			MockFrames::Synthetic => Ok(Self {
+				color: Stream::Synthetic,
+				ir: Stream::Synthetic,
+				depth: Stream::Synthetic,
+			}),
+			MockFrames::Raw { color, ir, depth } => Ok(Self {
+				color: Stream::new(color.as_ref(), COLOR_WIDTH * COLOR_HEIGHT * 4)?,
+				ir: Stream::new(ir.as_ref(), DEPTH_WIDTH * DEPTH_HEIGHT * 4)?,
+				depth: Stream::new(depth.as_ref(), DEPTH_WIDTH * DEPTH_HEIGHT * 4)?,
+			}),
+		}
+	}
+}
+
+/// The radius of the synthetic object, in depth pixels.
+const OBJECT_RADIUS: f32 = 30.0;
+
+/// The position of the synthetic object at `time`, in depth pixels, and its depth in millimeters.
+fn object(time: f32) -> (f32, f32, f32) {
+	let angle = time * std::f32::consts::TAU / 4.0;
+	(
+		DEPTH_WIDTH as f32 / 2.0 + 100.0 * angle.cos(),
+		DEPTH_HEIGHT as f32 / 2.0 + 80.0 * angle.sin(),
+		900.0,
+	)
+}
+
+/// The synthetic depth, in millimeters, at a pixel of the depth frame.
+fn synthetic_depth(time: f32, x: usize, y: usize) -> f32 {
+	let (object_x, object_y, object_depth) = object(time);
+	let (dx, dy) = (x as f32 - object_x, y as f32 - object_y);
+	if dx * dx + dy * dy < OBJECT_RADIUS * OBJECT_RADIUS {
+		object_depth
+	} else {
+		2500.0
+	}
+}
+
+fn float_frame(pixels: impl Iterator<Item = f32>) -> Vec<u8> {
+	pixels.flat_map(f32::to_ne_bytes).collect()
+}
+
+/// Emits frames to the listener until stopped.
+struct Emitter {
+	source: FrameSource,
+	/// Maps the synthetic object onto the color frame.
+	registration: Registration,
+	interval: Duration,
+	error_interval: u32,
+	drop_interval: u32,
+	rgb: bool,
+	depth: bool,
+	config: Arc<Mutex<Fn2DeviceConfig>>,
+	listener: Arc<Mutex<Option<Listener>>>,
+	stop: Arc<AtomicBool>,
+}
+
+impl Emitter {
+	fn run(mut self) {
+		let start = Instant::now();
+		let mut sequence = 0_u32;
+		let mut next = start;
+		while !self.stop.load(Ordering::Relaxed) {
+			next += self.interval;
+			if let Some(wait) = next.checked_duration_since(Instant::now()) {
+				std::thread::sleep(wait);
+			}
+
+			let elapsed = start.elapsed();
+			// timestamps are in units of 100 microseconds, and wrap around
+			#[allow(clippy::cast_possible_truncation)]
+			let timestamp = (elapsed.as_micros() / 100) as u32;
+			let time = elapsed.as_secs_f32();
+
+			let every = |interval: u32| interval != 0 && sequence % interval == interval - 1;
+			let dropped = every(self.drop_interval);
+			let status = u32::from(every(self.error_interval));
+			if dropped {
+				sequence = sequence.wrapping_add(1);
+				continue;
+			}
+
+			if self.rgb {
+				let mut frame = self.color(time);
+				frame.status = status;
+				self.emit(frame, Fn2FrameType_Color, timestamp, sequence);
+			}
+			if self.depth {
+				let (mut ir, mut depth) = self.ir_and_depth(time);
+				ir.status = status;
+				depth.status = status;
+				self.emit(ir, Fn2FrameType_Ir, timestamp, sequence);
+				self.emit(depth, Fn2FrameType_Depth, timestamp, sequence);
+			}
+			sequence = sequence.wrapping_add(1);
+		}
+	}
+
+	fn color(&mut self, time: f32) -> Fn2Frame {
+		let data = self
+			.source
+			.color
+			.next(COLOR_WIDTH * COLOR_HEIGHT * 4)
+			.unwrap_or_else(|| {
+				let (object_x, object_y, object_depth) = object(time);
+				let (object_x, object_y) = self
+					.registration
+					.apply_point(object_x, object_y, object_depth);
+				let radius = OBJECT_RADIUS * self.registration.color.fx / self.registration.ir.fx;
+				(0..COLOR_HEIGHT)
+					.flat_map(|y| (0..COLOR_WIDTH).map(move |x| (x, y)))
+					.flat_map(|(x, y)| {
+						let (dx, dy) = (x as f32 - object_x, y as f32 - object_y);
+						if dx * dx + dy * dy < radius * radius {
+							[0x40, 0x80, 0xe0, 0xff]
+						} else {
+							#[allow(clippy::cast_possible_truncation)]
+							let shade = (x * 255 / COLOR_WIDTH) as u8;
+							[shade, shade, shade, 0xff]
+						}
+					})
+					.collect()
+			});
+		let mut frame = new_frame(COLOR_WIDTH, COLOR_HEIGHT, 4, Fn2FrameFormat_Bgrx, data);
+		frame.exposure = 16.0;
+		frame.gain = 1.0;
+		frame.gamma = 1.0;
+		frame
+	}
+
+	fn ir_and_depth(&mut self, time: f32) -> (Fn2Frame, Fn2Frame) {
+		let frame_len = DEPTH_WIDTH * DEPTH_HEIGHT * 4;
+		let pixels = || (0..DEPTH_HEIGHT).flat_map(|y| (0..DEPTH_WIDTH).map(move |x| (x, y)));
+
+		let mut depth = self
+			.source
+			.depth
+			.next(frame_len)
+			.unwrap_or_else(|| float_frame(pixels().map(|(x, y)| synthetic_depth(time, x, y))));
+		let ir = self.source.ir.next(frame_len).unwrap_or_else(|| {
+			float_frame(pixels().map(|(x, y)| {
+				// closer objects reflect more light
+				1.0e9 / synthetic_depth(time, x, y).powi(2)
+			}))
+		});
+
+		// like libfreenect2, remove depths outside of the configured range
+		let config = *lock(&self.config);
+		for pixel in depth.chunks_exact_mut(4) {
+			let value = f32::from_ne_bytes(pixel.try_into().unwrap());
+			if !(config.min_depth * 1000.0..=config.max_depth * 1000.0).contains(&value) {
+				pixel.copy_from_slice(&0.0_f32.to_ne_bytes());
+			}
+		}
+
+		(
+			new_frame(DEPTH_WIDTH, DEPTH_HEIGHT, 4, Fn2FrameFormat_Float, ir),
+			new_frame(DEPTH_WIDTH, DEPTH_HEIGHT, 4, Fn2FrameFormat_Float, depth),
+		)
+	}
+
+	fn emit(&self, mut frame: Fn2Frame, ty: Fn2FrameType, timestamp: u32, sequence: u32) {
+		frame.timestamp = timestamp;
+		frame.sequence = sequence;
+		match &*lock(&self.listener) {
+			Some(Listener {
+				callback: Some(callback),
+				user_data,
+				..
+			}) => unsafe { callback(user_data.0, frame, ty) },
+			_ => unsafe { fn2_frame_free(frame.native) },
+		}
+	}
+}
+
+struct Registration {
+	ir: Fn2IrCameraParams,
+	color: Fn2ColorCameraParams,
+}
+
+/// The scales of the coordinates in the polynomials of [`Fn2ColorCameraParams`], as in libfreenect2.
+const DEPTH_Q: f32 = 0.01;
+const COLOR_Q: f32 = 0.002_199;
+
+impl Registration {
+	/// Map a point of the depth frame to the color frame, before accounting for the distance between the cameras, like libfreenect2.
+	///
+	/// This ignores the distortion of the IR camera's lens.
+	fn depth_to_color(&self, x: f32, y: f32) -> (f32, f32) {
+		let c = &self.color;
+		let x = (x - self.ir.cx) * DEPTH_Q;
+		let y = (y - self.ir.cy) * DEPTH_Q;
+		let polynomial = |[x3y0, x0y3, x2y1, x1y2, x2y0, x0y2, x1y1, x1y0, x0y1, x0y0]: [f32; 10]| {
+			x * x * x * x3y0
+				+ y * y * y * x0y3
+				+ x * x * y * x2y1
+				+ x * y * y * x1y2
+				+ x * x * x2y0
+				+ y * y * x0y2
+				+ x * y * x1y1
+				+ x * x1y0
+				+ y * x0y1
+				+ x0y0
+		};
+		let wx = polynomial([
+			c.mx_x3y0, c.mx_x0y3, c.mx_x2y1, c.mx_x1y2, c.mx_x2y0, c.mx_x0y2, c.mx_x1y1, c.mx_x1y0,
+			c.mx_x0y1, c.mx_x0y0,
+		]);
+		let wy = polynomial([
+			c.my_x3y0, c.my_x0y3, c.my_x2y1, c.my_x1y2, c.my_x2y0, c.my_x0y2, c.my_x1y1, c.my_x1y0,
+			c.my_x0y1, c.my_x0y0,
+		]);
+		(
+			wx / (c.fx * COLOR_Q) - c.shift_m / c.shift_d,
+			wy / COLOR_Q + c.cy,
+		)
+	}
+
+	/// Map a point of the depth frame, at a depth in millimeters, to the color frame.
+	fn apply_point(&self, x: f32, y: f32, depth: f32) -> (f32, f32) {
+		let (x, y) = self.depth_to_color(x, y);
+		(
+			(x + self.color.shift_m / depth) * self.color.fx + self.color.cx,
+			y,
+		)
+	}
+}
+
+unsafe fn view_slice<'a, T: Copy>(view: &Fn2FrameView) -> &'a mut [T] {
+	std::slice::from_raw_parts_mut(view.data.cast(), view.width * view.height)
+}
+
+#[no_mangle]
+extern "C" fn fn2_context_new() -> *mut Fn2Context {
+	Box::into_raw(Box::new(Context { devices: None })).cast()
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_context_enumerate_devices(this_: *mut Fn2Context) -> c_int {
+	let this = &mut *this_.cast::<Context>();
+	let devices = devices();
+	log(
+		Fn2LogLevel_Info,
+		&format!("[Freenect2Impl] found {} devices", devices.len()),
+	);
+	let len = devices.len();
+	this.devices = Some(devices);
+	len.try_into().unwrap_or(c_int::MAX)
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_context_get_device_serial_number(
+	this_: *const Fn2Context,
+	idx: c_int,
+	callback: Fn2StringCallback,
+	callback_data: *mut c_void,
+) {
+	let this = &*this_.cast::<Context>();
+	let serial = usize::try_from(idx)
+		.ok()
+		.and_then(|idx| this.devices.as_ref()?.get(idx))
+		.map_or("", |device| &device.serial);
+	call_string_callback(serial, callback, callback_data);
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_context_get_default_device_serial_number(
+	this_: *const Fn2Context,
+	callback: Fn2StringCallback,
+	callback_data: *mut c_void,
+) {
+	fn2_context_get_device_serial_number(this_, 0, callback, callback_data);
+}
+
+/// Open the device at `idx`, the device with the serial number `serial`, or else the first device, with `pipeline` or else the default pipeline.
+unsafe fn open(
+	this_: *mut Fn2Context,
+	idx: Option<c_int>,
+	serial: Option<Fn2RustyBorrowedString>,
+	pipeline: Option<Fn2PacketPipeline>,
+	already_open: *mut bool,
+) -> *mut Fn2Device {
+	*already_open = false;
+	let this = &mut *this_.cast::<Context>();
+	if let Some(pipeline) = pipeline.filter(|&pipeline| !fn2_packet_pipeline_is_available(pipeline)) {
+		log(
+			Fn2LogLevel_Error,
+			&format!("[MockDevice] packet pipeline {pipeline} is not available"),
+		);
+		return std::ptr::null_mut();
+	}
+
+	let devices = this.devices();
+	let device = match (idx, serial) {
+		(Some(idx), _) => usize::try_from(idx).ok().and_then(|idx| devices.get(idx)),
+		(None, Some(serial)) => {
+			let serial = std::slice::from_raw_parts(serial.data, serial.len);
+			devices
+				.iter()
+				.find(|device| device.serial.as_bytes() == serial)
+		}
+		(None, None) => devices.first(),
+	};
+	let Some(device) = device.cloned() else {
+		log(
+			Fn2LogLevel_Error,
+			"[Freenect2Impl] requested device is not connected!",
+		);
+		return std::ptr::null_mut();
+	};
+
+	if !lock(&OPEN)
+		.get_or_insert_with(HashSet::new)
+		.insert(device.serial.clone())
+	{
+		log(
+			Fn2LogLevel_Warning,
+			&format!(
+				"[Freenect2Impl] device {} is already be opened!",
+				device.serial
+			),
+		);
+		*already_open = true;
+		return std::ptr::null_mut();
+	}
+
+	log(
+		Fn2LogLevel_Info,
+		&format!("[MockDevice] opened device {}", device.serial),
+	);
+	Box::into_raw(Box::new(Device {
+		device,
+		config: Arc::new(Mutex::new(Fn2DeviceConfig {
+			min_depth: 0.5,
+			max_depth: 4.5,
+			enable_bilateral_filter: true,
+			enable_edge_aware_filter: true,
+		})),
+		listener: Arc::default(),
+		streaming: None,
+		open: true,
+	}))
+	.cast()
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_context_open_device(
+	this_: *mut Fn2Context,
+	idx: c_int,
+	already_open: *mut bool,
+) -> *mut Fn2Device {
+	open(this_, Some(idx), None, None, already_open)
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_context_open_device_by_serial(
+	this_: *mut Fn2Context,
+	serial: Fn2RustyBorrowedString,
+	already_open: *mut bool,
+) -> *mut Fn2Device {
+	open(this_, None, Some(serial), None, already_open)
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_context_open_default_device(
+	this_: *mut Fn2Context,
+	already_open: *mut bool,
+) -> *mut Fn2Device {
+	open(this_, None, None, None, already_open)
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_context_open_device_with_pipeline(
+	this_: *mut Fn2Context,
+	idx: c_int,
+	pipeline: Fn2PacketPipeline,
+	already_open: *mut bool,
+) -> *mut Fn2Device {
+	open(this_, Some(idx), None, Some(pipeline), already_open)
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_context_open_device_by_serial_with_pipeline(
+	this_: *mut Fn2Context,
+	serial: Fn2RustyBorrowedString,
+	pipeline: Fn2PacketPipeline,
+	already_open: *mut bool,
+) -> *mut Fn2Device {
+	open(this_, None, Some(serial), Some(pipeline), already_open)
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_context_open_default_device_with_pipeline(
+	this_: *mut Fn2Context,
+	pipeline: Fn2PacketPipeline,
+	already_open: *mut bool,
+) -> *mut Fn2Device {
+	open(this_, None, None, Some(pipeline), already_open)
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_context_free(this_: *mut Fn2Context) {
+	drop(Box::from_raw(this_.cast::<Context>()));
+}
+
+/// The mock only has the pipelines that libfreenect2 always has.
+#[no_mangle]
+extern "C" fn fn2_packet_pipeline_is_available(pipeline: Fn2PacketPipeline) -> bool {
+	matches!(pipeline, Fn2PacketPipeline_Cpu | Fn2PacketPipeline_Dump)
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_device_get_serial_number(
+	this_: *const Fn2Device,
+	callback: Fn2StringCallback,
+	callback_data: *mut c_void,
+) {
+	let this = &*this_.cast::<Device>();
+	call_string_callback(&this.device.serial, callback, callback_data);
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_device_get_firmware_version(
+	this_: *const Fn2Device,
+	callback: Fn2StringCallback,
+	callback_data: *mut c_void,
+) {
+	let this = &*this_.cast::<Device>();
+	call_string_callback(&this.device.firmware_version, callback, callback_data);
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_device_get_color_camera_params(
+	this_: *const Fn2Device,
+) -> Fn2ColorCameraParams {
+	(*this_.cast::<Device>()).device.color_params
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_device_get_ir_camera_params(this_: *const Fn2Device) -> Fn2IrCameraParams {
+	(*this_.cast::<Device>()).device.ir_params
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_device_set_color_camera_params(
+	this_: *mut Fn2Device,
+	params: Fn2ColorCameraParams,
+) {
+	(*this_.cast::<Device>()).device.color_params = params;
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_device_set_ir_camera_params(
+	this_: *mut Fn2Device,
+	params: Fn2IrCameraParams,
+) {
+	(*this_.cast::<Device>()).device.ir_params = params;
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_device_set_config(this_: *mut Fn2Device, config: Fn2DeviceConfig) {
+	*lock(&(*this_.cast::<Device>()).config) = config;
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_device_set_frame_listener(
+	this_: *mut Fn2Device,
+	callback: Fn2FrameCallback,
+	user_data: *mut c_void,
+	drop_user_data: Option<unsafe extern "C" fn(*mut c_void)>,
+) {
+	let this = &*this_.cast::<Device>();
+	let previous = lock(&this.listener).replace(Listener {
+		callback,
+		user_data: SendPtr(user_data),
+		drop_user_data,
+	});
+	// drop the previous listener after releasing the lock, in case dropping it sets another listener
+	drop(previous);
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_device_start(this_: *mut Fn2Device) -> bool {
+	(*this_.cast::<Device>()).start(true, true)
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_device_start_streams(
+	this_: *mut Fn2Device,
+	rgb: bool,
+	depth: bool,
+) -> bool {
+	(*this_.cast::<Device>()).start(rgb, depth)
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_device_stop(this_: *mut Fn2Device) -> bool {
+	(*this_.cast::<Device>()).stop()
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_device_close(this_: *mut Fn2Device) -> bool {
+	(*this_.cast::<Device>()).close();
+	true
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_device_free(this_: *mut Fn2Device) {
+	let mut this = Box::from_raw(this_.cast::<Device>());
+	this.close();
+	let listener = lock(&this.listener).take();
+	drop(listener);
+}
+
+#[no_mangle]
+extern "C" fn fn2_frame_new(width: usize, height: usize, bytes_per_pixel: usize) -> Fn2Frame {
+	new_frame(
+		width,
+		height,
+		bytes_per_pixel,
+		Fn2FrameFormat_Invalid,
+		vec![0; width * height * bytes_per_pixel],
+	)
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_frame_free(frame: *mut Fn2NativeFrame) {
+	drop(Box::from_raw(frame.cast::<NativeFrame>()));
+}
+
+#[no_mangle]
+extern "C" fn fn2_registration_new(
+	depth_params: Fn2IrCameraParams,
+	color_params: Fn2ColorCameraParams,
+) -> *mut Fn2Registration {
+	Box::into_raw(Box::new(Registration {
+		ir: depth_params,
+		color: color_params,
+	}))
+	.cast()
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_registration_apply_point(
+	this_: *const Fn2Registration,
+	dx: c_int,
+	dy: c_int,
+	dz: f32,
+	cx: *mut f32,
+	cy: *mut f32,
+) {
+	let this = &*this_.cast::<Registration>();
+	#[allow(clippy::cast_precision_loss)]
+	let (x, y) = this.apply_point(dx as f32, dy as f32, dz);
+	*cx = x;
+	*cy = y;
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_registration_apply(
+	this_: *const Fn2Registration,
+	color: Fn2FrameView,
+	depth: Fn2FrameView,
+	undistorted: Fn2FrameView,
+	registered: Fn2FrameView,
+	_enable_filter: bool,
+	bigdepth: *const Fn2FrameView,
+) {
+	let this = &*this_.cast::<Registration>();
+	let color = view_slice::<u32>(&color);
+	let depth = view_slice::<f32>(&depth);
+	let undistorted = view_slice::<f32>(&undistorted);
+	let registered = view_slice::<u32>(&registered);
+	let mut bigdepth = bigdepth
+		.as_ref()
+		.map(|bigdepth| view_slice::<f32>(bigdepth));
+	if let Some(bigdepth) = &mut bigdepth {
+		bigdepth.fill(f32::INFINITY);
+	}
+
+	for (index, &z) in depth.iter().enumerate() {
+		undistorted[index] = z;
+		registered[index] = 0;
+		if z.is_nan() || z <= 0.0 {
+			continue;
+		}
+
+		#[allow(clippy::cast_precision_loss)]
+		let (x, y) = this.apply_point(
+			(index % DEPTH_WIDTH) as f32,
+			(index / DEPTH_WIDTH) as f32,
+			z,
+		);
+		#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+		let (x, y) = (x.round() as isize, y.round() as isize);
+		let (Ok(x), Ok(y)) = (usize::try_from(x), usize::try_from(y)) else {
+			continue;
+		};
+		if x >= COLOR_WIDTH || y >= COLOR_HEIGHT {
+			continue;
+		}
+
+		registered[index] = color[y * COLOR_WIDTH + x];
+		if let Some(bigdepth) = &mut bigdepth {
+			// the first row of bigdepth is padding
+			let pixel = &mut bigdepth[(y + 1) * COLOR_WIDTH + x];
+			*pixel = pixel.min(z);
+		}
+	}
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_registration_undistort_depth(
+	_this_: *const Fn2Registration,
+	depth: Fn2FrameView,
+	undistorted: Fn2FrameView,
+) {
+	view_slice::<f32>(&undistorted).copy_from_slice(view_slice::<f32>(&depth));
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_registration_get_point_xyz(
+	this_: *const Fn2Registration,
+	undistorted: Fn2FrameView,
+	r: c_int,
+	c: c_int,
+	x: *mut f32,
+	y: *mut f32,
+	z: *mut f32,
+) {
+	let this = &*this_.cast::<Registration>();
+	#[allow(clippy::cast_sign_loss)]
+	let depth = view_slice::<f32>(&undistorted)[r as usize * DEPTH_WIDTH + c as usize];
+	if depth.is_nan() || depth <= 0.001 {
+		*x = f32::NAN;
+		*y = f32::NAN;
+		*z = f32::NAN;
+	} else {
+		// like libfreenect2, in meters
+		let depth = depth / 1000.0;
+		#[allow(clippy::cast_precision_loss)]
+		{
+			*x = (c as f32 + 0.5 - this.ir.cx) * depth / this.ir.fx;
+			*y = (r as f32 + 0.5 - this.ir.cy) * depth / this.ir.fy;
+		}
+		*z = depth;
+	}
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_registration_get_point_xyzrgb(
+	this_: *const Fn2Registration,
+	undistorted: Fn2FrameView,
+	registered: Fn2FrameView,
+	r: c_int,
+	c: c_int,
+	x: *mut f32,
+	y: *mut f32,
+	z: *mut f32,
+	rgb: *mut f32,
+) {
+	fn2_registration_get_point_xyz(this_, undistorted, r, c, x, y, z);
+	#[allow(clippy::cast_sign_loss)]
+	let pixel = view_slice::<f32>(&registered)[r as usize * DEPTH_WIDTH + c as usize];
+	*rgb = pixel;
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_registration_free(this_: *mut Fn2Registration) {
+	drop(Box::from_raw(this_.cast::<Registration>()));
+}
+
+#[no_mangle]
+unsafe extern "C" fn fn2_set_logger(vtable: Fn2LoggerVTable, user_data: *mut c_void) {
+	let previous = lock(&LOGGER).replace(Logger {
+		vtable,
+		user_data: SendPtr(user_data),
+	});
+	if let Some(Logger { vtable, user_data }) = previous {
+		if let Some(drop) = vtable.drop {
+			drop(user_data.0);
+		}
+	}
+}
@@ -1,6 +1,10 @@
 #include <cstring>
+#include <mutex>
+#include <unordered_set>
 #include <libfreenect2/libfreenect2.hpp>
 #include <libfreenect2/logger.h>
+#include <libfreenect2/packet_pipeline.h>
+#include <libfreenect2/registration.h>
 
 #include "wrapper.hpp"
 
@@ -86,28 +90,78 @@ static inline Fn2FrameFormat to_ours(libfreenect2::Frame::Format const lib) {
 	}
 }
 
+// Takes ownership of `lib`.
 static inline Fn2Frame to_ours(libfreenect2::Frame* const lib) {
-	auto size = lib->bytes_per_pixel;
-	if (lib->format != libfreenect2::Frame::Format::Raw) {
-		size *= lib->width * lib->height;
-	}
-	unsigned char* data = (unsigned char*)malloc(size);
-	memcpy(data, lib->data, size);
 	return {
-		lib->width, lib->height, lib->bytes_per_pixel, data, lib->timestamp, lib->sequence, lib->exposure, lib->gain, lib->gamma, lib->status, to_ours(lib->format),
+		lib->width, lib->height, lib->bytes_per_pixel, lib->data, lib->timestamp, lib->sequence, lib->exposure, lib->gain, lib->gamma, lib->status, to_ours(lib->format), reinterpret_cast<Fn2NativeFrame*>(lib),
 	};
 }
 
+// The returned frame borrows the data of `our` rather than owning it.
+static inline libfreenect2::Frame from_ours(Fn2FrameView const our) {
+	return libfreenect2::Frame{ our.width, our.height, our.bytes_per_pixel, our.data };
+}
+
+// Returns nullptr if the pipeline is not compiled into libfreenect2.
+static inline libfreenect2::PacketPipeline* make_pipeline(Fn2PacketPipeline const our) {
+	switch (our) {
+		case Cpu:
+			return new libfreenect2::CpuPacketPipeline{};
+		case OpenGl:
+#ifdef LIBFREENECT2_WITH_OPENGL_SUPPORT
+			return new libfreenect2::OpenGLPacketPipeline{};
+#else
+			return nullptr;
+#endif
+		case OpenCl:
+#ifdef LIBFREENECT2_WITH_OPENCL_SUPPORT
+			return new libfreenect2::OpenCLPacketPipeline{};
+#else
+			return nullptr;
+#endif
+		case Cuda:
+#ifdef LIBFREENECT2_WITH_CUDA_SUPPORT
+			return new libfreenect2::CudaPacketPipeline{};
+#else
+			return nullptr;
+#endif
+		case Dump:
+			return new libfreenect2::DumpPacketPipeline{};
+		default:
+			__builtin_unreachable();
+	}
+}
+
 struct ShimFrameListener : libfreenect2::FrameListener {
+	// Held while the callback runs, so that it is not replaced or dropped while libfreenect2's threads are calling it.
+	std::mutex mutex;
 	Fn2FrameCallback callback;
 	void* user_data;
 	void (*drop_user_data)(void*);
 
 	ShimFrameListener(Fn2FrameCallback const callback, void* const user_data, void (*const drop_user_data)(void*)) : callback(callback), user_data(user_data), drop_user_data(drop_user_data) {}
 
+	// Returns true because ownership of the frame is passed on to the callback.
 	virtual bool onNewFrame(libfreenect2::Frame::Type const type, libfreenect2::Frame* const frame) override {
+		std::lock_guard<std::mutex> const lock{ mutex };
 		callback(user_data, to_ours(frame), to_ours(type));
-		return false;
+		return true;
+	}
+
+	// Replaces the callback, waiting for any call to the previous one to finish, then drops the previous user data.
+	void replace(Fn2FrameCallback const new_callback, void* const new_user_data, void (*const new_drop_user_data)(void*)) {
+		void* old_user_data;
+		void (*old_drop_user_data)(void*);
+		{
+			std::lock_guard<std::mutex> const lock{ mutex };
+			callback = new_callback;
+			old_user_data = user_data;
+			user_data = new_user_data;
+			old_drop_user_data = drop_user_data;
+			drop_user_data = new_drop_user_data;
+		}
+		// outside the lock, as dropping the user data may wait for frames to be consumed
+		old_drop_user_data(old_user_data);
 	}
 
 	~ShimFrameListener() {
@@ -115,11 +169,20 @@ struct ShimFrameListener : libfreenect2::FrameListener {
 	}
 };
 
+// The devices that are wrapped in an `Fn2Device`.
+// libfreenect2 returns the existing device when opening one that is already open, which must not be wrapped twice.
+static std::mutex wrapped_devices_mutex;
+static std::unordered_set<libfreenect2::Freenect2Device*> wrapped_devices;
+
 struct Fn2Device {
 	libfreenect2::Freenect2Device* inner;
 	ShimFrameListener* listener = nullptr;
 
 	~Fn2Device() {
+		{
+			std::lock_guard<std::mutex> const lock{ wrapped_devices_mutex };
+			wrapped_devices.erase(inner);
+		}
 		delete inner;
 		if (listener) {
 			delete listener;
@@ -127,6 +190,18 @@ struct Fn2Device {
 	}
 };
 
+static Fn2Device* wrap_device(libfreenect2::Freenect2Device* const inner, bool* const already_open) {
+	if (!inner) {
+		return nullptr;
+	}
+	std::lock_guard<std::mutex> const lock{ wrapped_devices_mutex };
+	if (!wrapped_devices.insert(inner).second) {
+		*already_open = true;
+		return nullptr;
+	}
+	return new Fn2Device{ inner };
+}
+
 struct Fn2Context {
 	libfreenect2::Freenect2 inner;
 
@@ -155,38 +230,90 @@ void fn2_context_get_default_device_serial_number(Fn2Context const* const this_,
 	callback(callback_data, borrow_string(cxx));
 }
 
-Fn2Device* fn2_context_open_device(Fn2Context* const this_, int const idx) {
+Fn2Device* fn2_context_open_device(Fn2Context* const this_, int const idx, bool* const already_open) {
+	*already_open = false;
 	auto* const inner = this_->inner.openDevice(idx);
-	if (inner) {
-		return new Fn2Device{ inner };
-	} else {
-		return nullptr;
-	}
+	return wrap_device(inner, already_open);
 }
 
-Fn2Device* fn2_context_open_device_by_serial(Fn2Context* const this_, Fn2RustyBorrowedString const serial) {
+Fn2Device* fn2_context_open_device_by_serial(Fn2Context* const this_, Fn2RustyBorrowedString const serial, bool* const already_open) {
+	*already_open = false;
 	std::string serial_cxx{ reinterpret_cast<char const*>(serial.data), serial.len };
 	auto* const inner = this_->inner.openDevice(serial_cxx);
-	if (inner) {
-		return new Fn2Device{ inner };
-	} else {
+	return wrap_device(inner, already_open);
+}
+
+Fn2Device* fn2_context_open_default_device(Fn2Context* const this_, bool* const already_open) {
+	*already_open = false;
+	auto* const inner = this_->inner.openDefaultDevice();
+	return wrap_device(inner, already_open);
+}
+
+// In the following functions, libfreenect2 takes ownership of the pipeline, even if opening fails.
+
+Fn2Device* fn2_context_open_device_with_pipeline(Fn2Context* const this_, int const idx, Fn2PacketPipeline const pipeline, bool* const already_open) {
+	*already_open = false;
+	auto* const lib_pipeline = make_pipeline(pipeline);
+	if (!lib_pipeline) {
 		return nullptr;
 	}
+	auto* const inner = this_->inner.openDevice(idx, lib_pipeline);
+	return wrap_device(inner, already_open);
 }
 
-Fn2Device* fn2_context_open_default_device(Fn2Context* const this_) {
-	auto* const inner = this_->inner.openDefaultDevice();
-	if (inner) {
-		return new Fn2Device{ inner };
-	} else {
+Fn2Device* fn2_context_open_device_by_serial_with_pipeline(Fn2Context* const this_, Fn2RustyBorrowedString const serial, Fn2PacketPipeline const pipeline, bool* const already_open) {
+	*already_open = false;
+	auto* const lib_pipeline = make_pipeline(pipeline);
+	if (!lib_pipeline) {
+		return nullptr;
+	}
+	std::string serial_cxx{ reinterpret_cast<char const*>(serial.data), serial.len };
+	auto* const inner = this_->inner.openDevice(serial_cxx, lib_pipeline);
+	return wrap_device(inner, already_open);
+}
+
+Fn2Device* fn2_context_open_default_device_with_pipeline(Fn2Context* const this_, Fn2PacketPipeline const pipeline, bool* const already_open) {
+	*already_open = false;
+	auto* const lib_pipeline = make_pipeline(pipeline);
+	if (!lib_pipeline) {
 		return nullptr;
 	}
+	auto* const inner = this_->inner.openDefaultDevice(lib_pipeline);
+	return wrap_device(inner, already_open);
 }
 
 void fn2_context_free(Fn2Context* const this_) {
 	delete this_;
 }
 
+bool fn2_packet_pipeline_is_available(Fn2PacketPipeline const pipeline) {
+	switch (pipeline) {
+		case Cpu:
+		case Dump:
+			return true;
+		case OpenGl:
+#ifdef LIBFREENECT2_WITH_OPENGL_SUPPORT
+			return true;
+#else
+			return false;
+#endif
+		case OpenCl:
+#ifdef LIBFREENECT2_WITH_OPENCL_SUPPORT
+			return true;
+#else
+			return false;
+#endif
+		case Cuda:
+#ifdef LIBFREENECT2_WITH_CUDA_SUPPORT
+			return true;
+#else
+			return false;
+#endif
+		default:
+			__builtin_unreachable();
+	}
+}
+
 void fn2_device_get_serial_number(Fn2Device const* const this_, Fn2StringCallback const callback, void* const callback_data) {
 	auto const cxx = this_->inner->getSerialNumber();
 	callback(callback_data, borrow_string(cxx));
@@ -223,9 +350,7 @@ void fn2_device_set_frame_listener(Fn2Device* const this_, void (*const callback
 		this_->inner->setColorFrameListener(this_->listener);
 		this_->inner->setIrAndDepthFrameListener(this_->listener);
 	} else {
-		this_->listener->callback = callback;
-		this_->listener->user_data = user_data;
-		this_->listener->drop_user_data = drop_user_data;
+		this_->listener->replace(callback, user_data, drop_user_data);
 	}
 }
 
@@ -249,6 +374,63 @@ void fn2_device_free(Fn2Device* const this_) {
 	delete this_;
 }
 
+Fn2Frame fn2_frame_new(size_t const width, size_t const height, size_t const bytes_per_pixel) {
+	auto* const lib = new libfreenect2::Frame{ width, height, bytes_per_pixel };
+	lib->format = libfreenect2::Frame::Format::Invalid;
+	memset(lib->data, 0, width * height * bytes_per_pixel);
+	return to_ours(lib);
+}
+
+void fn2_frame_free(Fn2NativeFrame* const frame) {
+	delete reinterpret_cast<libfreenect2::Frame*>(frame);
+}
+
+struct Fn2Registration {
+	libfreenect2::Registration inner;
+};
+
+Fn2Registration* fn2_registration_new(Fn2IrCameraParams const depth_params, Fn2ColorCameraParams const color_params) {
+	return new Fn2Registration{ { from_ours(depth_params), from_ours(color_params) } };
+}
+
+void fn2_registration_apply_point(Fn2Registration const* const this_, int const dx, int const dy, float const dz, float* const cx, float* const cy) {
+	this_->inner.apply(dx, dy, dz, *cx, *cy);
+}
+
+void fn2_registration_apply(Fn2Registration const* const this_, Fn2FrameView const color, Fn2FrameView const depth, Fn2FrameView const undistorted, Fn2FrameView const registered, bool const enable_filter, Fn2FrameView const* const bigdepth) {
+	auto const lib_color = from_ours(color);
+	auto const lib_depth = from_ours(depth);
+	auto lib_undistorted = from_ours(undistorted);
+	auto lib_registered = from_ours(registered);
+	if (bigdepth) {
+		auto lib_bigdepth = from_ours(*bigdepth);
+		this_->inner.apply(&lib_color, &lib_depth, &lib_undistorted, &lib_registered, enable_filter, &lib_bigdepth);
+	} else {
+		this_->inner.apply(&lib_color, &lib_depth, &lib_undistorted, &lib_registered, enable_filter);
+	}
+}
+
+void fn2_registration_undistort_depth(Fn2Registration const* const this_, Fn2FrameView const depth, Fn2FrameView const undistorted) {
+	auto const lib_depth = from_ours(depth);
+	auto lib_undistorted = from_ours(undistorted);
+	this_->inner.undistortDepth(&lib_depth, &lib_undistorted);
+}
+
+void fn2_registration_get_point_xyz(Fn2Registration const* const this_, Fn2FrameView const undistorted, int const r, int const c, float* const x, float* const y, float* const z) {
+	auto const lib_undistorted = from_ours(undistorted);
+	this_->inner.getPointXYZ(&lib_undistorted, r, c, *x, *y, *z);
+}
+
+void fn2_registration_get_point_xyzrgb(Fn2Registration const* const this_, Fn2FrameView const undistorted, Fn2FrameView const registered, int const r, int const c, float* const x, float* const y, float* const z, float* const rgb) {
+	auto const lib_undistorted = from_ours(undistorted);
+	auto const lib_registered = from_ours(registered);
+	this_->inner.getPointXYZRGB(&lib_undistorted, &lib_registered, r, c, *x, *y, *z, *rgb);
+}
+
+void fn2_registration_free(Fn2Registration* const this_) {
+	delete this_;
+}
+
 struct Logger : libfreenect2::Logger {
 	Fn2LoggerVTable vtable;
 	void* user_data;
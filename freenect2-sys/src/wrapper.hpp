@@ -75,11 +75,14 @@ enum Fn2FrameFormat {
 	Gray,
 };
 
+// A `libfreenect2::Frame`, which owns the frame's data.
+struct Fn2NativeFrame;
+
 struct Fn2Frame {
 	size_t width;
 	size_t height;
 	size_t bytes_per_pixel;
-	unsigned char* data;  // owned
+	unsigned char* data;  // owned by `native`
 	uint32_t timestamp;
 	uint32_t sequence;
 	float exposure;
@@ -87,10 +90,27 @@ struct Fn2Frame {
 	float gamma;
 	uint32_t status;
 	Fn2FrameFormat format;
+	Fn2NativeFrame* native;  // owned; free with `fn2_frame_free`
 };
 
 typedef void (*Fn2FrameCallback)(void*, Fn2Frame, Fn2FrameType);
 
+// The image data of a frame, borrowed for the duration of a call.
+struct Fn2FrameView {
+	size_t width;
+	size_t height;
+	size_t bytes_per_pixel;
+	unsigned char* data;
+};
+
+enum Fn2PacketPipeline {
+	Cpu,
+	OpenGl,
+	OpenCl,
+	Cuda,
+	Dump,
+};
+
 enum Fn2LogLevel {
 	None,
 	Error,
@@ -107,16 +127,24 @@ struct Fn2LoggerVTable {
 
 struct Fn2Device;
 struct Fn2Context;
+struct Fn2Registration;
 
 Fn2Context* fn2_context_new();
 int fn2_context_enumerate_devices(Fn2Context* this_);
 void fn2_context_get_device_serial_number(Fn2Context const* this_, int idx, Fn2StringCallback callback, void* callback_data);
 void fn2_context_get_default_device_serial_number(Fn2Context const* this_, Fn2StringCallback callback, void* callback_data);
-Fn2Device* fn2_context_open_device(Fn2Context* this_, int idx);
-Fn2Device* fn2_context_open_device_by_serial(Fn2Context* this_, Fn2RustyBorrowedString serial);
-Fn2Device* fn2_context_open_default_device(Fn2Context* this_);
+// The following functions return nullptr if opening fails.
+// `already_open` is set to whether that is because the device is already open.
+Fn2Device* fn2_context_open_device(Fn2Context* this_, int idx, bool* already_open);
+Fn2Device* fn2_context_open_device_by_serial(Fn2Context* this_, Fn2RustyBorrowedString serial, bool* already_open);
+Fn2Device* fn2_context_open_default_device(Fn2Context* this_, bool* already_open);
+Fn2Device* fn2_context_open_device_with_pipeline(Fn2Context* this_, int idx, Fn2PacketPipeline pipeline, bool* already_open);
+Fn2Device* fn2_context_open_device_by_serial_with_pipeline(Fn2Context* this_, Fn2RustyBorrowedString serial, Fn2PacketPipeline pipeline, bool* already_open);
+Fn2Device* fn2_context_open_default_device_with_pipeline(Fn2Context* this_, Fn2PacketPipeline pipeline, bool* already_open);
 void fn2_context_free(Fn2Context* this_);
 
+bool fn2_packet_pipeline_is_available(Fn2PacketPipeline pipeline);
+
 void fn2_device_get_serial_number(Fn2Device const* this_, Fn2StringCallback callback, void* callback_data);
 void fn2_device_get_firmware_version(Fn2Device const* this_, Fn2StringCallback callback, void* callback_data);
 Fn2ColorCameraParams fn2_device_get_color_camera_params(Fn2Device const* this_);
@@ -124,6 +152,8 @@ Fn2IrCameraParams fn2_device_get_ir_camera_params(Fn2Device const* this_);
 void fn2_device_set_color_camera_params(Fn2Device* this_, Fn2ColorCameraParams params);
 void fn2_device_set_ir_camera_params(Fn2Device* this_, Fn2IrCameraParams params);
 void fn2_device_set_config(Fn2Device* this_, Fn2DeviceConfig config);
+// Replaces any previous listener, passing its user data to its `drop_user_data`.
+// This is safe while streams are running: it waits for any call to the previous listener to return first.
 void fn2_device_set_frame_listener(Fn2Device* this_, Fn2FrameCallback callback, void* user_data, void drop_user_data(void*));
 bool fn2_device_start(Fn2Device* this_);
 bool fn2_device_start_streams(Fn2Device* this_, bool rgb, bool depth);
@@ -131,5 +161,18 @@ bool fn2_device_stop(Fn2Device* this_);
 bool fn2_device_close(Fn2Device* this_);
 void fn2_device_free(Fn2Device* this_);
 
+// The data of the new frame is zeroed and its format is `Invalid`.
+Fn2Frame fn2_frame_new(size_t width, size_t height, size_t bytes_per_pixel);
+void fn2_frame_free(Fn2NativeFrame* frame);
+
+Fn2Registration* fn2_registration_new(Fn2IrCameraParams depth_params, Fn2ColorCameraParams color_params);
+void fn2_registration_apply_point(Fn2Registration const* this_, int dx, int dy, float dz, float* cx, float* cy);
+// `bigdepth` may be null.
+void fn2_registration_apply(Fn2Registration const* this_, Fn2FrameView color, Fn2FrameView depth, Fn2FrameView undistorted, Fn2FrameView registered, bool enable_filter, Fn2FrameView const* bigdepth);
+void fn2_registration_undistort_depth(Fn2Registration const* this_, Fn2FrameView depth, Fn2FrameView undistorted);
+void fn2_registration_get_point_xyz(Fn2Registration const* this_, Fn2FrameView undistorted, int r, int c, float* x, float* y, float* z);
+void fn2_registration_get_point_xyzrgb(Fn2Registration const* this_, Fn2FrameView undistorted, Fn2FrameView registered, int r, int c, float* x, float* y, float* z, float* rgb);
+void fn2_registration_free(Fn2Registration* this_);
+
 void fn2_set_logger(Fn2LoggerVTable vtable, void* user_data);
 }
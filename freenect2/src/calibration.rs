@@ -0,0 +1,167 @@
+//! Provides [`Calibration`], which saves the camera parameters of a device to a file and loads them back.
+//!
+//! Each Kinect is calibrated at the factory.
+//! Saving the calibration makes it possible to keep it under version control for each sensor, to correct it, and to process frames without the device that captured them.
+//!
+//! Calibrations are stored as TOML, or as JSON if the file name ends in `.json`.
+//! For example:
+//!
+//! ```toml
+//! serial_number = "012345678912"
+//!
+//! [ir_camera_params]
+//! fx = 365.456
+//! fy = 365.456
+//! # ...
+//!
+//! [color_camera_params]
+//! fx = 1081.372
+//! # ...
+//! ```
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::device::{ColorCameraParams, Device, Error, IrCameraParams};
+
+/// Errors that can occur while loading or saving a [`Calibration`].
+#[derive(Debug)]
+pub enum CalibrationError {
+	/// The file could not be read or written.
+	Io(std::io::Error),
+	/// The file is not valid JSON or does not contain a calibration, or the calibration could not be serialized as JSON.
+	Json(serde_json::Error),
+	/// The file is not valid TOML, or does not contain a calibration.
+	Toml(toml::de::Error),
+	/// The calibration could not be serialized as TOML.
+	SerializeToml(toml::ser::Error),
+}
+
+impl std::fmt::Display for CalibrationError {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Io(error) => write!(formatter, "{error}"),
+			Self::Json(error) => write!(formatter, "invalid calibration: {error}"),
+			Self::Toml(error) => write!(formatter, "invalid calibration: {error}"),
+			Self::SerializeToml(error) => write!(formatter, "could not serialize calibration: {error}"),
+		}
+	}
+}
+
+impl std::error::Error for CalibrationError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::Io(error) => Some(error),
+			Self::Json(error) => Some(error),
+			Self::Toml(error) => Some(error),
+			Self::SerializeToml(error) => Some(error),
+		}
+	}
+}
+
+/// The camera parameters of a device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Calibration {
+	/// The serial number of the device that the parameters belong to.
+	pub serial_number: String,
+	/// The parameters of the IR camera.
+	pub ir_camera_params: IrCameraParams,
+	/// The parameters of the color camera.
+	pub color_camera_params: ColorCameraParams,
+}
+
+impl Calibration {
+	/// Get the current camera parameters of `device`, which are its factory calibration unless they have been replaced.
+	#[must_use]
+	pub fn for_device(device: &Device) -> Self {
+		Self {
+			serial_number: device.serial_number(),
+			ir_camera_params: device.ir_camera_params(),
+			color_camera_params: device.color_camera_params(),
+		}
+	}
+
+	/// Replace the camera parameters of `device` with these.
+	///
+	/// The serial number is not checked against the device's.
+	///
+	/// # Errors
+	///
+	/// Fails if streams are running, in which case the camera cannot be reconfigured.
+	pub fn apply(&self, device: &mut Device) -> Result<(), Error> {
+		device.set_ir_camera_params(self.ir_camera_params)?;
+		device.set_color_camera_params(self.color_camera_params)
+	}
+
+	/// Parse a calibration from JSON.
+	///
+	/// # Errors
+	///
+	/// Fails if `json` is not a valid calibration.
+	pub fn from_json(json: &str) -> Result<Self, CalibrationError> {
+		serde_json::from_str(json).map_err(CalibrationError::Json)
+	}
+
+	/// Serialize the calibration as pretty-printed JSON.
+	///
+	/// # Errors
+	///
+	/// Fails if a parameter cannot be represented in JSON.
+	pub fn to_json(&self) -> Result<String, CalibrationError> {
+		serde_json::to_string_pretty(self).map_err(CalibrationError::Json)
+	}
+
+	/// Parse a calibration from TOML.
+	///
+	/// # Errors
+	///
+	/// Fails if `toml` is not a valid calibration.
+	pub fn from_toml(toml: &str) -> Result<Self, CalibrationError> {
+		toml::from_str(toml).map_err(CalibrationError::Toml)
+	}
+
+	/// Serialize the calibration as TOML.
+	///
+	/// # Errors
+	///
+	/// Fails if a parameter cannot be represented in TOML.
+	pub fn to_toml(&self) -> Result<String, CalibrationError> {
+		toml::to_string(self).map_err(CalibrationError::SerializeToml)
+	}
+
+	/// Load a calibration from `path`, which is read as JSON if its extension is `json` and as TOML otherwise.
+	///
+	/// # Errors
+	///
+	/// Fails if the file cannot be read or is not a valid calibration.
+	pub fn load(path: &Path) -> Result<Self, CalibrationError> {
+		let raw = std::fs::read_to_string(path).map_err(CalibrationError::Io)?;
+		if is_json(path) {
+			Self::from_json(&raw)
+		} else {
+			Self::from_toml(&raw)
+		}
+	}
+
+	/// Save the calibration to `path`, as JSON if its extension is `json` and as TOML otherwise.
+	///
+	/// # Errors
+	///
+	/// Fails if the calibration cannot be serialized or the file cannot be written.
+	pub fn save(&self, path: &Path) -> Result<(), CalibrationError> {
+		let raw = if is_json(path) {
+			self.to_json()?
+		} else {
+			self.to_toml()?
+		};
+		std::fs::write(path, raw).map_err(CalibrationError::Io)
+	}
+}
+
+fn is_json(path: &Path) -> bool {
+	path
+		.extension()
+		.is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
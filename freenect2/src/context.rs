@@ -5,6 +5,89 @@ use std::ptr::{addr_of_mut, NonNull};
 use freenect2_sys as sys;
 
 use crate::device::Device;
+use crate::PacketPipeline;
+
+/// Errors that can occur when opening a device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpenError {
+	/// The requested [`PacketPipeline`] is not compiled into the installed libfreenect2.
+	PipelineUnavailable(PacketPipeline),
+	/// The default device was requested, but no devices were discovered.
+	NoDevices,
+	/// No device was discovered with the requested index.
+	InvalidIndex {
+		/// The requested index.
+		index: u32,
+		/// The number of devices that were discovered.
+		num_devices: u32,
+	},
+	/// No device was discovered with the requested serial number.
+	NoSuchSerial(String),
+	/// The device is already open.
+	AlreadyOpen,
+	/// The device could not be opened, for example because of a USB error or because the current user does not have permission to access it.
+	Failed {
+		/// The warnings and errors that libfreenect2 logged while trying to open the device, which usually explain why it failed.
+		log: Vec<String>,
+	},
+}
+
+impl std::fmt::Display for OpenError {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::PipelineUnavailable(pipeline) => write!(
+				formatter,
+				"the {pipeline} packet pipeline is not compiled into the installed libfreenect2"
+			),
+			Self::NoDevices => formatter.write_str("no devices found"),
+			Self::InvalidIndex { index, num_devices } => write!(
+				formatter,
+				"there is no device {index}; found {num_devices} device{}",
+				if *num_devices == 1 { "" } else { "s" }
+			),
+			Self::NoSuchSerial(serial) => {
+				write!(formatter, "no device with serial number {serial} found")
+			}
+			Self::AlreadyOpen => formatter.write_str("the device is already open"),
+			Self::Failed { log } => {
+				formatter.write_str("could not open device")?;
+				if !log.is_empty() {
+					write!(formatter, ": {}", log.join("; "))?;
+				}
+				if log
+					.iter()
+					.any(|message| message.contains("LIBUSB_ERROR_ACCESS"))
+				{
+					formatter.write_str(
+						" (check that the current user may access the device, for example by installing libfreenect2's udev rules)",
+					)?;
+				}
+				Ok(())
+			}
+		}
+	}
+}
+
+impl std::error::Error for OpenError {}
+
+/// A device that was discovered during enumeration.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceInfo {
+	/// The index of the device, as accepted by [`Context::open_device`].
+	///
+	/// Indices are only valid until the devices are enumerated again with [`Context::refresh`].
+	pub index: u32,
+	/// The serial number of the device, as accepted by [`Context::open_device_by_serial`].
+	pub serial: String,
+}
+
+/// Which device to open.
+#[derive(Debug, Clone, Copy)]
+enum Target<'a> {
+	Index(u32),
+	Serial(&'a str),
+	Default,
+}
 
 /// The context used to discover and open devices.
 #[derive(Debug)]
@@ -54,10 +137,32 @@ impl Context {
 			inner,
 			num_devices: 0,
 		};
-		ret.enumerate_devices();
+		ret.refresh();
 		ret
 	}
 
+	/// Enumerate the devices again, to discover devices that have been connected or disconnected since the last enumeration.
+	///
+	/// Returns the new number of devices.
+	pub fn refresh(&mut self) -> u32 {
+		self.num_devices = unsafe { sys::fn2_context_enumerate_devices(self.inner.as_ptr()) }
+			.try_into()
+			.unwrap();
+		self.num_devices
+	}
+
+	/// Get the devices that were discovered during the last enumeration.
+	#[must_use]
+	pub fn devices(&self) -> Vec<DeviceInfo> {
+		(0..self.num_devices)
+			.filter_map(|index| {
+				self
+					.device_serial_number(index)
+					.map(|serial| DeviceInfo { index, serial })
+			})
+			.collect()
+	}
+
 	/// Gets the number of devices that were discovered during enumeration.
 	#[must_use]
 	pub fn num_devices(&self) -> u32 {
@@ -99,54 +204,149 @@ impl Context {
 
 	/// Open a device by its index.
 	///
-	/// Returns `None` if the device index is invalid (`>= num_devices()`), or if the device is already open.
-	pub fn open_device(&mut self, device_idx: u32) -> Option<Device> {
-		let raw =
-			unsafe { sys::fn2_context_open_device(self.inner.as_ptr(), device_idx.try_into().ok()?) };
-		if raw.is_null() {
-			None
-		} else {
-			Some(unsafe { Device::from_raw(raw, false) })
-		}
+	/// # Errors
+	///
+	/// Fails if the device index is invalid (`>= num_devices()`), if the device is already open, or if it cannot be opened.
+	pub fn open_device(&mut self, device_idx: u32) -> Result<Device, OpenError> {
+		self.open(Target::Index(device_idx), None)
 	}
 
 	/// Open the default device.
 	///
-	/// Returns `None` if no devices were discovered and thus there is no default device, or if the device is already open.
-	pub fn open_default_device(&mut self) -> Option<Device> {
-		let raw = unsafe { sys::fn2_context_open_default_device(self.inner.as_ptr()) };
-		if raw.is_null() {
-			None
-		} else {
-			Some(unsafe { Device::from_raw(raw, false) })
-		}
+	/// # Errors
+	///
+	/// Fails if no devices were discovered and thus there is no default device, if the device is already open, or if it cannot be opened.
+	pub fn open_default_device(&mut self) -> Result<Device, OpenError> {
+		self.open(Target::Default, None)
 	}
 
 	/// Opens a device based on its serial number.
 	///
-	/// Returns `None` if there is no device by that serial number, or if the device is already open.
-	pub fn open_device_by_serial(&mut self, serial: &str) -> Option<Device> {
-		let raw = unsafe {
-			sys::fn2_context_open_device_by_serial(
-				self.inner.as_ptr(),
-				sys::Fn2RustyBorrowedString {
+	/// # Errors
+	///
+	/// Fails if there is no device by that serial number, if the device is already open, or if it cannot be opened.
+	pub fn open_device_by_serial(&mut self, serial: &str) -> Result<Device, OpenError> {
+		self.open(Target::Serial(serial), None)
+	}
+
+	/// Open a device by its index, decoding its packets with `pipeline`.
+	///
+	/// # Errors
+	///
+	/// Fails if `pipeline` is not available, if the device index is invalid (`>= num_devices()`), if the device is already open, or if it cannot be opened.
+	pub fn open_device_with_pipeline(
+		&mut self,
+		device_idx: u32,
+		pipeline: PacketPipeline,
+	) -> Result<Device, OpenError> {
+		self.open(Target::Index(device_idx), Some(pipeline))
+	}
+
+	/// Open the default device, decoding its packets with `pipeline`.
+	///
+	/// # Errors
+	///
+	/// Fails if `pipeline` is not available, if no devices were discovered and thus there is no default device, if the device is already open, or if it cannot be opened.
+	pub fn open_default_device_with_pipeline(
+		&mut self,
+		pipeline: PacketPipeline,
+	) -> Result<Device, OpenError> {
+		self.open(Target::Default, Some(pipeline))
+	}
+
+	/// Open a device based on its serial number, decoding its packets with `pipeline`.
+	///
+	/// # Errors
+	///
+	/// Fails if `pipeline` is not available, if there is no device by that serial number, if the device is already open, or if it cannot be opened.
+	pub fn open_by_serial_with_pipeline(
+		&mut self,
+		serial: &str,
+		pipeline: PacketPipeline,
+	) -> Result<Device, OpenError> {
+		self.open(Target::Serial(serial), Some(pipeline))
+	}
+
+	/// Check that `target` exists and that `pipeline` is available, then open the device, decoding its packets with `pipeline` or libfreenect2's default.
+	fn open(
+		&mut self,
+		target: Target<'_>,
+		pipeline: Option<PacketPipeline>,
+	) -> Result<Device, OpenError> {
+		if let Some(pipeline) = pipeline.filter(|pipeline| !pipeline.is_available()) {
+			return Err(OpenError::PipelineUnavailable(pipeline));
+		}
+
+		let this = self.inner.as_ptr();
+		let mut already_open = false;
+		let already_open_ptr = addr_of_mut!(already_open);
+		let (raw, log) = match target {
+			Target::Index(index) => {
+				let invalid = OpenError::InvalidIndex {
+					index,
+					num_devices: self.num_devices,
+				};
+				if index >= self.num_devices {
+					return Err(invalid);
+				}
+				let index = index.try_into().map_err(|_| invalid)?;
+				crate::logger::capture(|| unsafe {
+					match pipeline {
+						Some(pipeline) => sys::fn2_context_open_device_with_pipeline(
+							this,
+							index,
+							pipeline.into(),
+							already_open_ptr,
+						),
+						None => sys::fn2_context_open_device(this, index, already_open_ptr),
+					}
+				})
+			}
+			Target::Serial(serial) => {
+				if !self.devices().iter().any(|device| device.serial == serial) {
+					return Err(OpenError::NoSuchSerial(serial.to_owned()));
+				}
+				let serial = sys::Fn2RustyBorrowedString {
 					data: serial.as_ptr(),
 					len: serial.len(),
-				},
-			)
+				};
+				crate::logger::capture(|| unsafe {
+					match pipeline {
+						Some(pipeline) => sys::fn2_context_open_device_by_serial_with_pipeline(
+							this,
+							serial,
+							pipeline.into(),
+							already_open_ptr,
+						),
+						None => sys::fn2_context_open_device_by_serial(this, serial, already_open_ptr),
+					}
+				})
+			}
+			Target::Default => {
+				if self.num_devices == 0 {
+					return Err(OpenError::NoDevices);
+				}
+				crate::logger::capture(|| unsafe {
+					match pipeline {
+						Some(pipeline) => sys::fn2_context_open_default_device_with_pipeline(
+							this,
+							pipeline.into(),
+							already_open_ptr,
+						),
+						None => sys::fn2_context_open_default_device(this, already_open_ptr),
+					}
+				})
+			}
 		};
-		if raw.is_null() {
-			None
+
+		if !raw.is_null() {
+			Ok(unsafe { Device::from_raw(raw, false) })
+		} else if already_open {
+			Err(OpenError::AlreadyOpen)
 		} else {
-			Some(unsafe { Device::from_raw(raw, false) })
+			Err(OpenError::Failed { log })
 		}
 	}
-
-	fn enumerate_devices(&mut self) {
-		self.num_devices = unsafe { sys::fn2_context_enumerate_devices(self.inner.as_ptr()) }
-			.try_into()
-			.unwrap();
-	}
 }
 
 impl Drop for Context {
@@ -6,6 +6,8 @@ use freenect2_sys as sys;
 /// Extrinsic parameters are used in a formula to map coordinates in the depth camera to the color camera.
 /// They cannot be used for matrix transformation.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
 pub struct ColorCameraParams {
 	/// Focal length x (pixel)
 	pub fx: f32,
@@ -0,0 +1,82 @@
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::{Frame, FrameType};
+
+/// Counts of the frames received from one stream of a device, to detect problems such as unreliable USB transfers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FrameCounts {
+	/// The number of frames received.
+	pub received: u64,
+	/// The number of frames received whose [status](Frame::status) is not [`Ok`](crate::FrameStatus::Ok), because libfreenect2 could not decode them.
+	pub errors: u64,
+	/// The number of frames missing between those received, according to their sequence numbers.
+	///
+	/// libfreenect2 drops frames whose USB packets are incomplete, as well as frames that arrive while the previous one is still being processed, for example because the frame listener is slow.
+	pub skipped: u64,
+}
+
+#[derive(Debug, Default)]
+struct Stream {
+	counts: FrameCounts,
+	/// The sequence number of the last frame received, or `None` if there has not been one since streams were last started.
+	last_sequence: Option<u32>,
+}
+
+impl Stream {
+	fn count(&mut self, frame: &Frame) {
+		self.counts.received += 1;
+		if frame.errors_occurred() {
+			self.counts.errors += 1;
+		}
+		if let Some(last) = self.last_sequence {
+			// sequence numbers wrap around; a frame from before the last one is not a gap
+			let gap = frame.sequence().wrapping_sub(last).wrapping_sub(1);
+			if gap < u32::MAX / 2 {
+				self.counts.skipped += u64::from(gap);
+			}
+		}
+		self.last_sequence = Some(frame.sequence());
+	}
+}
+
+/// The counts of each stream, shared between a device and its frame listener.
+#[derive(Debug, Default)]
+pub(super) struct Counters {
+	color: Mutex<Stream>,
+	ir: Mutex<Stream>,
+	depth: Mutex<Stream>,
+}
+
+impl Counters {
+	fn stream(&self, ty: FrameType) -> MutexGuard<'_, Stream> {
+		match ty {
+			FrameType::Color => &self.color,
+			FrameType::Ir => &self.ir,
+			FrameType::Depth => &self.depth,
+		}
+		.lock()
+		.unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// Count a frame that was received.
+	pub(super) fn count(&self, frame: &Frame, ty: FrameType) {
+		self.stream(ty).count(frame);
+	}
+
+	pub(super) fn get(&self, ty: FrameType) -> FrameCounts {
+		self.stream(ty).counts
+	}
+
+	/// Forget the sequence numbers of the last frames once streams have stopped, since they may start again.
+	pub(super) fn stopped(&self) {
+		for ty in [FrameType::Color, FrameType::Ir, FrameType::Depth] {
+			self.stream(ty).last_sequence = None;
+		}
+	}
+
+	pub(super) fn reset(&self) {
+		for ty in [FrameType::Color, FrameType::Ir, FrameType::Depth] {
+			*self.stream(ty) = Stream::default();
+		}
+	}
+}
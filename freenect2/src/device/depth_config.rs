@@ -2,6 +2,8 @@ use freenect2_sys as sys;
 
 /// Depth processing configuration.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
 pub struct DepthConfig {
 	/// Clip at this minimum depth, in meters.
 	pub min_depth: f32,
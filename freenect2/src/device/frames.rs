@@ -0,0 +1,333 @@
+//! Provides [`Frames`] and, with the `futures` feature, [`FrameStream`]: bounded queues that a frame listener fills on libfreenect2's threads, to be received from elsewhere.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{RecvTimeoutError, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::Waker;
+use std::time::{Duration, Instant};
+
+use crate::{Frame, FrameType};
+
+/// What to do with a new frame when the queue of a [`Frames`] is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DropPolicy {
+	/// Discard the oldest queued frame to make room, so that the receiver always gets the most recent frames.
+	DropOldest,
+	/// Discard the new frame.
+	DropNewest,
+	/// Wait until the receiver makes room.
+	///
+	/// This blocks libfreenect2's processing thread, so the device may drop packets instead; those are not counted.
+	///
+	/// Stopping the device or replacing its frame listener waits for the blocked thread, so while the queue is full, drop the receiver before doing either, or they never return.
+	Block,
+}
+
+/// Tunable parameters for [`Device::frames`](super::Device::frames).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueConfig {
+	/// The maximum number of frames waiting to be received.
+	pub capacity: usize,
+	/// What to do with frames that arrive when the queue is full.
+	pub policy: DropPolicy,
+}
+
+impl Default for QueueConfig {
+	/// A queue of four frames that drops the oldest frame when full, which keeps latency low.
+	fn default() -> Self {
+		Self {
+			capacity: 4,
+			policy: DropPolicy::DropOldest,
+		}
+	}
+}
+
+#[derive(Debug)]
+struct State {
+	queue: VecDeque<(Frame, FrameType)>,
+	/// Whether the frame listener has been dropped, so that no more frames will arrive.
+	disconnected: bool,
+	/// Whether the [`Frames`] has been dropped, so that nothing will make room in the queue.
+	closed: bool,
+	/// The task waiting for a frame in a [`FrameStream`], if any.
+	waker: Option<Waker>,
+}
+
+#[derive(Debug)]
+struct Shared {
+	config: QueueConfig,
+	state: Mutex<State>,
+	not_empty: Condvar,
+	not_full: Condvar,
+	dropped: AtomicU64,
+}
+
+impl Shared {
+	fn lock(&self) -> MutexGuard<'_, State> {
+		self
+			.state
+			.lock()
+			.unwrap_or_else(std::sync::PoisonError::into_inner)
+	}
+}
+
+/// The sending half, owned by the frame listener.
+pub(super) struct Sender {
+	shared: Arc<Shared>,
+}
+
+impl Sender {
+	pub(super) fn send(&self, frame: Frame, ty: FrameType) {
+		let shared = &*self.shared;
+		let mut state = shared.lock();
+		while state.queue.len() >= shared.config.capacity && !state.closed {
+			match shared.config.policy {
+				DropPolicy::DropOldest => {
+					state.queue.pop_front();
+					shared.dropped.fetch_add(1, Ordering::Relaxed);
+				}
+				DropPolicy::DropNewest => {
+					shared.dropped.fetch_add(1, Ordering::Relaxed);
+					return;
+				}
+				DropPolicy::Block => {
+					state = shared
+						.not_full
+						.wait(state)
+						.unwrap_or_else(std::sync::PoisonError::into_inner);
+				}
+			}
+		}
+		if !state.closed {
+			state.queue.push_back((frame, ty));
+			let waker = state.waker.take();
+			drop(state);
+			shared.not_empty.notify_one();
+			if let Some(waker) = waker {
+				waker.wake();
+			}
+		}
+	}
+}
+
+impl Drop for Sender {
+	fn drop(&mut self) {
+		let mut state = self.shared.lock();
+		state.disconnected = true;
+		let waker = state.waker.take();
+		drop(state);
+		self.shared.not_empty.notify_all();
+		if let Some(waker) = waker {
+			waker.wake();
+		}
+	}
+}
+
+/// A queue of the frames received from a device, created by [`Device::frames`](super::Device::frames).
+///
+/// Iterating blocks until a frame arrives and ends once no more frames can arrive: when the device is dropped or its frame listener is replaced.
+#[derive(Debug)]
+pub struct Frames {
+	shared: Arc<Shared>,
+}
+
+impl Frames {
+	/// Create a queue and the sender that fills it.
+	///
+	/// # Panics
+	///
+	/// Panics if `config.capacity` is zero.
+	pub(super) fn new(config: QueueConfig) -> (Sender, Self) {
+		assert!(config.capacity > 0, "the queue capacity must be positive");
+
+		let shared = Arc::new(Shared {
+			config,
+			state: Mutex::new(State {
+				queue: VecDeque::with_capacity(config.capacity),
+				disconnected: false,
+				closed: false,
+				waker: None,
+			}),
+			not_empty: Condvar::new(),
+			not_full: Condvar::new(),
+			dropped: AtomicU64::new(0),
+		});
+		(
+			Sender {
+				shared: Arc::clone(&shared),
+			},
+			Self { shared },
+		)
+	}
+
+	/// The parameters that this was created with.
+	#[must_use]
+	pub fn config(&self) -> QueueConfig {
+		self.shared.config
+	}
+
+	/// The number of frames that have been discarded because the queue was full.
+	#[must_use]
+	pub fn dropped(&self) -> u64 {
+		self.shared.dropped.load(Ordering::Relaxed)
+	}
+
+	/// The number of frames waiting to be received.
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.shared.lock().queue.len()
+	}
+
+	/// Whether no frames are waiting to be received.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	fn pop(&self, state: &mut State) -> Option<(Frame, FrameType)> {
+		let frame = state.queue.pop_front();
+		if frame.is_some() {
+			self.shared.not_full.notify_one();
+		}
+		frame
+	}
+
+	/// Wait for a frame.
+	///
+	/// Returns `None` if no more frames can arrive.
+	pub fn recv(&self) -> Option<(Frame, FrameType)> {
+		let mut state = self.shared.lock();
+		loop {
+			if let Some(frame) = self.pop(&mut state) {
+				return Some(frame);
+			}
+			if state.disconnected {
+				return None;
+			}
+			state = self
+				.shared
+				.not_empty
+				.wait(state)
+				.unwrap_or_else(std::sync::PoisonError::into_inner);
+		}
+	}
+
+	/// Wait for a frame for at most `timeout`.
+	///
+	/// # Errors
+	///
+	/// Fails if no frame arrives in time or if no more frames can arrive.
+	pub fn recv_timeout(&self, timeout: Duration) -> Result<(Frame, FrameType), RecvTimeoutError> {
+		let deadline = Instant::now().checked_add(timeout);
+		let mut state = self.shared.lock();
+		loop {
+			if let Some(frame) = self.pop(&mut state) {
+				return Ok(frame);
+			}
+			if state.disconnected {
+				return Err(RecvTimeoutError::Disconnected);
+			}
+			let remaining = match deadline {
+				Some(deadline) => deadline
+					.checked_duration_since(Instant::now())
+					.ok_or(RecvTimeoutError::Timeout)?,
+				None => timeout,
+			};
+			state = self
+				.shared
+				.not_empty
+				.wait_timeout(state, remaining)
+				.unwrap_or_else(std::sync::PoisonError::into_inner)
+				.0;
+		}
+	}
+
+	/// Take a frame if one is waiting, without blocking.
+	///
+	/// # Errors
+	///
+	/// Fails if no frame is waiting or if no more frames can arrive.
+	pub fn try_recv(&self) -> Result<(Frame, FrameType), TryRecvError> {
+		let mut state = self.shared.lock();
+		match self.pop(&mut state) {
+			Some(frame) => Ok(frame),
+			None if state.disconnected => Err(TryRecvError::Disconnected),
+			None => Err(TryRecvError::Empty),
+		}
+	}
+}
+
+impl Iterator for Frames {
+	type Item = (Frame, FrameType);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.recv()
+	}
+}
+
+impl Drop for Frames {
+	fn drop(&mut self) {
+		let mut state = self.shared.lock();
+		state.closed = true;
+		state.queue.clear();
+		drop(state);
+		self.shared.not_full.notify_all();
+	}
+}
+
+/// An asynchronous stream of the frames received from a device, created by [`Device::frame_stream`](super::Device::frame_stream).
+///
+/// The stream ends once no more frames can arrive: when the device is dropped or its frame listener is replaced.
+#[cfg(feature = "futures")]
+#[derive(Debug)]
+pub struct FrameStream {
+	frames: Frames,
+}
+
+#[cfg(feature = "futures")]
+impl FrameStream {
+	pub(super) fn new(frames: Frames) -> Self {
+		Self { frames }
+	}
+
+	/// The parameters that this was created with.
+	#[must_use]
+	pub fn config(&self) -> QueueConfig {
+		self.frames.config()
+	}
+
+	/// The number of frames that have been discarded because the queue was full.
+	#[must_use]
+	pub fn dropped(&self) -> u64 {
+		self.frames.dropped()
+	}
+}
+
+#[cfg(feature = "futures")]
+impl futures_core::Stream for FrameStream {
+	type Item = (Frame, FrameType);
+
+	fn poll_next(
+		self: std::pin::Pin<&mut Self>,
+		context: &mut std::task::Context<'_>,
+	) -> std::task::Poll<Option<Self::Item>> {
+		let frames = &self.frames;
+		let mut state = frames.shared.lock();
+		if let Some(frame) = frames.pop(&mut state) {
+			std::task::Poll::Ready(Some(frame))
+		} else if state.disconnected {
+			std::task::Poll::Ready(None)
+		} else {
+			match &mut state.waker {
+				Some(waker) if waker.will_wake(context.waker()) => (),
+				waker => *waker = Some(context.waker().clone()),
+			}
+			std::task::Poll::Pending
+		}
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.frames.len(), None)
+	}
+}
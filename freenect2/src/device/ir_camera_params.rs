@@ -2,6 +2,8 @@ use freenect2_sys as sys;
 
 /// IR camera intrinsic calibration parameters.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
 pub struct IrCameraParams {
 	/// Focal length x (pixel)
 	pub fx: f32,
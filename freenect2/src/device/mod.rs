@@ -1,19 +1,34 @@
 //! Provides [`Device`] and related types.
 
+use std::any::Any;
 use std::os::raw::c_void;
+use std::panic::AssertUnwindSafe;
 use std::ptr::{addr_of_mut, NonNull};
+use std::sync::{Arc, Mutex, PoisonError};
 
 use freenect2_sys as sys;
 
+use crate::frame_set::{FrameSync, Synced};
 use crate::{Frame, FrameType};
 
 mod color_camera_params;
+mod counters;
 mod depth_config;
+mod frames;
 mod ir_camera_params;
+mod playback;
+mod typestate;
 
 pub use color_camera_params::ColorCameraParams;
+use counters::Counters;
+pub use counters::FrameCounts;
 pub use depth_config::DepthConfig;
+#[cfg(feature = "futures")]
+pub use frames::FrameStream;
+pub use frames::{DropPolicy, Frames, QueueConfig};
 pub use ir_camera_params::IrCameraParams;
+pub use playback::{PlaybackDevice, Speed};
+pub use typestate::{OpenDevice, StreamingDevice, TransitionError};
 
 /// Errors that can occur related to the device.
 #[derive(Debug, Clone, Copy)]
@@ -26,6 +41,8 @@ pub enum Error {
 	///
 	/// `libfreenect2` does not provide any information about the underlying error.
 	Library,
+	/// A frame listener panicked. The panic can be retrieved with [`Device::take_listener_panic`].
+	ListenerPanicked,
 }
 
 impl std::fmt::Display for Error {
@@ -36,17 +53,63 @@ impl std::fmt::Display for Error {
 			}
 			Self::NotRunning => "streams are not running; nothing to stop",
 			Self::Library => "libfreenect2 library error",
+			Self::ListenerPanicked => "a frame listener panicked",
 		})
 	}
 }
 
 impl std::error::Error for Error {}
 
+/// The payload of a panic in a frame listener, kept until the owner of the device takes it.
+#[derive(Default)]
+struct ListenerPanic(Mutex<Option<Box<dyn Any + Send>>>);
+
+impl ListenerPanic {
+	fn take(&self) -> Option<Box<dyn Any + Send>> {
+		self.0.lock().unwrap_or_else(PoisonError::into_inner).take()
+	}
+
+	fn is_set(&self) -> bool {
+		self
+			.0
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.is_some()
+	}
+
+	fn set(&self, payload: Box<dyn Any + Send>) {
+		*self.0.lock().unwrap_or_else(PoisonError::into_inner) = Some(payload);
+	}
+}
+
+impl std::fmt::Debug for ListenerPanic {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		formatter
+			.debug_tuple("ListenerPanic")
+			.field(&self.is_set())
+			.finish()
+	}
+}
+
+/// A frame listener, along with where to put its panics.
+struct Listener<F> {
+	callback: F,
+	/// Whether the callback has panicked, after which it is not called again.
+	panicked: bool,
+	panic: Arc<ListenerPanic>,
+	counters: Arc<Counters>,
+}
+
 /// A Kinect V2 device.
+///
+/// Whether streams are running is checked at runtime.
+/// [`OpenDevice`] and [`StreamingDevice`] check it at compile time instead.
 #[derive(Debug)]
 pub struct Device {
 	inner: NonNull<sys::Fn2Device>,
 	started: bool,
+	listener_panic: Arc<ListenerPanic>,
+	counters: Arc<Counters>,
 }
 
 impl Device {
@@ -67,6 +130,8 @@ impl Device {
 		Self {
 			inner: NonNull::new(raw).unwrap(),
 			started,
+			listener_panic: Arc::default(),
+			counters: Arc::default(),
 		}
 	}
 
@@ -148,23 +213,53 @@ impl Device {
 	}
 
 	/// Set the frame listener, which is called when the Kinect device has a frame available.
+	///
+	/// This replaces any previous listener, which is dropped.
+	/// If streams are running, this first waits for any call to the previous listener to return.
+	///
+	/// The listener is called on one of libfreenect2's threads.
+	/// If it panics, the panic is caught, the listener is not called again, and [`stop`](Self::stop) fails with [`Error::ListenerPanicked`].
 	pub fn set_frame_listener<F: FnMut(Frame, FrameType) + Send + 'static>(&mut self, listener: F) {
 		unsafe extern "C" fn call_listener<F: FnMut(Frame, FrameType) + 'static>(
 			user_data: *mut c_void,
 			frame: sys::Fn2Frame,
 			ty: sys::Fn2FrameType,
 		) {
-			let ty = ty.try_into().unwrap();
-			(*user_data.cast::<F>())(Frame::from_sys(frame), ty);
+			let listener = &mut *user_data.cast::<Listener<F>>();
+			// unwinding into libfreenect2 is undefined behavior
+			let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+				let frame = Frame::from_sys(frame);
+				let Ok(ty) = ty.try_into() else {
+					log::error!("dropping frame of unknown type {ty}");
+					return;
+				};
+				listener.counters.count(&frame, ty);
+				if !listener.panicked {
+					(listener.callback)(frame, ty);
+				}
+			}));
+			if let Err(payload) = result {
+				listener.panicked = true;
+				listener.panic.set(payload);
+			}
 		}
 
 		unsafe extern "C" fn drop_listener<F: FnMut(Frame, FrameType) + 'static>(
 			user_data: *mut c_void,
 		) {
-			drop(Box::from_raw(user_data.cast::<F>()));
+			let listener = Box::from_raw(user_data.cast::<Listener<F>>());
+			let panic = Arc::clone(&listener.panic);
+			if let Err(payload) = std::panic::catch_unwind(AssertUnwindSafe(move || drop(listener))) {
+				panic.set(payload);
+			}
 		}
 
-		let listener = Box::into_raw(Box::new(listener));
+		let listener = Box::into_raw(Box::new(Listener {
+			callback: listener,
+			panicked: false,
+			panic: Arc::clone(&self.listener_panic),
+			counters: Arc::clone(&self.counters),
+		}));
 		unsafe {
 			sys::fn2_device_set_frame_listener(
 				self.inner.as_ptr(),
@@ -175,6 +270,62 @@ impl Device {
 		}
 	}
 
+	/// Take the payload of the last panic in a frame listener, if any, so that it can be inspected or [resumed](std::panic::resume_unwind).
+	pub fn take_listener_panic(&mut self) -> Option<Box<dyn Any + Send>> {
+		self.listener_panic.take()
+	}
+
+	/// Counts of the frames of type `ty` that frame listeners have received since the device was opened or the counts were [reset](Self::reset_frame_counts).
+	///
+	/// Frames are counted even after a listener panics.
+	#[must_use]
+	pub fn frame_counts(&self, ty: FrameType) -> FrameCounts {
+		self.counters.get(ty)
+	}
+
+	/// Reset the counts of frames of all types to zero.
+	pub fn reset_frame_counts(&self) {
+		self.counters.reset();
+	}
+
+	/// Set the frame listener to one that groups frames from the same instant into sets using `sync`, then calls `listener` with each set.
+	///
+	/// This replaces any listener set with [`set_frame_listener`](Self::set_frame_listener).
+	pub fn set_frame_set_listener<F: FnMut(Synced) + Send + 'static>(
+		&mut self,
+		mut sync: FrameSync,
+		mut listener: F,
+	) {
+		self.set_frame_listener(move |frame, ty| sync.push(frame, ty, &mut listener));
+	}
+
+	/// Set the frame listener to one that queues frames to be received from the returned [`Frames`].
+	///
+	/// This replaces any listener set with [`set_frame_listener`](Self::set_frame_listener).
+	/// With [`DropPolicy::Block`], drop the [`Frames`] before calling [`stop`](Self::stop), which otherwise waits forever if the queue is full.
+	///
+	/// # Panics
+	///
+	/// Panics if `config.capacity` is zero.
+	pub fn frames(&mut self, config: QueueConfig) -> Frames {
+		let (sender, frames) = Frames::new(config);
+		self.set_frame_listener(move |frame, ty| sender.send(frame, ty));
+		frames
+	}
+
+	/// Set the frame listener to one that queues frames to be received asynchronously from the returned [`FrameStream`].
+	///
+	/// This replaces any listener set with [`set_frame_listener`](Self::set_frame_listener).
+	/// With [`DropPolicy::Block`], libfreenect2's processing thread waits until the stream is polled, so drop the stream before calling [`stop`](Self::stop).
+	///
+	/// # Panics
+	///
+	/// Panics if `config.capacity` is zero.
+	#[cfg(feature = "futures")]
+	pub fn frame_stream(&mut self, config: QueueConfig) -> FrameStream {
+		FrameStream::new(self.frames(config))
+	}
+
 	/// Whether streams have started.
 	#[must_use]
 	pub fn started(&self) -> bool {
@@ -218,11 +369,32 @@ impl Device {
 	/// # Errors
 	///
 	/// Fails if streams are not running or if the library returns an error.
+	/// Also fails with [`Error::ListenerPanicked`] if a frame listener has panicked and the panic has not been taken with [`take_listener_panic`](Self::take_listener_panic), although the streams are stopped.
 	pub fn stop(&mut self) -> Result<(), Error> {
 		if !self.started {
 			Err(Error::NotRunning)
 		} else if unsafe { sys::fn2_device_stop(self.inner.as_ptr()) } {
 			self.started = false;
+			self.counters.stopped();
+			if self.listener_panic.is_set() {
+				Err(Error::ListenerPanicked)
+			} else {
+				Ok(())
+			}
+		} else {
+			Err(Error::Library)
+		}
+	}
+
+	/// Stop the streams if they are running, then close the device.
+	///
+	/// Dropping the device also closes it, but ignores any error.
+	///
+	/// # Errors
+	///
+	/// Fails if the library returns an error.
+	pub fn close(self) -> Result<(), Error> {
+		if unsafe { sys::fn2_device_close(self.inner.as_ptr()) } {
 			Ok(())
 		} else {
 			Err(Error::Library)
@@ -0,0 +1,438 @@
+use std::any::Any;
+use std::io::Read;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "futures")]
+use super::FrameStream;
+use super::{
+	ColorCameraParams, Counters, DepthConfig, Error, FrameCounts, Frames, IrCameraParams, Listener,
+	ListenerPanic, QueueConfig,
+};
+use crate::frame_set::{FrameSync, Synced};
+use crate::recording::{Header, Recording, RecordingError};
+use crate::{Frame, FrameType};
+
+/// How fast a [`PlaybackDevice`] replays frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Speed {
+	/// With the same intervals between frames as when they were recorded.
+	RealTime,
+	/// As soon as the previous frame has been handled.
+	AsFastAsPossible,
+}
+
+type BoxedListener = Listener<Box<dyn FnMut(Frame, FrameType) + Send>>;
+
+/// The frame listener, shared with the playback thread.
+type SharedListener = Arc<Mutex<Option<BoxedListener>>>;
+
+fn lock(listener: &SharedListener) -> MutexGuard<'_, Option<BoxedListener>> {
+	listener.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+type Source = Recording<Box<dyn Read + Send>>;
+
+/// The state of the recording while it is not being played.
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+enum Position {
+	/// Frames remain to be played, starting with the frame that was about to be played when the playback was stopped, if any.
+	Paused(Source, Option<(Frame, FrameType)>),
+	/// The recording has ended or failed.
+	Finished,
+}
+
+/// A running playback thread.
+#[derive(Debug)]
+struct Playback {
+	stop: Arc<AtomicBool>,
+	/// Whether the recording has ended, which is set before the listener is dropped.
+	finished: Arc<AtomicBool>,
+	thread: JoinHandle<Position>,
+}
+
+/// Which frames to play, and how.
+#[derive(Debug, Clone, Copy)]
+struct Options {
+	speed: Speed,
+	rgb: bool,
+	depth: bool,
+}
+
+impl Options {
+	fn wants(self, ty: FrameType) -> bool {
+		match ty {
+			FrameType::Color => self.rgb,
+			FrameType::Ir | FrameType::Depth => self.depth,
+		}
+	}
+}
+
+/// Play frames from `source` until it ends or `stop` is set.
+fn play(
+	mut source: Source,
+	mut next: Option<(Frame, FrameType)>,
+	options: Options,
+	listener: &SharedListener,
+	stop: &AtomicBool,
+	finished: &AtomicBool,
+) -> Position {
+	// the time at which the first frame is played, and its timestamp
+	let mut start: Option<(Instant, u32)> = None;
+	while !stop.load(Ordering::Relaxed) {
+		let (frame, ty) = match next
+			.take()
+			.map_or_else(|| source.read_frame(), |frame| Ok(Some(frame)))
+		{
+			Ok(Some(frame)) => frame,
+			Ok(None) => break,
+			Err(error) => {
+				log::error!("stopping playback: {error}");
+				break;
+			}
+		};
+		if !options.wants(ty) {
+			continue;
+		}
+
+		if options.speed == Speed::RealTime {
+			let (start_time, start_timestamp) = *start.get_or_insert((Instant::now(), frame.timestamp()));
+			// timestamps are in units of 100 microseconds, and wrap around
+			let offset =
+				Duration::from_micros(u64::from(frame.timestamp().wrapping_sub(start_timestamp)) * 100);
+			// sleep in short steps so that stopping is not delayed by a long gap in the recording
+			while let Some(remaining) = (start_time + offset).checked_duration_since(Instant::now()) {
+				if stop.load(Ordering::Relaxed) {
+					return Position::Paused(source, Some((frame, ty)));
+				}
+				std::thread::sleep(remaining.min(Duration::from_millis(50)));
+			}
+		}
+
+		let mut listener = lock(listener);
+		if let Some(listener) = &mut *listener {
+			listener.counters.count(&frame, ty);
+			if !listener.panicked {
+				let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+					(listener.callback)(frame, ty);
+				}));
+				if let Err(payload) = result {
+					listener.panicked = true;
+					listener.panic.set(payload);
+				}
+			}
+		}
+	}
+
+	if stop.load(Ordering::Relaxed) {
+		Position::Paused(source, None)
+	} else {
+		finished.store(true, Ordering::Relaxed);
+		// drop the listener so that iterating over `Frames` ends with the recording
+		let listener = lock(listener).take();
+		if let Some(listener) = listener {
+			let panic = Arc::clone(&listener.panic);
+			if let Err(payload) = std::panic::catch_unwind(AssertUnwindSafe(move || drop(listener))) {
+				panic.set(payload);
+			}
+		}
+		Position::Finished
+	}
+}
+
+/// Replays a [`Recording`] through the same interface as a [`Device`](super::Device), for working without a Kinect.
+///
+/// Frames are passed to the frame listener on a separate thread, as libfreenect2 does.
+/// Stopping pauses the playback, and starting again resumes it.
+/// When the recording ends, the frame listener is dropped, which ends iteration over [`Frames`].
+pub struct PlaybackDevice {
+	header: Header,
+	speed: Speed,
+	listener: SharedListener,
+	listener_panic: Arc<ListenerPanic>,
+	counters: Arc<Counters>,
+	/// The recording while it is not being played, or `None` while it is.
+	position: Option<Position>,
+	playback: Option<Playback>,
+}
+
+impl std::fmt::Debug for PlaybackDevice {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		formatter
+			.debug_struct("PlaybackDevice")
+			.field("header", &self.header)
+			.field("speed", &self.speed)
+			.field("listener_panic", &self.listener_panic)
+			.field("counters", &self.counters)
+			.field("position", &self.position)
+			.field("playback", &self.playback)
+			.finish_non_exhaustive()
+	}
+}
+
+impl PlaybackDevice {
+	/// Open the recording at `path` to be played in real time.
+	///
+	/// # Errors
+	///
+	/// Fails if the file cannot be read or is not a valid recording.
+	pub fn open(path: impl AsRef<Path>) -> Result<Self, RecordingError> {
+		Recording::open(path).map(Self::new)
+	}
+
+	/// Play `recording`, from its current position, in real time.
+	#[must_use]
+	pub fn new<R: Read + Send + 'static>(recording: Recording<R>) -> Self {
+		Self {
+			header: recording.header().clone(),
+			speed: Speed::RealTime,
+			listener: Arc::default(),
+			listener_panic: Arc::default(),
+			counters: Arc::default(),
+			position: Some(Position::Paused(recording.boxed(), None)),
+			playback: None,
+		}
+	}
+
+	/// The properties of the device that the recording was captured with.
+	#[must_use]
+	pub fn header(&self) -> &Header {
+		&self.header
+	}
+
+	/// Get the serial number of the device that the recording was captured with.
+	#[must_use]
+	pub fn serial_number(&self) -> String {
+		self.header.serial_number.clone()
+	}
+
+	/// Get the firmware version of the device that the recording was captured with.
+	#[must_use]
+	pub fn firmware_version(&self) -> String {
+		self.header.firmware_version.clone()
+	}
+
+	/// Get the parameters of the color camera.
+	#[must_use]
+	pub fn color_camera_params(&self) -> ColorCameraParams {
+		self.header.color_camera_params
+	}
+
+	/// Get the parameters of the IR camera.
+	#[must_use]
+	pub fn ir_camera_params(&self) -> IrCameraParams {
+		self.header.ir_camera_params
+	}
+
+	/// Get the depth processing configuration that the depth frames were produced with.
+	#[must_use]
+	pub fn depth_config(&self) -> DepthConfig {
+		self.header.depth_config
+	}
+
+	/// How fast frames are replayed.
+	#[must_use]
+	pub fn speed(&self) -> Speed {
+		self.speed
+	}
+
+	/// Set how fast frames are replayed.
+	///
+	/// # Errors
+	///
+	/// Fails if streams are running.
+	pub fn set_speed(&mut self, speed: Speed) -> Result<(), Error> {
+		if self.started() {
+			Err(Error::StreamsRunning)
+		} else {
+			self.speed = speed;
+			Ok(())
+		}
+	}
+
+	/// Set the frame listener, which is called with each frame of the recording.
+	///
+	/// This replaces any previous listener, which is dropped.
+	///
+	/// The listener is called on the playback thread.
+	/// If it panics, the panic is caught, the listener is not called again, and [`stop`](Self::stop) fails with [`Error::ListenerPanicked`].
+	pub fn set_frame_listener<F: FnMut(Frame, FrameType) + Send + 'static>(&mut self, listener: F) {
+		let previous = lock(&self.listener).replace(Listener {
+			callback: Box::new(listener),
+			panicked: false,
+			panic: Arc::clone(&self.listener_panic),
+			counters: Arc::clone(&self.counters),
+		});
+		// drop the previous listener without holding the lock, in case it blocks
+		if let Some(previous) = previous {
+			if let Err(payload) = std::panic::catch_unwind(AssertUnwindSafe(move || drop(previous))) {
+				self.listener_panic.set(payload);
+			}
+		}
+	}
+
+	/// Take the payload of the last panic in a frame listener, if any, so that it can be inspected or [resumed](std::panic::resume_unwind).
+	pub fn take_listener_panic(&mut self) -> Option<Box<dyn Any + Send>> {
+		self.listener_panic.take()
+	}
+
+	/// Counts of the frames of type `ty` that frame listeners have received since the recording was opened or the counts were [reset](Self::reset_frame_counts).
+	///
+	/// The errors and skipped frames are those of the device that the recording was captured with, as far as the recording shows them.
+	#[must_use]
+	pub fn frame_counts(&self, ty: FrameType) -> FrameCounts {
+		self.counters.get(ty)
+	}
+
+	/// Reset the counts of frames of all types to zero.
+	pub fn reset_frame_counts(&self) {
+		self.counters.reset();
+	}
+
+	/// Set the frame listener to one that groups frames from the same instant into sets using `sync`, then calls `listener` with each set.
+	///
+	/// This replaces any listener set with [`set_frame_listener`](Self::set_frame_listener).
+	pub fn set_frame_set_listener<F: FnMut(Synced) + Send + 'static>(
+		&mut self,
+		mut sync: FrameSync,
+		mut listener: F,
+	) {
+		self.set_frame_listener(move |frame, ty| sync.push(frame, ty, &mut listener));
+	}
+
+	/// Set the frame listener to one that queues frames to be received from the returned [`Frames`].
+	///
+	/// This replaces any listener set with [`set_frame_listener`](Self::set_frame_listener).
+	/// With [`Speed::AsFastAsPossible`], [`DropPolicy::Block`](super::DropPolicy::Block) replays every frame without dropping any.
+	/// In that case, drop the [`Frames`] before calling [`stop`](Self::stop), which otherwise waits forever if the queue is full.
+	///
+	/// # Panics
+	///
+	/// Panics if `config.capacity` is zero.
+	pub fn frames(&mut self, config: QueueConfig) -> Frames {
+		let (sender, frames) = Frames::new(config);
+		self.set_frame_listener(move |frame, ty| sender.send(frame, ty));
+		frames
+	}
+
+	/// Set the frame listener to one that queues frames to be received asynchronously from the returned [`FrameStream`].
+	///
+	/// This replaces any listener set with [`set_frame_listener`](Self::set_frame_listener).
+	///
+	/// # Panics
+	///
+	/// Panics if `config.capacity` is zero.
+	#[cfg(feature = "futures")]
+	pub fn frame_stream(&mut self, config: QueueConfig) -> FrameStream {
+		FrameStream::new(self.frames(config))
+	}
+
+	/// Whether streams have started.
+	#[must_use]
+	pub fn started(&self) -> bool {
+		self.playback.is_some()
+	}
+
+	/// Whether the whole recording has been played.
+	#[must_use]
+	pub fn finished(&self) -> bool {
+		match &self.playback {
+			Some(playback) => playback.finished.load(Ordering::Relaxed),
+			None => matches!(self.position, Some(Position::Finished)),
+		}
+	}
+
+	/// Start playing all streams.
+	///
+	/// # Errors
+	///
+	/// Fails if streams are already running.
+	pub fn start(&mut self) -> Result<(), Error> {
+		self.start_streams(true, true)
+	}
+
+	/// Start playing some streams; frames of the other streams are skipped.
+	///
+	/// # Errors
+	///
+	/// Fails if streams are already running.
+	pub fn start_streams(&mut self, rgb: bool, depth: bool) -> Result<(), Error> {
+		if self.started() {
+			return Err(Error::StreamsRunning);
+		}
+
+		let options = Options {
+			speed: self.speed,
+			rgb,
+			depth,
+		};
+		let stop = Arc::new(AtomicBool::new(false));
+		let finished = Arc::new(AtomicBool::new(false));
+		let thread = if let Some(Position::Paused(source, next)) = self.position.take() {
+			let listener = Arc::clone(&self.listener);
+			let stop = Arc::clone(&stop);
+			let finished = Arc::clone(&finished);
+			std::thread::spawn(move || play(source, next, options, &listener, &stop, &finished))
+		} else {
+			// there is nothing left to play, but the device still behaves as if it were started
+			finished.store(true, Ordering::Relaxed);
+			std::thread::spawn(|| Position::Finished)
+		};
+		self.playback = Some(Playback {
+			stop,
+			finished,
+			thread,
+		});
+		Ok(())
+	}
+
+	/// Stop playing, keeping the position in the recording.
+	///
+	/// # Errors
+	///
+	/// Fails if streams are not running.
+	/// Also fails with [`Error::ListenerPanicked`] if a frame listener has panicked and the panic has not been taken with [`take_listener_panic`](Self::take_listener_panic), although the streams are stopped.
+	pub fn stop(&mut self) -> Result<(), Error> {
+		let playback = self.playback.take().ok_or(Error::NotRunning)?;
+		playback.stop.store(true, Ordering::Relaxed);
+		self.position = Some(
+			playback
+				.thread
+				.join()
+				.unwrap_or_else(|payload| std::panic::resume_unwind(payload)),
+		);
+		if self.listener_panic.is_set() {
+			Err(Error::ListenerPanicked)
+		} else {
+			Ok(())
+		}
+	}
+
+	/// Stop the streams if they are running, then close the recording.
+	///
+	/// Dropping the device also closes it, but ignores any error.
+	///
+	/// # Errors
+	///
+	/// Fails with [`Error::ListenerPanicked`] if a frame listener has panicked and the panic has not been taken.
+	pub fn close(mut self) -> Result<(), Error> {
+		match self.stop() {
+			Ok(()) | Err(Error::NotRunning) => Ok(()),
+			Err(error) => Err(error),
+		}
+	}
+}
+
+impl Drop for PlaybackDevice {
+	fn drop(&mut self) {
+		if let Some(playback) = self.playback.take() {
+			playback.stop.store(true, Ordering::Relaxed);
+			let _ = playback.thread.join();
+		}
+	}
+}
@@ -0,0 +1,315 @@
+//! Provides [`OpenDevice`] and [`StreamingDevice`], which track whether the streams of a [`Device`] are running in the type system rather than at runtime.
+
+#[cfg(feature = "futures")]
+use super::FrameStream;
+use super::{
+	ColorCameraParams, DepthConfig, Device, Error, FrameCounts, Frames, IrCameraParams, QueueConfig,
+};
+use crate::frame_set::{FrameSync, Synced};
+use crate::{Frame, FrameType};
+
+/// A failed state transition of an [`OpenDevice`] or a [`StreamingDevice`], which gives back the device in its previous state.
+///
+/// `libfreenect2` does not provide any information about the underlying error.
+#[derive(Debug)]
+pub struct TransitionError<D> {
+	/// The device, in the state it was in before the transition.
+	pub device: D,
+}
+
+impl<D> std::fmt::Display for TransitionError<D> {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		Error::Library.fmt(formatter)
+	}
+}
+
+impl<D: std::fmt::Debug> std::error::Error for TransitionError<D> {}
+
+/// A Kinect V2 device whose streams are not running.
+///
+/// This can be reconfigured and given a frame listener, then started to get a [`StreamingDevice`].
+///
+/// Convert a [`Device`] with [`TryFrom`], or convert back with [`From`].
+#[derive(Debug)]
+pub struct OpenDevice {
+	device: Device,
+}
+
+impl OpenDevice {
+	/// Get the serial number of the device.
+	#[must_use]
+	pub fn serial_number(&self) -> String {
+		self.device.serial_number()
+	}
+
+	/// Get the firmware version of the device.
+	#[must_use]
+	pub fn firmware_version(&self) -> String {
+		self.device.firmware_version()
+	}
+
+	/// Get the parameters of the color camera.
+	#[must_use]
+	pub fn color_camera_params(&self) -> ColorCameraParams {
+		self.device.color_camera_params()
+	}
+
+	/// Set the parameters of the color camera.
+	#[allow(clippy::missing_panics_doc)]
+	pub fn set_color_camera_params(&mut self, params: ColorCameraParams) {
+		self
+			.device
+			.set_color_camera_params(params)
+			.expect("streams are stopped");
+	}
+
+	/// Get the parameters of the IR camera.
+	#[must_use]
+	pub fn ir_camera_params(&self) -> IrCameraParams {
+		self.device.ir_camera_params()
+	}
+
+	/// Set the parameters of the IR camera.
+	#[allow(clippy::missing_panics_doc)]
+	pub fn set_ir_camera_params(&mut self, params: IrCameraParams) {
+		self
+			.device
+			.set_ir_camera_params(params)
+			.expect("streams are stopped");
+	}
+
+	/// Set the device's depth processing configuration.
+	pub fn set_depth_config(&self, config: DepthConfig) {
+		self.device.set_depth_config(config);
+	}
+
+	/// Set the frame listener, which is called when the Kinect device has a frame available.
+	///
+	/// See [`Device::set_frame_listener`].
+	pub fn set_frame_listener<F: FnMut(Frame, FrameType) + Send + 'static>(&mut self, listener: F) {
+		self.device.set_frame_listener(listener);
+	}
+
+	/// Set the frame listener to one that groups frames from the same instant into sets.
+	///
+	/// See [`Device::set_frame_set_listener`].
+	pub fn set_frame_set_listener<F: FnMut(Synced) + Send + 'static>(
+		&mut self,
+		sync: FrameSync,
+		listener: F,
+	) {
+		self.device.set_frame_set_listener(sync, listener);
+	}
+
+	/// Set the frame listener to one that queues frames.
+	///
+	/// See [`Device::frames`].
+	///
+	/// # Panics
+	///
+	/// Panics if `config.capacity` is zero.
+	pub fn frames(&mut self, config: QueueConfig) -> Frames {
+		self.device.frames(config)
+	}
+
+	/// Set the frame listener to one that queues frames to be received asynchronously.
+	///
+	/// See [`Device::frame_stream`].
+	///
+	/// # Panics
+	///
+	/// Panics if `config.capacity` is zero.
+	#[cfg(feature = "futures")]
+	pub fn frame_stream(&mut self, config: QueueConfig) -> FrameStream {
+		self.device.frame_stream(config)
+	}
+
+	/// Take the payload of the last panic in a frame listener, if any.
+	///
+	/// See [`Device::take_listener_panic`].
+	pub fn take_listener_panic(&mut self) -> Option<Box<dyn std::any::Any + Send>> {
+		self.device.take_listener_panic()
+	}
+
+	/// Counts of the frames of type `ty` that frame listeners have received.
+	///
+	/// See [`Device::frame_counts`].
+	#[must_use]
+	pub fn frame_counts(&self, ty: FrameType) -> FrameCounts {
+		self.device.frame_counts(ty)
+	}
+
+	/// Reset the counts of frames of all types to zero.
+	pub fn reset_frame_counts(&self) {
+		self.device.reset_frame_counts();
+	}
+
+	/// Start all streams and data processing.
+	///
+	/// # Errors
+	///
+	/// Fails if the library returns an error.
+	pub fn start(mut self) -> Result<StreamingDevice, TransitionError<Self>> {
+		match self.device.start() {
+			Ok(()) => Ok(StreamingDevice {
+				device: self.device,
+			}),
+			Err(_) => Err(TransitionError { device: self }),
+		}
+	}
+
+	/// Start some streams and data processing.
+	///
+	/// # Errors
+	///
+	/// Fails if the library returns an error.
+	pub fn start_streams(
+		mut self,
+		rgb: bool,
+		depth: bool,
+	) -> Result<StreamingDevice, TransitionError<Self>> {
+		match self.device.start_streams(rgb, depth) {
+			Ok(()) => Ok(StreamingDevice {
+				device: self.device,
+			}),
+			Err(_) => Err(TransitionError { device: self }),
+		}
+	}
+
+	/// Close the device.
+	///
+	/// See [`Device::close`].
+	///
+	/// # Errors
+	///
+	/// Fails if the library returns an error.
+	pub fn close(self) -> Result<(), Error> {
+		self.device.close()
+	}
+}
+
+impl TryFrom<Device> for OpenDevice {
+	type Error = Device;
+
+	/// Fails, giving back the device, if its streams are running.
+	fn try_from(device: Device) -> Result<Self, Device> {
+		if device.started() {
+			Err(device)
+		} else {
+			Ok(Self { device })
+		}
+	}
+}
+
+impl From<OpenDevice> for Device {
+	fn from(open: OpenDevice) -> Self {
+		open.device
+	}
+}
+
+/// A Kinect V2 device whose streams are running.
+///
+/// Stop it to get an [`OpenDevice`], which can be reconfigured.
+///
+/// Convert a [`Device`] with [`TryFrom`], or convert back with [`From`].
+#[derive(Debug)]
+pub struct StreamingDevice {
+	device: Device,
+}
+
+impl StreamingDevice {
+	/// Get the serial number of the device.
+	#[must_use]
+	pub fn serial_number(&self) -> String {
+		self.device.serial_number()
+	}
+
+	/// Get the firmware version of the device.
+	#[must_use]
+	pub fn firmware_version(&self) -> String {
+		self.device.firmware_version()
+	}
+
+	/// Get the parameters of the color camera.
+	#[must_use]
+	pub fn color_camera_params(&self) -> ColorCameraParams {
+		self.device.color_camera_params()
+	}
+
+	/// Get the parameters of the IR camera.
+	#[must_use]
+	pub fn ir_camera_params(&self) -> IrCameraParams {
+		self.device.ir_camera_params()
+	}
+
+	/// Set the device's depth processing configuration.
+	pub fn set_depth_config(&self, config: DepthConfig) {
+		self.device.set_depth_config(config);
+	}
+
+	/// Take the payload of the last panic in a frame listener, if any.
+	///
+	/// See [`Device::take_listener_panic`].
+	pub fn take_listener_panic(&mut self) -> Option<Box<dyn std::any::Any + Send>> {
+		self.device.take_listener_panic()
+	}
+
+	/// Counts of the frames of type `ty` that frame listeners have received.
+	///
+	/// See [`Device::frame_counts`].
+	#[must_use]
+	pub fn frame_counts(&self, ty: FrameType) -> FrameCounts {
+		self.device.frame_counts(ty)
+	}
+
+	/// Reset the counts of frames of all types to zero.
+	pub fn reset_frame_counts(&self) {
+		self.device.reset_frame_counts();
+	}
+
+	/// Stop the streams and data processing.
+	///
+	/// Unlike [`Device::stop`], this does not fail if a frame listener has panicked; use [`OpenDevice::take_listener_panic`] to check.
+	///
+	/// # Errors
+	///
+	/// Fails if the library returns an error.
+	pub fn stop(mut self) -> Result<OpenDevice, TransitionError<Self>> {
+		match self.device.stop() {
+			Ok(()) | Err(Error::ListenerPanicked) => Ok(OpenDevice {
+				device: self.device,
+			}),
+			Err(_) => Err(TransitionError { device: self }),
+		}
+	}
+
+	/// Stop the streams and close the device.
+	///
+	/// See [`Device::close`].
+	///
+	/// # Errors
+	///
+	/// Fails if the library returns an error.
+	pub fn close(self) -> Result<(), Error> {
+		self.device.close()
+	}
+}
+
+impl TryFrom<Device> for StreamingDevice {
+	type Error = Device;
+
+	/// Fails, giving back the device, if its streams are not running.
+	fn try_from(device: Device) -> Result<Self, Device> {
+		if device.started() {
+			Ok(Self { device })
+		} else {
+			Err(device)
+		}
+	}
+}
+
+impl From<StreamingDevice> for Device {
+	fn from(streaming: StreamingDevice) -> Self {
+		streaming.device
+	}
+}
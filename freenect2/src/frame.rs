@@ -1,58 +1,120 @@
-//! Provides [`Frame`], [`Type`], and [`Format`].
+//! Provides [`Frame`], [`Type`], [`Format`], and [`Status`], as well as typed views of the image data: [`DepthImage`], [`IrImage`], and [`ColorImage`].
+
+use std::ops::Deref;
+use std::ptr::NonNull;
+use std::slice::ChunksExact;
 
 use freenect2_sys as sys;
 
+/// The width of depth and IR frames, in pixels.
+pub const DEPTH_WIDTH: usize = 512;
+/// The height of depth and IR frames, in pixels.
+pub const DEPTH_HEIGHT: usize = 424;
+/// The width of color frames, in pixels.
+pub const COLOR_WIDTH: usize = 1920;
+/// The height of color frames, in pixels.
+pub const COLOR_HEIGHT: usize = 1080;
+
 /// A frame from one of the device's cameras.
+///
+/// The frame owns the buffer that libfreenect2 decoded it into, so receiving a frame does not copy its data.
 #[derive(Debug)]
 pub struct Frame {
+	native: NonNull<sys::Fn2NativeFrame>,
+	data: NonNull<u8>,
+	data_len: usize,
 	width: usize,
 	height: usize,
 	bytes_per_pixel: usize,
-	data: Box<[u8]>,
 	timestamp: u32,
 	sequence: u32,
 	exposure: f32,
 	gain: f32,
-	errors_occurred: bool,
+	gamma: f32,
+	status: Status,
 	format: Format,
 }
 
+// SAFETY: the frame has exclusive ownership of its buffer, which libfreenect2 does not access after handing it over.
+unsafe impl Send for Frame {}
+unsafe impl Sync for Frame {}
+
 impl Frame {
-	/// Convert from the unsafe equivalent.
+	/// Convert from the unsafe equivalent, taking ownership of it.
 	///
 	/// # Safety
 	///
-	/// This conversion is unsafe because it makes assumptions about the validity and size of the `data` pointer.
-	/// It should never be null.
+	/// `native` should point to a `libfreenect2::Frame` that is not used elsewhere, such as one passed to a frame listener that then returned `true`, or one obtained from `fn2_frame_new`.
+	/// It will be freed with `fn2_frame_free` when the frame is dropped.
+	///
+	/// `data` should be the data of `native`.
 	/// It should be aligned to at least 8-byte boundary.
 	/// If `format` is `Fn2FrameFormat_Raw`, it should be `bytes_per_pixel` bytes long, otherwise it should be `width * height * bytes_per_pixel` bytes long.
 	///
 	/// # Panics
 	///
-	/// Panics if the frame format is invalid.
+	/// Panics if the frame format is invalid or if `native` or `data` is null.
 	#[must_use]
 	pub unsafe fn from_sys(sys: sys::Fn2Frame) -> Self {
-		let format = sys.format.try_into().unwrap();
-
-		let data_len = match format {
-			Format::Raw => sys.bytes_per_pixel,
-			_ => sys.width * sys.height * sys.bytes_per_pixel,
-		};
-
-		Self {
+		let native = NonNull::new(sys.native).expect("frame is null");
+		// take ownership before anything can panic so that the frame is not leaked
+		let mut ret = Self {
+			native,
+			data: NonNull::dangling(),
+			data_len: 0,
 			width: sys.width,
 			height: sys.height,
 			bytes_per_pixel: sys.bytes_per_pixel,
-			data: Box::from_raw(std::ptr::slice_from_raw_parts_mut(sys.data, data_len)),
 			timestamp: sys.timestamp,
 			sequence: sys.sequence,
 			exposure: sys.exposure,
 			gain: sys.gain,
-			errors_occurred: sys.status > 0,
-			format,
+			gamma: sys.gamma,
+			status: sys.status.into(),
+			format: Format::Invalid,
+		};
+
+		ret.format = sys.format.try_into().unwrap();
+		ret.data = NonNull::new(sys.data).expect("frame data is null");
+		ret.data_len = match ret.format {
+			Format::Raw => sys.bytes_per_pixel,
+			_ => sys.width * sys.height * sys.bytes_per_pixel,
+		};
+		ret
+	}
+
+	/// Create a frame filled with zeros, to be written to by operations such as [registration](crate::registration).
+	///
+	/// The frame takes its timestamp and sequence number from `source`, the frame it is derived from.
+	pub(crate) fn zeroed(
+		source: &Frame,
+		width: usize,
+		height: usize,
+		bytes_per_pixel: usize,
+		format: Format,
+	) -> Self {
+		let mut ret = unsafe { Self::from_sys(sys::fn2_frame_new(width, height, bytes_per_pixel)) };
+		ret.format = format;
+		ret.timestamp = source.timestamp;
+		ret.sequence = source.sequence;
+		ret
+	}
+
+	/// Borrow the image data as the unsafe equivalent, for passing to the library to read.
+	pub(crate) fn view(&self) -> sys::Fn2FrameView {
+		sys::Fn2FrameView {
+			width: self.width,
+			height: self.height,
+			bytes_per_pixel: self.bytes_per_pixel,
+			data: self.data.as_ptr(),
 		}
 	}
 
+	/// Borrow the image data as the unsafe equivalent, for passing to the library to write.
+	pub(crate) fn view_mut(&mut self) -> sys::Fn2FrameView {
+		self.view()
+	}
+
 	/// The width of the frame, in pixels.
 	#[must_use]
 	pub fn width(&self) -> usize {
@@ -78,7 +140,7 @@ impl Frame {
 	/// It will be aligned to at least an 8-byte boundary.
 	#[must_use]
 	pub fn data(&self) -> &[u8] {
-		&self.data
+		unsafe { std::slice::from_raw_parts(self.data.as_ptr(), self.data_len) }
 	}
 
 	/// The data itself, mutably.
@@ -86,13 +148,15 @@ impl Frame {
 	/// It will be aligned to at least an 8-byte boundary.
 	#[must_use]
 	pub fn data_mut(&mut self) -> &mut [u8] {
-		&mut self.data
+		unsafe { std::slice::from_raw_parts_mut(self.data.as_ptr(), self.data_len) }
 	}
 
-	/// Consume the frame and return the owned data.
+	/// Consume the frame and return a copy of its data.
+	///
+	/// The buffer is owned by libfreenect2, so this copies it; use [`data`](Self::data) to avoid copying.
 	#[must_use]
 	pub fn into_data(self) -> Box<[u8]> {
-		self.data
+		self.data().into()
 	}
 
 	/// In units of 100 microseconds.
@@ -119,10 +183,22 @@ impl Frame {
 		self.gain
 	}
 
-	/// Indicates if any errors occurred.
+	/// From 1.0 (bright) to 6.4 (dark).
+	#[must_use]
+	pub fn gamma(&self) -> f32 {
+		self.gamma
+	}
+
+	/// Whether the frame was decoded successfully.
+	#[must_use]
+	pub fn status(&self) -> Status {
+		self.status
+	}
+
+	/// Indicates if any errors occurred, meaning that the [status](Self::status) is not [`Status::Ok`].
 	#[must_use]
 	pub fn errors_occurred(&self) -> bool {
-		self.errors_occurred
+		self.status != Status::Ok
 	}
 
 	/// The format of the image data returned by `data`.
@@ -130,6 +206,225 @@ impl Frame {
 	pub fn format(&self) -> Format {
 		self.format
 	}
+
+	/// Check that the frame has one of `formats`, the given dimensions, and 4 bytes per pixel, then reinterpret its data as pixels of type `P`.
+	fn pixels<P: bytemuck::Pod>(
+		&self,
+		formats: &[Format],
+		width: usize,
+		height: usize,
+	) -> Result<Image<'_, P>, ViewError> {
+		if !formats.contains(&self.format) {
+			return Err(ViewError::Format(self.format));
+		}
+		if (self.width, self.height, self.bytes_per_pixel) != (width, height, 4) {
+			return Err(ViewError::Dimensions {
+				width: self.width,
+				height: self.height,
+				bytes_per_pixel: self.bytes_per_pixel,
+			});
+		}
+		let pixels = bytemuck::try_cast_slice(self.data()).map_err(|_| ViewError::Misaligned)?;
+		Ok(Image {
+			width,
+			height,
+			pixels,
+		})
+	}
+
+	/// View the data of a depth frame.
+	///
+	/// # Errors
+	///
+	/// Fails if the frame is not 512 by 424 and in the Float format.
+	pub fn as_depth(&self) -> Result<DepthImage<'_>, ViewError> {
+		self
+			.pixels(&[Format::Float], DEPTH_WIDTH, DEPTH_HEIGHT)
+			.map(DepthImage)
+	}
+
+	/// View the data of an IR frame.
+	///
+	/// # Errors
+	///
+	/// Fails if the frame is not 512 by 424 and in the Float format.
+	pub fn as_ir(&self) -> Result<IrImage<'_>, ViewError> {
+		self
+			.pixels(&[Format::Float], DEPTH_WIDTH, DEPTH_HEIGHT)
+			.map(IrImage)
+	}
+
+	/// View the data of a color frame.
+	///
+	/// # Errors
+	///
+	/// Fails if the frame is not 1920 by 1080 and in the Bgrx or Rgbx format.
+	pub fn as_color(&self) -> Result<ColorImage<'_>, ViewError> {
+		let format = self.format;
+		self
+			.pixels(&[Format::Bgrx, Format::Rgbx], COLOR_WIDTH, COLOR_HEIGHT)
+			.map(|image| ColorImage { image, format })
+	}
+}
+
+impl Drop for Frame {
+	fn drop(&mut self) {
+		unsafe {
+			sys::fn2_frame_free(self.native.as_ptr());
+		}
+	}
+}
+
+/// Error returned when a [`Frame`] cannot be viewed as a particular kind of image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewError {
+	/// The frame has the wrong format.
+	Format(Format),
+	/// The frame has the wrong dimensions.
+	Dimensions {
+		/// The actual width of the frame.
+		width: usize,
+		/// The actual height of the frame.
+		height: usize,
+		/// The actual number of bytes per pixel.
+		bytes_per_pixel: usize,
+	},
+	/// The frame's data is not aligned to the pixel type.
+	Misaligned,
+}
+
+impl std::fmt::Display for ViewError {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Format(format) => write!(formatter, "frame has the wrong format ({format:?})"),
+			Self::Dimensions {
+				width,
+				height,
+				bytes_per_pixel,
+			} => write!(
+				formatter,
+				"frame has the wrong dimensions ({width}x{height} with {bytes_per_pixel} bytes per pixel)"
+			),
+			Self::Misaligned => formatter.write_str("frame data is misaligned"),
+		}
+	}
+}
+
+impl std::error::Error for ViewError {}
+
+/// A borrowed image with pixels of type `P`, stored row by row.
+#[derive(Debug, Clone, Copy)]
+pub struct Image<'a, P> {
+	width: usize,
+	height: usize,
+	pixels: &'a [P],
+}
+
+impl<'a, P: Copy> Image<'a, P> {
+	/// The width of the image, in pixels.
+	#[must_use]
+	pub fn width(&self) -> usize {
+		self.width
+	}
+
+	/// The height of the image, in pixels.
+	#[must_use]
+	pub fn height(&self) -> usize {
+		self.height
+	}
+
+	/// All pixels, row by row.
+	#[must_use]
+	pub fn pixels(&self) -> &'a [P] {
+		self.pixels
+	}
+
+	/// The pixel at column `x` and row `y`, or `None` if it is outside of the image.
+	#[must_use]
+	pub fn get(&self, x: usize, y: usize) -> Option<P> {
+		(x < self.width && y < self.height).then(|| self.pixels[y * self.width + x])
+	}
+
+	/// Iterate over the rows of the image, from top to bottom.
+	pub fn rows(&self) -> ChunksExact<'a, P> {
+		self.pixels.chunks_exact(self.width)
+	}
+}
+
+/// A view of a depth frame, in millimeters.
+#[derive(Debug, Clone, Copy)]
+pub struct DepthImage<'a>(Image<'a, f32>);
+
+impl<'a> Deref for DepthImage<'a> {
+	type Target = Image<'a, f32>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl DepthImage<'_> {
+	/// Whether `depth` is a valid measurement, rather than a non-positive, NaN, or infinite value indicating missing data.
+	#[must_use]
+	pub fn is_valid(depth: f32) -> bool {
+		depth.is_finite() && depth > 0.0
+	}
+
+	/// The depth at column `x` and row `y`, or `None` if it is outside of the image or invalid.
+	#[must_use]
+	pub fn get_valid(&self, x: usize, y: usize) -> Option<f32> {
+		self.get(x, y).filter(|&depth| Self::is_valid(depth))
+	}
+
+	/// Whether each pixel is valid, row by row.
+	pub fn valid_mask(&self) -> impl Iterator<Item = bool> + '_ {
+		self.pixels().iter().map(|&depth| Self::is_valid(depth))
+	}
+}
+
+/// A view of an IR frame, with values ranging from 0.0 to 65535.0.
+#[derive(Debug, Clone, Copy)]
+pub struct IrImage<'a>(Image<'a, f32>);
+
+impl<'a> Deref for IrImage<'a> {
+	type Target = Image<'a, f32>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+/// A view of a color frame, with 4 bytes per pixel in the order given by [`format`](Self::format).
+#[derive(Debug, Clone, Copy)]
+pub struct ColorImage<'a> {
+	image: Image<'a, [u8; 4]>,
+	format: Format,
+}
+
+impl<'a> Deref for ColorImage<'a> {
+	type Target = Image<'a, [u8; 4]>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.image
+	}
+}
+
+impl ColorImage<'_> {
+	/// The order of the bytes in each pixel, either Bgrx or Rgbx.
+	#[must_use]
+	pub fn format(&self) -> Format {
+		self.format
+	}
+
+	/// The red, green, and blue components of the pixel at column `x` and row `y`, or `None` if it is outside of the image.
+	#[must_use]
+	pub fn get_rgb(&self, x: usize, y: usize) -> Option<[u8; 3]> {
+		let [first, green, third, _] = self.get(x, y)?;
+		Some(match self.format {
+			Format::Bgrx => [third, green, first],
+			_ => [first, green, third],
+		})
+	}
 }
 
 /// The possible image formats of a frame's data.
@@ -149,6 +444,39 @@ pub enum Format {
 	Rgbx,
 }
 
+/// Whether a frame was decoded successfully, from the status word that libfreenect2 gives it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+	/// The frame was decoded successfully.
+	Ok,
+	/// The packet processor failed to decode the frame, so its data is incomplete or garbage.
+	///
+	/// This happens when a JPEG packet from the color camera is corrupt, often because of USB transfer errors, or when a depth processor that runs on the GPU fails.
+	DecodeFailed,
+	/// A status that libfreenect2 did not use when this was written.
+	Unknown(u32),
+}
+
+impl From<u32> for Status {
+	fn from(raw: u32) -> Self {
+		match raw {
+			0 => Self::Ok,
+			1 => Self::DecodeFailed,
+			raw => Self::Unknown(raw),
+		}
+	}
+}
+
+impl From<Status> for u32 {
+	fn from(status: Status) -> Self {
+		match status {
+			Status::Ok => 0,
+			Status::DecodeFailed => 1,
+			Status::Unknown(raw) => raw,
+		}
+	}
+}
+
 /// Error returned when converting from unsafe equivalent if the data represents an invalid variant.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct UnknownFormat;
@@ -198,12 +526,12 @@ pub enum Type {
 	Color,
 	/// From the depth camera.
 	///
-	/// The image will be 512 by 424 and in the Float format, with values ranging from 0.0 to 65536.0.
+	/// The image will be 512 by 424 and in the Float format, in millimeters.
+	/// Non-positive, NaN, and infinities represent invalid or missing data.
 	Depth,
 	/// From the IR camera.
 	///
-	/// The image will be 512 by 424 and in the Float format, in millimeters.
-	/// Non-positive, NaN, and infinities represent invalid or missing data.
+	/// The image will be 512 by 424 and in the Float format, with values ranging from 0.0 to 65535.0.
 	Ir,
 }
 
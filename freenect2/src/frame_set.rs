@@ -0,0 +1,185 @@
+//! Provides [`FrameSync`], which groups frames from different cameras that were captured at the same instant into [`FrameSet`]s.
+//!
+//! The color camera and the depth camera are not triggered together and their frames are decoded on different threads, so frames of different types arrive independently and in no particular order.
+//! Frames are matched by their timestamps, which share a clock.
+//! Depth and IR frames are decoded from the same packets, so they always have the same timestamp.
+
+use crate::{Frame, FrameType};
+
+const ALL_TYPES: [FrameType; 3] = [FrameType::Color, FrameType::Ir, FrameType::Depth];
+
+/// The signed difference `later - earlier` between two timestamps, allowing for them wrapping around.
+#[allow(clippy::cast_possible_wrap)]
+fn difference(earlier: u32, later: u32) -> i32 {
+	later.wrapping_sub(earlier) as i32
+}
+
+/// A group of frames from the same instant.
+#[derive(Debug, Default)]
+pub struct FrameSet {
+	/// The color frame.
+	pub color: Option<Frame>,
+	/// The IR frame.
+	pub ir: Option<Frame>,
+	/// The depth frame.
+	pub depth: Option<Frame>,
+}
+
+impl FrameSet {
+	fn slot(&mut self, ty: FrameType) -> &mut Option<Frame> {
+		match ty {
+			FrameType::Color => &mut self.color,
+			FrameType::Ir => &mut self.ir,
+			FrameType::Depth => &mut self.depth,
+		}
+	}
+
+	/// Iterate over the frames in the set.
+	pub fn frames(&self) -> impl Iterator<Item = &Frame> {
+		[&self.color, &self.ir, &self.depth]
+			.into_iter()
+			.filter_map(Option::as_ref)
+	}
+
+	/// Whether the set contains no frames.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.frames().next().is_none()
+	}
+
+	/// The earliest timestamp of the frames in the set, or `None` if it is empty.
+	#[must_use]
+	pub fn timestamp(&self) -> Option<u32> {
+		self.frames().map(Frame::timestamp).min()
+	}
+}
+
+/// A result of [`FrameSync::push`].
+#[derive(Debug)]
+pub enum Synced {
+	/// A frame of each of the requested types, all from the same instant.
+	Complete(FrameSet),
+	/// Frames that could not be matched with frames of the other requested types and were discarded.
+	///
+	/// This happens when frames are dropped by the device or arrive too late.
+	Partial(FrameSet),
+}
+
+/// Tunable parameters for [`FrameSync`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncConfig {
+	/// Whether sets should include a color frame.
+	pub color: bool,
+	/// Whether sets should include an IR frame.
+	pub ir: bool,
+	/// Whether sets should include a depth frame.
+	pub depth: bool,
+	/// The largest difference between the timestamps of frames in the same set, in units of 100 microseconds.
+	pub tolerance: u32,
+}
+
+impl Default for SyncConfig {
+	/// Sets of all three types, with a tolerance of half of the cameras' frame interval, so that each frame can only match the closest frame of each other type.
+	fn default() -> Self {
+		Self {
+			color: true,
+			ir: true,
+			depth: true,
+			tolerance: 166,
+		}
+	}
+}
+
+impl SyncConfig {
+	fn wants(self, ty: FrameType) -> bool {
+		match ty {
+			FrameType::Color => self.color,
+			FrameType::Ir => self.ir,
+			FrameType::Depth => self.depth,
+		}
+	}
+}
+
+/// Groups frames that arrive independently into [`FrameSet`]s.
+///
+/// The latest unmatched frame of each type is kept until the frames of the other types from the same instant arrive.
+#[derive(Debug)]
+pub struct FrameSync {
+	config: SyncConfig,
+	pending: FrameSet,
+}
+
+impl FrameSync {
+	/// Create a synchronizer for the frame types requested by `config`.
+	///
+	/// # Panics
+	///
+	/// Panics if `config` does not request any frame types.
+	#[must_use]
+	pub fn new(config: SyncConfig) -> Self {
+		assert!(
+			config.color || config.ir || config.depth,
+			"at least one frame type must be requested"
+		);
+
+		Self {
+			config,
+			pending: FrameSet::default(),
+		}
+	}
+
+	/// The parameters that this was created with.
+	#[must_use]
+	pub fn config(&self) -> SyncConfig {
+		self.config
+	}
+
+	/// Add a frame, passing any sets that result to `emit`.
+	///
+	/// Frames of types that were not requested are dropped.
+	/// If frames have to be discarded to make room for `frame`, they are emitted first as [`Synced::Partial`], then the set that `frame` completes, if any, is emitted as [`Synced::Complete`].
+	pub fn push(&mut self, frame: Frame, ty: FrameType, mut emit: impl FnMut(Synced)) {
+		if !self.config.wants(ty) {
+			return;
+		}
+
+		let timestamp = frame.timestamp();
+		let tolerance = i64::from(self.config.tolerance);
+
+		let mut discarded = FrameSet::default();
+		// a newer frame of the same type replaces the pending one
+		*discarded.slot(ty) = self.pending.slot(ty).replace(frame);
+		// pending frames that are too old to match this one are also too old to match any later frame
+		for other in ALL_TYPES {
+			let slot = self.pending.slot(other);
+			if slot
+				.as_ref()
+				.is_some_and(|pending| i64::from(difference(pending.timestamp(), timestamp)) > tolerance)
+			{
+				*discarded.slot(other) = slot.take();
+			}
+		}
+		if !discarded.is_empty() {
+			emit(Synced::Partial(discarded));
+		}
+
+		let all_present = ALL_TYPES
+			.into_iter()
+			.all(|ty| !self.config.wants(ty) || self.pending.slot(ty).is_some());
+		let (earliest, latest) = self
+			.pending
+			.frames()
+			.map(|pending| i64::from(difference(timestamp, pending.timestamp())))
+			.fold((0, 0), |(earliest, latest), offset| {
+				(earliest.min(offset), latest.max(offset))
+			});
+		if all_present && latest - earliest <= tolerance {
+			emit(Synced::Complete(std::mem::take(&mut self.pending)));
+		}
+	}
+
+	/// Remove and return the frames that have not been matched yet.
+	pub fn flush(&mut self) -> FrameSet {
+		std::mem::take(&mut self.pending)
+	}
+}
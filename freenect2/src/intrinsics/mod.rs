@@ -0,0 +1,356 @@
+//! Provides [`Intrinsics`], which converts camera parameters to and from the YAML calibration files of OpenCV and ROS.
+//!
+//! Both formats store a camera matrix and the five distortion coefficients of the `plumb_bob` model, `[k1, k2, p1, p2, k3]`, along with the size of the image.
+//! OpenCV's are written by `cv::FileStorage`, as in its camera calibration sample, and ROS's are the `camera_info` files of `camera_calibration_parsers`.
+//!
+//! [`IrCameraParams`] convert to and from [`Intrinsics`] without loss.
+//! Only the focal length and principal point of [`ColorCameraParams`] can be expressed:
+//! `shift_d`, `shift_m`, and the `mx_*` and `my_*` coefficients of the polynomial that maps depth pixels to color pixels are lost, and since libfreenect2 does not model the distortion of the color camera, it is written as zero.
+//! [`Intrinsics::to_color_camera_params`] therefore takes the parameters to keep from elsewhere, usually the device.
+//!
+//! Only the subset of YAML that these tools write can be read.
+//! In particular, sequences must be written in the `[a, b, c]` style.
+
+mod yaml;
+
+use std::fmt::Write;
+
+use self::yaml::Document;
+use crate::device::{ColorCameraParams, IrCameraParams};
+
+/// The size of IR and depth images, in pixels.
+const IR_SIZE: [u32; 2] = [512, 424];
+/// The size of color images, in pixels.
+const COLOR_SIZE: [u32; 2] = [1920, 1080];
+
+/// Errors that can occur while reading [`Intrinsics`] or converting them to camera parameters.
+#[derive(Debug)]
+pub enum IntrinsicsError {
+	/// The YAML could not be parsed.
+	Syntax {
+		/// The line on which the error occurred, starting at 1.
+		line: usize,
+		/// What is wrong.
+		reason: &'static str,
+	},
+	/// A required key is missing, such as `camera_matrix.data`.
+	Missing(String),
+	/// The value of a key is of the wrong type or size.
+	Invalid(String),
+	/// The distortion model is not `plumb_bob`, or has more coefficients than it.
+	UnsupportedDistortionModel(String),
+	/// The image size does not match the camera that the parameters are being converted for.
+	WrongSize {
+		/// The size of the camera's images.
+		expected: [u32; 2],
+		/// The size in the calibration.
+		actual: [u32; 2],
+	},
+}
+
+impl std::fmt::Display for IntrinsicsError {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Syntax { line, reason } => write!(formatter, "line {line}: {reason}"),
+			Self::Missing(key) => write!(formatter, "missing `{key}`"),
+			Self::Invalid(key) => write!(formatter, "invalid `{key}`"),
+			Self::UnsupportedDistortionModel(model) => {
+				write!(formatter, "unsupported distortion model `{model}`")
+			}
+			Self::WrongSize {
+				expected: [expected_width, expected_height],
+				actual: [actual_width, actual_height],
+			} => write!(
+				formatter,
+				"image size is {actual_width}x{actual_height}, but the camera's is {expected_width}x{expected_height}"
+			),
+		}
+	}
+}
+
+impl std::error::Error for IntrinsicsError {}
+
+/// The intrinsic parameters of a camera, in the form used by OpenCV and ROS.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Intrinsics {
+	/// The width and height of the image, in pixels.
+	pub size: [u32; 2],
+	/// The camera matrix, `[[fx, 0, cx], [0, fy, cy], [0, 0, 1]]`.
+	pub camera_matrix: [[f32; 3]; 3],
+	/// The distortion coefficients `[k1, k2, p1, p2, k3]`.
+	pub distortion: [f32; 5],
+}
+
+impl Intrinsics {
+	fn new(size: [u32; 2], [fx, fy, cx, cy]: [f32; 4], distortion: [f32; 5]) -> Self {
+		Self {
+			size,
+			camera_matrix: [[fx, 0.0, cx], [0.0, fy, cy], [0.0, 0.0, 1.0]],
+			distortion,
+		}
+	}
+
+	fn check_size(self, expected: [u32; 2]) -> Result<(), IntrinsicsError> {
+		if self.size == expected {
+			Ok(())
+		} else {
+			Err(IntrinsicsError::WrongSize {
+				expected,
+				actual: self.size,
+			})
+		}
+	}
+
+	/// Convert to parameters of the IR camera.
+	///
+	/// The skew of the camera matrix is ignored.
+	///
+	/// # Errors
+	///
+	/// Fails if the size is not that of IR images, 512x424.
+	pub fn to_ir_camera_params(self) -> Result<IrCameraParams, IntrinsicsError> {
+		self.check_size(IR_SIZE)?;
+		let [[fx, _, cx], [_, fy, cy], _] = self.camera_matrix;
+		let [k1, k2, p1, p2, k3] = self.distortion;
+		Ok(IrCameraParams {
+			fx,
+			fy,
+			cx,
+			cy,
+			k1,
+			k2,
+			k3,
+			p1,
+			p2,
+		})
+	}
+
+	/// Replace the focal length and principal point of `params` with these, keeping the rest of its parameters.
+	///
+	/// The skew of the camera matrix and the distortion coefficients are ignored.
+	///
+	/// # Errors
+	///
+	/// Fails if the size is not that of color images, 1920x1080.
+	pub fn to_color_camera_params(
+		self,
+		params: ColorCameraParams,
+	) -> Result<ColorCameraParams, IntrinsicsError> {
+		self.check_size(COLOR_SIZE)?;
+		let [[fx, _, cx], [_, fy, cy], _] = self.camera_matrix;
+		Ok(ColorCameraParams {
+			fx,
+			fy,
+			cx,
+			cy,
+			..params
+		})
+	}
+
+	/// Write in the YAML format of OpenCV's `FileStorage`, with the keys `image_width`, `image_height`, `camera_matrix`, and `distortion_coefficients`.
+	#[must_use]
+	pub fn to_opencv_yaml(self) -> String {
+		let [width, height] = self.size;
+		let mut yaml = format!("%YAML:1.0\n---\nimage_width: {width}\nimage_height: {height}\n");
+		write_matrix(
+			&mut yaml,
+			"camera_matrix",
+			true,
+			3,
+			&self.camera_matrix.concat(),
+		);
+		write_matrix(
+			&mut yaml,
+			"distortion_coefficients",
+			true,
+			1,
+			&self.distortion,
+		);
+		yaml
+	}
+
+	/// Read from the YAML format of OpenCV's `FileStorage`, as written by [`to_opencv_yaml`](Self::to_opencv_yaml).
+	///
+	/// Four distortion coefficients are accepted, in which case `k3` is zero, as are more than five if the rest are zero.
+	///
+	/// # Errors
+	///
+	/// Fails if the YAML cannot be parsed, a key is missing or invalid, or the distortion coefficients cannot be expressed in the `plumb_bob` model.
+	pub fn from_opencv_yaml(yaml: &str) -> Result<Self, IntrinsicsError> {
+		let document = Document::parse(yaml)?;
+		Self::from_document(&document)
+	}
+
+	/// Write in the YAML format of ROS's `camera_info` files.
+	///
+	/// The rectification matrix is the identity and the projection matrix is the camera matrix, as for any monocular camera.
+	/// The camera name is written as a double-quoted scalar, so it may contain any characters.
+	#[must_use]
+	pub fn to_ros_yaml(self, camera_name: &str) -> String {
+		let [width, height] = self.size;
+		let [[fx, _, cx], [_, fy, cy], _] = self.camera_matrix;
+		let mut yaml = format!(
+			"image_width: {width}\nimage_height: {height}\ncamera_name: {}\n",
+			quote(camera_name)
+		);
+		write_matrix(
+			&mut yaml,
+			"camera_matrix",
+			false,
+			3,
+			&self.camera_matrix.concat(),
+		);
+		yaml.push_str("distortion_model: plumb_bob\n");
+		write_matrix(
+			&mut yaml,
+			"distortion_coefficients",
+			false,
+			1,
+			&self.distortion,
+		);
+		write_matrix(
+			&mut yaml,
+			"rectification_matrix",
+			false,
+			3,
+			&[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+		);
+		write_matrix(
+			&mut yaml,
+			"projection_matrix",
+			false,
+			3,
+			&[fx, 0.0, cx, 0.0, 0.0, fy, cy, 0.0, 0.0, 0.0, 1.0, 0.0],
+		);
+		yaml
+	}
+
+	/// Read from the YAML format of ROS's `camera_info` files, as written by [`to_ros_yaml`](Self::to_ros_yaml).
+	///
+	/// The camera name, rectification matrix, and projection matrix are ignored.
+	///
+	/// # Errors
+	///
+	/// Fails if the YAML cannot be parsed, a key is missing or invalid, or the distortion model is not `plumb_bob`.
+	pub fn from_ros_yaml(yaml: &str) -> Result<Self, IntrinsicsError> {
+		let document = Document::parse(yaml)?;
+		let model = document.scalar("distortion_model")?;
+		if model != "plumb_bob" {
+			return Err(IntrinsicsError::UnsupportedDistortionModel(
+				model.to_owned(),
+			));
+		}
+		Self::from_document(&document)
+	}
+
+	fn from_document(document: &Document) -> Result<Self, IntrinsicsError> {
+		let size = [
+			document.parse_scalar("image_width")?,
+			document.parse_scalar("image_height")?,
+		];
+
+		let camera_matrix = match document.matrix("camera_matrix")? {
+			(3, 3, data) => [0, 1, 2].map(|row| [0, 1, 2].map(|col| data[row * 3 + col])),
+			_ => return Err(IntrinsicsError::Invalid("camera_matrix".to_owned())),
+		};
+
+		let (rows, cols, mut coefficients) = document.matrix("distortion_coefficients")?;
+		if (rows != 1 && cols != 1) || coefficients.len() < 4 {
+			return Err(IntrinsicsError::Invalid(
+				"distortion_coefficients".to_owned(),
+			));
+		}
+		coefficients.resize(coefficients.len().max(5), 0.0);
+		if coefficients[5..]
+			.iter()
+			.any(|&coefficient| coefficient != 0.0)
+		{
+			return Err(IntrinsicsError::UnsupportedDistortionModel(format!(
+				"{} coefficients",
+				coefficients.len()
+			)));
+		}
+		let distortion = [0, 1, 2, 3, 4].map(|index| coefficients[index]);
+
+		Ok(Self {
+			size,
+			camera_matrix,
+			distortion,
+		})
+	}
+}
+
+impl From<IrCameraParams> for Intrinsics {
+	fn from(params: IrCameraParams) -> Self {
+		Self::new(
+			IR_SIZE,
+			[params.fx, params.fy, params.cx, params.cy],
+			[params.k1, params.k2, params.p1, params.p2, params.k3],
+		)
+	}
+}
+
+/// Only the focal length and principal point are converted; see the [module documentation](self).
+impl From<ColorCameraParams> for Intrinsics {
+	fn from(params: ColorCameraParams) -> Self {
+		Self::new(
+			COLOR_SIZE,
+			[params.fx, params.fy, params.cx, params.cy],
+			[0.0; 5],
+		)
+	}
+}
+
+/// Write `value` as a YAML double-quoted scalar, escaping quotes, backslashes, and control characters.
+fn quote(value: &str) -> String {
+	let mut quoted = String::with_capacity(value.len() + 2);
+	quoted.push('"');
+	for c in value.chars() {
+		match c {
+			'"' => quoted.push_str("\\\""),
+			'\\' => quoted.push_str("\\\\"),
+			'\n' => quoted.push_str("\\n"),
+			'\r' => quoted.push_str("\\r"),
+			'\t' => quoted.push_str("\\t"),
+			c if c.is_control() => write!(quoted, "\\u{:04x}", u32::from(c)).unwrap(),
+			c => quoted.push(c),
+		}
+	}
+	quoted.push('"');
+	quoted
+}
+
+/// Append the matrix `name` with `rows` rows and the elements `data`, as an `!!opencv-matrix` of doubles if `opencv` is set.
+///
+/// Non-finite elements are written as YAML's `.nan` and `.inf`, or as `.Nan` and `.Inf` like OpenCV does.
+fn write_matrix(yaml: &mut String, name: &str, opencv: bool, rows: usize, data: &[f32]) {
+	let data: Vec<_> = data
+		.iter()
+		.map(|&element| {
+			let (nan, inf) = if opencv {
+				(".Nan", ".Inf")
+			} else {
+				(".nan", ".inf")
+			};
+			if element.is_nan() {
+				nan.to_owned()
+			} else if element.is_infinite() {
+				format!("{}{inf}", if element < 0.0 { "-" } else { "" })
+			} else {
+				format!("{element:?}")
+			}
+		})
+		.collect();
+	let (tag, indent, dt) = if opencv {
+		(" !!opencv-matrix", "   ", "   dt: d\n")
+	} else {
+		("", "  ", "")
+	};
+	writeln!(
+		yaml,
+		"{name}:{tag}\n{indent}rows: {rows}\n{indent}cols: {cols}\n{dt}{indent}data: [{data}]",
+		cols = data.len() / rows,
+		data = data.join(", "),
+	)
+	.unwrap();
+}
@@ -0,0 +1,233 @@
+//! Reads the small subset of YAML that OpenCV's `FileStorage` and ROS's `camera_calibration_parsers` write.
+//!
+//! A document is a mapping whose values are scalars, flow sequences of scalars such as `[1, 2, 3]`, which may span several lines, or mappings of those.
+//! Tags such as `!!opencv-matrix` and directives such as `%YAML:1.0` are ignored.
+//! Double-quoted scalars may contain escape sequences, and matrix elements may be `.nan` or `.inf` in any case, as OpenCV writes `.Nan` and `.Inf`.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use super::IntrinsicsError;
+
+#[derive(Debug)]
+enum Value {
+	Scalar(String),
+	Sequence(Vec<String>),
+}
+
+/// The values of a document, keyed by their path, such as `camera_matrix.data`.
+#[derive(Debug)]
+pub(super) struct Document(HashMap<String, Value>);
+
+impl Document {
+	pub(super) fn parse(yaml: &str) -> Result<Self, IntrinsicsError> {
+		let mut values = HashMap::new();
+		// the key and indentation of the mapping that more indented lines belong to
+		let mut parent: Option<(String, usize)> = None;
+		let mut lines = yaml.lines().enumerate();
+		while let Some((index, line)) = lines.next() {
+			let syntax = |reason| IntrinsicsError::Syntax {
+				line: index + 1,
+				reason,
+			};
+
+			let line = strip_comment(line).trim_end();
+			let content = line.trim_start();
+			if content.is_empty() || content.starts_with('%') || content == "---" || content == "..." {
+				continue;
+			}
+			let indent = line.len() - content.len();
+
+			let (key, rest) = content
+				.split_once(':')
+				.ok_or_else(|| syntax("expected `key: value`"))?;
+			let key = unquote(key.trim()).map_err(syntax)?;
+			let mut rest = rest.trim_start();
+			if rest.starts_with('!') {
+				rest = rest
+					.split_once(char::is_whitespace)
+					.map_or("", |(_, rest)| rest.trim_start());
+			}
+
+			let path = match &parent {
+				Some((parent, parent_indent)) if indent > *parent_indent => format!("{parent}.{key}"),
+				_ => {
+					parent = None;
+					key
+				}
+			};
+
+			let value = if rest.is_empty() {
+				if parent.is_some() {
+					return Err(syntax("mappings are nested too deeply"));
+				}
+				parent = Some((path, indent));
+				continue;
+			} else if let Some(sequence) = rest.strip_prefix('[') {
+				let mut sequence = sequence.to_owned();
+				while !sequence.contains(']') {
+					let (_, next) = lines
+						.next()
+						.ok_or_else(|| syntax("sequence is not terminated"))?;
+					sequence.push(' ');
+					sequence.push_str(strip_comment(next));
+				}
+				let (items, after) = sequence.split_once(']').unwrap();
+				if !after.trim().is_empty() {
+					return Err(syntax("unexpected text after sequence"));
+				}
+				Value::Sequence(
+					items
+						.split(',')
+						.map(str::trim)
+						.filter(|item| !item.is_empty())
+						.map(unquote)
+						.collect::<Result<_, _>>()
+						.map_err(syntax)?,
+				)
+			} else {
+				Value::Scalar(unquote(rest).map_err(syntax)?)
+			};
+			values.insert(path, value);
+		}
+		Ok(Self(values))
+	}
+
+	/// Get the scalar at `path`.
+	pub(super) fn scalar(&self, path: &str) -> Result<&str, IntrinsicsError> {
+		match self.0.get(path) {
+			Some(Value::Scalar(value)) => Ok(value),
+			Some(Value::Sequence(_)) => Err(IntrinsicsError::Invalid(path.to_owned())),
+			None => Err(IntrinsicsError::Missing(path.to_owned())),
+		}
+	}
+
+	/// Parse the scalar at `path`.
+	pub(super) fn parse_scalar<T: FromStr>(&self, path: &str) -> Result<T, IntrinsicsError> {
+		self
+			.scalar(path)?
+			.parse()
+			.map_err(|_| IntrinsicsError::Invalid(path.to_owned()))
+	}
+
+	/// Get the elements of the matrix `name`, which has `rows`, `cols`, and `data` keys, in row-major order.
+	pub(super) fn matrix(&self, name: &str) -> Result<(usize, usize, Vec<f32>), IntrinsicsError> {
+		let rows = self.parse_scalar(&format!("{name}.rows"))?;
+		let cols = self.parse_scalar(&format!("{name}.cols"))?;
+		let path = format!("{name}.data");
+		let data = match self.0.get(&path) {
+			Some(Value::Sequence(data)) => data
+				.iter()
+				.map(|element| parse_float(element))
+				.collect::<Option<Vec<f32>>>()
+				.filter(|data| data.len() == rows * cols),
+			Some(Value::Scalar(_)) => None,
+			None => return Err(IntrinsicsError::Missing(path)),
+		};
+		data
+			.map(|data| (rows, cols, data))
+			.ok_or(IntrinsicsError::Invalid(path))
+	}
+}
+
+/// Remove a comment, which starts with `#` at the start of the line or after whitespace, outside of quotes.
+fn strip_comment(line: &str) -> &str {
+	let mut quote = None;
+	let mut escaped = false;
+	let mut previous = ' ';
+	for (index, c) in line.char_indices() {
+		match quote {
+			Some('"') if escaped => escaped = false,
+			Some('"') if c == '\\' => escaped = true,
+			Some(open) if c == open => quote = None,
+			None if c == '"' || c == '\'' => quote = Some(c),
+			None if c == '#' && previous.is_whitespace() => return &line[..index],
+			_ => (),
+		}
+		previous = c;
+	}
+	line
+}
+
+/// Remove the quotes around a scalar, replacing escape sequences in double quotes and doubled quotes in single quotes.
+fn unquote(value: &str) -> Result<String, &'static str> {
+	if let Some(quoted) = value
+		.strip_prefix('"')
+		.and_then(|value| value.strip_suffix('"'))
+	{
+		unescape(quoted)
+	} else if let Some(quoted) = value
+		.strip_prefix('\'')
+		.and_then(|value| value.strip_suffix('\''))
+	{
+		Ok(quoted.replace("''", "'"))
+	} else {
+		Ok(value.to_owned())
+	}
+}
+
+/// Replace the escape sequences of a double-quoted scalar.
+fn unescape(quoted: &str) -> Result<String, &'static str> {
+	const INVALID: &str = "invalid escape sequence";
+	let mut unescaped = String::with_capacity(quoted.len());
+	let mut chars = quoted.chars();
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			unescaped.push(c);
+			continue;
+		}
+		let c = match chars.next().ok_or(INVALID)? {
+			'0' => '\0',
+			'a' => '\x07',
+			'b' => '\x08',
+			't' | '\t' => '\t',
+			'n' => '\n',
+			'v' => '\x0b',
+			'f' => '\x0c',
+			'r' => '\r',
+			'e' => '\x1b',
+			'N' => '\u{85}',
+			'_' => '\u{a0}',
+			'L' => '\u{2028}',
+			'P' => '\u{2029}',
+			c @ (' ' | '"' | '/' | '\\') => c,
+			kind @ ('x' | 'u' | 'U') => {
+				let digits = match kind {
+					'x' => 2,
+					'u' => 4,
+					_ => 8,
+				};
+				let code: String = chars.by_ref().take(digits).collect();
+				if code.len() != digits || !code.chars().all(|c| c.is_ascii_hexdigit()) {
+					return Err(INVALID);
+				}
+				u32::from_str_radix(&code, 16)
+					.ok()
+					.and_then(char::from_u32)
+					.ok_or(INVALID)?
+			}
+			_ => return Err(INVALID),
+		};
+		unescaped.push(c);
+	}
+	Ok(unescaped)
+}
+
+/// Parse a number, accepting YAML's `.nan`, `.inf`, and `-.inf` in any case.
+fn parse_float(value: &str) -> Option<f32> {
+	let (negative, magnitude) = match value.strip_prefix('-') {
+		Some(magnitude) => (true, magnitude),
+		None => (false, value.strip_prefix('+').unwrap_or(value)),
+	};
+	if magnitude.eq_ignore_ascii_case(".nan") {
+		Some(f32::NAN)
+	} else if magnitude.eq_ignore_ascii_case(".inf") {
+		Some(if negative {
+			f32::NEG_INFINITY
+		} else {
+			f32::INFINITY
+		})
+	} else {
+		value.parse().ok()
+	}
+}
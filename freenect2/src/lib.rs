@@ -3,6 +3,14 @@
 //! A safe wrapper around [libfreenect2](https://github.com/OpenKinect/libfreenect2).
 //!
 //! To get started, create a [`Context`] which can be used to discover and open [Device]s.
+//! Frames can be saved with a [`Recorder`](recording::Recorder) and replayed without a Kinect by a [`PlaybackDevice`](device::PlaybackDevice).
+//! Camera parameters can be exchanged with OpenCV and ROS through [`intrinsics`].
+//!
+//! ## Features
+//!
+//! - `futures`: adds `Device::frame_stream`, which receives frames as a [`futures_core::Stream`](https://docs.rs/futures-core/0.3/futures_core/stream/trait.Stream.html).
+//! - `mock`: replaces libfreenect2 with fake devices that produce synthetic or recorded frames, for testing without a Kinect. They are configured through [`mock`].
+//! - `serde`: implements `Serialize` and `Deserialize` for the camera parameters and [`DepthConfig`](device::DepthConfig), and adds [`calibration`], which saves and loads the camera parameters of a device as JSON or TOML.
 
 #![deny(
 	absolute_paths_not_starting_with_crate,
@@ -29,23 +37,38 @@
 #![allow(clippy::let_underscore_drop)]
 
 use std::os::raw::c_void;
+use std::panic::AssertUnwindSafe;
 
 use freenect2_sys as sys;
 
+#[cfg(feature = "serde")]
+pub mod calibration;
 pub mod context;
 pub mod device;
 pub mod frame;
-mod logger;
+pub mod frame_set;
+pub mod intrinsics;
+pub mod logger;
+pub mod pipeline;
+pub mod recording;
+pub mod registration;
+
+#[cfg(feature = "mock")]
+pub use freenect2_sys::mock;
 
-pub use context::Context;
+pub use context::{Context, DeviceInfo};
 pub use device::Device;
-pub use frame::{Format as FrameFormat, Frame, Type as FrameType};
+pub use frame::{Format as FrameFormat, Frame, Status as FrameStatus, Type as FrameType};
+pub use pipeline::PacketPipeline;
 
 unsafe extern "C" fn string_closure(user_data: *mut c_void, borrowed: sys::Fn2RustyBorrowedString) {
-	let s = &mut *user_data.cast::<String>();
-	*s = String::from_utf8_lossy(std::slice::from_raw_parts(
-		borrowed.data.cast(),
-		borrowed.len,
-	))
-	.into_owned();
+	// unwinding into libfreenect2 is undefined behavior; if this panics, the string is left empty
+	let _ = std::panic::catch_unwind(AssertUnwindSafe(|| {
+		let s = &mut *user_data.cast::<String>();
+		*s = String::from_utf8_lossy(std::slice::from_raw_parts(
+			borrowed.data.cast(),
+			borrowed.len,
+		))
+		.into_owned();
+	}));
 }
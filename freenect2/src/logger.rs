@@ -1,14 +1,113 @@
+//! Routes libfreenect2's log messages to the [`log`] crate.
+//!
+//! Messages are logged with the target `libfreenect2::Component`, where `Component` is the part of libfreenect2 that logged it, such as `libfreenect2::Freenect2Impl`.
+//! Messages that do not name a component have the target `libfreenect2`.
+//!
+//! libfreenect2 only formats messages up to the [native level](set_native_level), which follows [`log::max_level`] by default.
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use freenect2_sys as sys;
+use log::LevelFilter;
+
+/// Zero to follow [`log::max_level`], or one more than the level set with [`set_native_level`].
+static NATIVE_LEVEL: AtomicUsize = AtomicUsize::new(0);
+
+/// Set the most verbose level of messages that libfreenect2 logs, or `None` to follow [`log::max_level`].
+///
+/// Messages that are more verbose are not even formatted, which saves work compared to filtering them in the logger.
+pub fn set_native_level(level: Option<LevelFilter>) {
+	NATIVE_LEVEL.store(
+		level.map_or(0, |level| level as usize + 1),
+		Ordering::Relaxed,
+	);
+}
+
+/// The most verbose level of messages that libfreenect2 logs.
+#[must_use]
+pub fn native_level() -> LevelFilter {
+	match NATIVE_LEVEL.load(Ordering::Relaxed) {
+		0 => log::max_level(),
+		1 => LevelFilter::Off,
+		2 => LevelFilter::Error,
+		3 => LevelFilter::Warn,
+		4 => LevelFilter::Info,
+		5 => LevelFilter::Debug,
+		_ => LevelFilter::Trace,
+	}
+}
+
+/// The target of a message: `libfreenect2::Component` if it starts with `[Component]`, or `libfreenect2` otherwise.
+fn target(message: &str) -> String {
+	let component = message
+		.strip_prefix('[')
+		.and_then(|rest| rest.split_once(']'))
+		.map(|(component, _)| component)
+		.filter(|component| {
+			!component.is_empty()
+				&& component
+					.chars()
+					.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+		});
+	match component {
+		Some(component) => format!("libfreenect2::{component}"),
+		None => "libfreenect2".to_owned(),
+	}
+}
+
+thread_local! {
+	/// The warnings and errors logged on this thread during [`capture`], or `None` if not capturing.
+	static CAPTURED: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// Call `f`, collecting the warnings and errors that libfreenect2 logs on this thread meanwhile.
+///
+/// The messages are still passed on to `log` as well.
+pub(crate) fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<String>) {
+	/// Restores the previous capture, even if `f` panics.
+	struct Guard(Option<Vec<String>>);
+
+	impl Drop for Guard {
+		fn drop(&mut self) {
+			CAPTURED.with(|captured| captured.replace(self.0.take()));
+		}
+	}
+
+	let guard = Guard(CAPTURED.with(|captured| captured.replace(Some(Vec::new()))));
+	let ret = f();
+	let messages = CAPTURED.with(RefCell::take);
+	drop(guard);
+	(ret, messages.unwrap_or_default())
+}
 
 mod vtable {
 	use std::os::raw::c_void;
 
 	use freenect2_sys as sys;
+	use log::LevelFilter;
 
 	pub(in crate::logger) unsafe extern "C" fn drop(_this_: *mut c_void) {}
 	pub(in crate::logger) unsafe extern "C" fn level(_this_: *const c_void) -> sys::Fn2LogLevel {
-		// don't actually do anything here; filter in `log` instead
-		sys::Fn2LogLevel_Debug
+		// unwinding into libfreenect2 is undefined behavior, so fall back to logging warnings and errors
+		std::panic::catch_unwind(|| {
+			let mut level = super::native_level();
+			// warnings and errors are needed to explain failures, even if they are not logged
+			let capturing = super::CAPTURED
+				.try_with(|captured| captured.borrow().is_some())
+				.unwrap_or(false);
+			if capturing {
+				level = level.max(LevelFilter::Warn);
+			}
+			match level {
+				LevelFilter::Off => sys::Fn2LogLevel_None,
+				LevelFilter::Error => sys::Fn2LogLevel_Error,
+				LevelFilter::Warn => sys::Fn2LogLevel_Warning,
+				LevelFilter::Info => sys::Fn2LogLevel_Info,
+				LevelFilter::Debug | LevelFilter::Trace => sys::Fn2LogLevel_Debug,
+			}
+		})
+		.unwrap_or(sys::Fn2LogLevel_Warning)
 	}
 	pub(in crate::logger) unsafe extern "C" fn log(
 		_this_: *mut c_void,
@@ -21,17 +120,32 @@ mod vtable {
 			sys::Fn2LogLevel_Error => log::Level::Error,
 			sys::Fn2LogLevel_Warning => log::Level::Warn,
 			sys::Fn2LogLevel_Info => log::Level::Info,
-			sys::Fn2LogLevel_Debug | sys::Fn2LogLevel_None => log::Level::Debug,
-			_ => unreachable!(),
+			_ => log::Level::Debug,
 		};
-		log::Log::log(
-			log::logger(),
-			&log::Record::builder()
-				.args(std::format_args!("{message}"))
-				.level(level)
-				.module_path(Some("libfreenect2"))
-				.build(),
-		);
+
+		// unwinding into libfreenect2 is undefined behavior, and there is nowhere to report a panicking logger to
+		let _ = std::panic::catch_unwind(move || {
+			if level <= log::Level::Warn {
+				// `try_with` because libfreenect2 may log while this thread is being torn down
+				let _ = super::CAPTURED.try_with(|captured| {
+					if let Some(captured) = captured.borrow_mut().as_mut() {
+						captured.push(message.clone());
+					}
+				});
+			}
+			// messages above the native level were only formatted to be captured
+			if level <= super::native_level() {
+				log::Log::log(
+					log::logger(),
+					&log::Record::builder()
+						.args(std::format_args!("{message}"))
+						.level(level)
+						.target(&super::target(&message))
+						.module_path(Some("libfreenect2"))
+						.build(),
+				);
+			}
+		});
 	}
 }
 
@@ -0,0 +1,94 @@
+//! Provides [`PacketPipeline`].
+
+use freenect2_sys as sys;
+
+/// The implementation used to decode the raw packets from the device into frames.
+///
+/// Only the CPU and dump pipelines are always available; the others depend on how libfreenect2 was compiled.
+/// Use [`is_available`](Self::is_available) to check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PacketPipeline {
+	/// Decode on the CPU. Slow, but works everywhere.
+	Cpu,
+	/// Decode using OpenGL.
+	OpenGl,
+	/// Decode using `OpenCL`.
+	OpenCl,
+	/// Decode using CUDA.
+	Cuda,
+	/// Do not decode; frames contain the raw packets.
+	Dump,
+}
+
+impl PacketPipeline {
+	/// All pipelines, ordered from most to least preferred.
+	///
+	/// [`Dump`](Self::Dump) comes last, as it yields undecoded frames in the [`Raw`](crate::FrameFormat::Raw) format, which cannot be viewed as depth or IR images; exclude it when picking a pipeline for consumers of those.
+	pub const ALL: [Self; 5] = [
+		Self::Cuda,
+		Self::OpenCl,
+		Self::OpenGl,
+		Self::Cpu,
+		Self::Dump,
+	];
+
+	/// Whether the pipeline is compiled into the installed libfreenect2.
+	#[must_use]
+	pub fn is_available(self) -> bool {
+		unsafe { sys::fn2_packet_pipeline_is_available(self.into()) }
+	}
+
+	/// The name of the pipeline, as accepted by [`FromStr`](std::str::FromStr).
+	#[must_use]
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::Cpu => "cpu",
+			Self::OpenGl => "opengl",
+			Self::OpenCl => "opencl",
+			Self::Cuda => "cuda",
+			Self::Dump => "dump",
+		}
+	}
+}
+
+impl From<PacketPipeline> for sys::Fn2PacketPipeline {
+	fn from(our: PacketPipeline) -> Self {
+		match our {
+			PacketPipeline::Cpu => sys::Fn2PacketPipeline_Cpu,
+			PacketPipeline::OpenGl => sys::Fn2PacketPipeline_OpenGl,
+			PacketPipeline::OpenCl => sys::Fn2PacketPipeline_OpenCl,
+			PacketPipeline::Cuda => sys::Fn2PacketPipeline_Cuda,
+			PacketPipeline::Dump => sys::Fn2PacketPipeline_Dump,
+		}
+	}
+}
+
+impl std::fmt::Display for PacketPipeline {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		formatter.write_str(self.name())
+	}
+}
+
+/// Error returned when parsing a [`PacketPipeline`] from an unknown name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownPipeline;
+
+impl std::fmt::Display for UnknownPipeline {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		formatter
+			.write_str("unknown packet pipeline; expected `cpu`, `opengl`, `opencl`, `cuda`, or `dump`")
+	}
+}
+
+impl std::error::Error for UnknownPipeline {}
+
+impl std::str::FromStr for PacketPipeline {
+	type Err = UnknownPipeline;
+
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		Self::ALL
+			.into_iter()
+			.find(|pipeline| pipeline.name() == name)
+			.ok_or(UnknownPipeline)
+	}
+}
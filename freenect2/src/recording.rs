@@ -0,0 +1,531 @@
+//! Provides [`Recorder`], which saves frames along with the properties of the device that captured them, and [`Recording`], which reads them back.
+//!
+//! To replay a recording as if it came from a device, use [`PlaybackDevice`](crate::device::PlaybackDevice).
+//!
+//! # Format
+//!
+//! All numbers are little-endian, and strings are a `u32` length followed by that many bytes of UTF-8.
+//!
+//! 1. The magic bytes `FN2RECRD`, then the version of the format as a `u16`, currently [`VERSION`].
+//! 2. The [`Header`]: the serial number and firmware version as strings, the 9 [`IrCameraParams`] and the 26 [`ColorCameraParams`] as `f32`s in the order that they are declared, then the [`DepthConfig`] as two `f32`s and two bytes that are 0 or 1.
+//! 3. The frames, until the end of the file. Each frame is its type and format as bytes, its width, height, and bytes per pixel as `u32`s, its timestamp and sequence number as `u32`s, its exposure, gain, and gamma as `f32`s, its status as a `u32`, then its data as a `u64` length followed by that many bytes.
+//!
+//! Version 1 did not store the gamma and status of frames, which read as 0.0 and [`Status::Ok`](crate::FrameStatus::Ok).
+//!
+//! Frame types are numbered 0 for color, 1 for IR, and 2 for depth.
+//! Frame formats are numbered 0 for invalid, 1 for raw, 2 for float, 3 for BGRX, 4 for RGBX, and 5 for gray.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use freenect2_sys as sys;
+
+use crate::device::{ColorCameraParams, DepthConfig, Device, IrCameraParams};
+use crate::{Frame, FrameFormat, FrameType};
+
+const MAGIC: [u8; 8] = *b"FN2RECRD";
+
+/// The version of the format that is written, which is also the newest one that can be read.
+pub const VERSION: u16 = 2;
+
+/// The oldest version of the format that can be read.
+const MIN_VERSION: u16 = 1;
+
+/// The largest frame that will be read, in bytes, so that a corrupt length does not exhaust memory.
+const MAX_FRAME_LEN: u64 = 64 << 20;
+
+/// Errors that can occur while reading a recording.
+#[derive(Debug)]
+pub enum RecordingError {
+	/// Reading failed, or the recording ended in the middle of a frame.
+	Io(std::io::Error),
+	/// The data does not start with the magic bytes of a recording.
+	NotARecording,
+	/// The recording was written in a version of the format that is not supported.
+	UnsupportedVersion(u16),
+	/// The recording contains an invalid value.
+	Invalid(&'static str),
+}
+
+impl std::fmt::Display for RecordingError {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Io(error) => write!(formatter, "could not read recording: {error}"),
+			Self::NotARecording => formatter.write_str("not a freenect2 recording"),
+			Self::UnsupportedVersion(version) => {
+				write!(
+					formatter,
+					"unsupported recording version {version} (expected {MIN_VERSION} to {VERSION})"
+				)
+			}
+			Self::Invalid(what) => write!(formatter, "invalid recording: {what}"),
+		}
+	}
+}
+
+impl std::error::Error for RecordingError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::Io(error) => Some(error),
+			_ => None,
+		}
+	}
+}
+
+impl From<std::io::Error> for RecordingError {
+	fn from(error: std::io::Error) -> Self {
+		Self::Io(error)
+	}
+}
+
+/// The properties of the device that a recording was captured with.
+#[derive(Debug, Clone)]
+pub struct Header {
+	/// The serial number of the device.
+	pub serial_number: String,
+	/// The firmware version of the device.
+	pub firmware_version: String,
+	/// The parameters of the IR camera.
+	pub ir_camera_params: IrCameraParams,
+	/// The parameters of the color camera.
+	pub color_camera_params: ColorCameraParams,
+	/// The depth processing configuration that the depth frames were produced with.
+	pub depth_config: DepthConfig,
+}
+
+impl Header {
+	/// Get the properties of `device`, which has been configured with `depth_config`.
+	#[must_use]
+	pub fn for_device(device: &Device, depth_config: DepthConfig) -> Self {
+		Self {
+			serial_number: device.serial_number(),
+			firmware_version: device.firmware_version(),
+			ir_camera_params: device.ir_camera_params(),
+			color_camera_params: device.color_camera_params(),
+			depth_config,
+		}
+	}
+}
+
+fn type_code(ty: FrameType) -> u8 {
+	match ty {
+		FrameType::Color => 0,
+		FrameType::Ir => 1,
+		FrameType::Depth => 2,
+	}
+}
+
+fn format_code(format: FrameFormat) -> u8 {
+	match format {
+		FrameFormat::Invalid => 0,
+		FrameFormat::Raw => 1,
+		FrameFormat::Float => 2,
+		FrameFormat::Bgrx => 3,
+		FrameFormat::Rgbx => 4,
+		FrameFormat::Gray => 5,
+	}
+}
+
+fn write_string(writer: &mut impl Write, string: &str) -> std::io::Result<()> {
+	let len = u32::try_from(string.len())
+		.map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "string is too long"))?;
+	writer.write_all(&len.to_le_bytes())?;
+	writer.write_all(string.as_bytes())
+}
+
+fn write_f32s(writer: &mut impl Write, values: &[f32]) -> std::io::Result<()> {
+	values
+		.iter()
+		.try_for_each(|value| writer.write_all(&value.to_le_bytes()))
+}
+
+fn write_u32(writer: &mut impl Write, value: usize) -> std::io::Result<()> {
+	let value = u32::try_from(value)
+		.map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "value is too large"))?;
+	writer.write_all(&value.to_le_bytes())
+}
+
+/// Writes frames to a recording.
+///
+/// To record a device, create a recorder with its [`Header`], then write frames from its [frame listener](Device::set_frame_listener).
+#[derive(Debug)]
+pub struct Recorder<W: Write> {
+	writer: W,
+}
+
+impl Recorder<BufWriter<File>> {
+	/// Create a recording at `path`, replacing any existing file, and write `header` to it.
+	///
+	/// # Errors
+	///
+	/// Fails if the file cannot be created or written.
+	pub fn create(path: impl AsRef<Path>, header: &Header) -> std::io::Result<Self> {
+		Self::new(BufWriter::new(File::create(path)?), header)
+	}
+}
+
+impl<W: Write> Recorder<W> {
+	/// Start a recording in `writer` by writing `header` to it.
+	///
+	/// # Errors
+	///
+	/// Fails if writing fails.
+	pub fn new(mut writer: W, header: &Header) -> std::io::Result<Self> {
+		writer.write_all(&MAGIC)?;
+		writer.write_all(&VERSION.to_le_bytes())?;
+		write_string(&mut writer, &header.serial_number)?;
+		write_string(&mut writer, &header.firmware_version)?;
+
+		let ir = header.ir_camera_params;
+		write_f32s(
+			&mut writer,
+			&[
+				ir.fx, ir.fy, ir.cx, ir.cy, ir.k1, ir.k2, ir.k3, ir.p1, ir.p2,
+			],
+		)?;
+		let color = header.color_camera_params;
+		write_f32s(
+			&mut writer,
+			&[
+				color.fx,
+				color.fy,
+				color.cx,
+				color.cy,
+				color.shift_d,
+				color.shift_m,
+				color.mx_x3y0,
+				color.mx_x0y3,
+				color.mx_x2y1,
+				color.mx_x1y2,
+				color.mx_x2y0,
+				color.mx_x0y2,
+				color.mx_x1y1,
+				color.mx_x1y0,
+				color.mx_x0y1,
+				color.mx_x0y0,
+				color.my_x3y0,
+				color.my_x0y3,
+				color.my_x2y1,
+				color.my_x1y2,
+				color.my_x2y0,
+				color.my_x0y2,
+				color.my_x1y1,
+				color.my_x1y0,
+				color.my_x0y1,
+				color.my_x0y0,
+			],
+		)?;
+		let depth = header.depth_config;
+		write_f32s(&mut writer, &[depth.min_depth, depth.max_depth])?;
+		writer.write_all(&[
+			depth.enable_bilateral_filter.into(),
+			depth.enable_edge_aware_filter.into(),
+		])?;
+
+		Ok(Self { writer })
+	}
+
+	/// Append a frame of type `ty`.
+	///
+	/// # Errors
+	///
+	/// Fails if writing fails.
+	pub fn write(&mut self, frame: &Frame, ty: FrameType) -> std::io::Result<()> {
+		let writer = &mut self.writer;
+		writer.write_all(&[type_code(ty), format_code(frame.format())])?;
+		write_u32(writer, frame.width())?;
+		write_u32(writer, frame.height())?;
+		write_u32(writer, frame.bytes_per_pixel())?;
+		writer.write_all(&frame.timestamp().to_le_bytes())?;
+		writer.write_all(&frame.sequence().to_le_bytes())?;
+		write_f32s(writer, &[frame.exposure(), frame.gain(), frame.gamma()])?;
+		writer.write_all(&u32::from(frame.status()).to_le_bytes())?;
+		let data = frame.data();
+		writer.write_all(&(data.len() as u64).to_le_bytes())?;
+		writer.write_all(data)
+	}
+
+	/// Flush the recording and return the writer.
+	///
+	/// Dropping the recorder also flushes a [`BufWriter`], but ignores any error.
+	///
+	/// # Errors
+	///
+	/// Fails if flushing fails.
+	pub fn finish(mut self) -> std::io::Result<W> {
+		self.writer.flush()?;
+		Ok(self.writer)
+	}
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> std::io::Result<[u8; N]> {
+	let mut bytes = [0; N];
+	reader.read_exact(&mut bytes)?;
+	Ok(bytes)
+}
+
+fn read_u32(reader: &mut impl Read) -> std::io::Result<u32> {
+	read_array(reader).map(u32::from_le_bytes)
+}
+
+fn read_f32s<const N: usize>(reader: &mut impl Read) -> std::io::Result<[f32; N]> {
+	let mut values = [0.0; N];
+	for value in &mut values {
+		*value = f32::from_le_bytes(read_array(reader)?);
+	}
+	Ok(values)
+}
+
+fn read_bool(reader: &mut impl Read) -> Result<bool, RecordingError> {
+	match read_array(reader)? {
+		[0] => Ok(false),
+		[1] => Ok(true),
+		_ => Err(RecordingError::Invalid("boolean is not 0 or 1")),
+	}
+}
+
+fn read_string(reader: &mut impl Read) -> Result<String, RecordingError> {
+	let len = read_u32(reader)?;
+	let mut bytes = Vec::new();
+	reader.take(len.into()).read_to_end(&mut bytes)?;
+	if bytes.len() != len as usize {
+		return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+	}
+	String::from_utf8(bytes).map_err(|_| RecordingError::Invalid("string is not UTF-8"))
+}
+
+#[allow(clippy::similar_names)]
+fn read_color_camera_params(reader: &mut impl Read) -> std::io::Result<ColorCameraParams> {
+	let [fx, fy, cx, cy, shift_d, shift_m, mx_x3y0, mx_x0y3, mx_x2y1, mx_x1y2, mx_x2y0, mx_x0y2, mx_x1y1, mx_x1y0, mx_x0y1, mx_x0y0, my_x3y0, my_x0y3, my_x2y1, my_x1y2, my_x2y0, my_x0y2, my_x1y1, my_x1y0, my_x0y1, my_x0y0] =
+		read_f32s(reader)?;
+	Ok(ColorCameraParams {
+		fx,
+		fy,
+		cx,
+		cy,
+		shift_d,
+		shift_m,
+		mx_x3y0,
+		mx_x0y3,
+		mx_x2y1,
+		mx_x1y2,
+		mx_x2y0,
+		mx_x0y2,
+		mx_x1y1,
+		mx_x1y0,
+		mx_x0y1,
+		mx_x0y0,
+		my_x3y0,
+		my_x0y3,
+		my_x2y1,
+		my_x1y2,
+		my_x2y0,
+		my_x0y2,
+		my_x1y1,
+		my_x1y0,
+		my_x0y1,
+		my_x0y0,
+	})
+}
+
+/// Reads frames from a recording written by a [`Recorder`].
+///
+/// Iterating yields the frames in the order that they were written, and stops after the first error.
+pub struct Recording<R: Read> {
+	header: Header,
+	version: u16,
+	reader: R,
+	failed: bool,
+}
+
+impl Recording<BufReader<File>> {
+	/// Open the recording at `path` and read its header.
+	///
+	/// # Errors
+	///
+	/// Fails if the file cannot be read or is not a valid recording.
+	pub fn open(path: impl AsRef<Path>) -> Result<Self, RecordingError> {
+		Self::new(BufReader::new(File::open(path)?))
+	}
+}
+
+impl<R: Read> std::fmt::Debug for Recording<R> {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		formatter
+			.debug_struct("Recording")
+			.field("header", &self.header)
+			.field("version", &self.version)
+			.field("failed", &self.failed)
+			.finish_non_exhaustive()
+	}
+}
+
+impl<R: Read> Recording<R> {
+	/// Read the header of the recording in `reader`.
+	///
+	/// # Errors
+	///
+	/// Fails if reading fails or if the data is not a valid recording.
+	pub fn new(mut reader: R) -> Result<Self, RecordingError> {
+		let magic: [u8; 8] = read_array(&mut reader).map_err(|error| {
+			if error.kind() == std::io::ErrorKind::UnexpectedEof {
+				RecordingError::NotARecording
+			} else {
+				error.into()
+			}
+		})?;
+		if magic != MAGIC {
+			return Err(RecordingError::NotARecording);
+		}
+		let version = u16::from_le_bytes(read_array(&mut reader)?);
+		if !(MIN_VERSION..=VERSION).contains(&version) {
+			return Err(RecordingError::UnsupportedVersion(version));
+		}
+
+		let serial_number = read_string(&mut reader)?;
+		let firmware_version = read_string(&mut reader)?;
+		let [fx, fy, cx, cy, k1, k2, k3, p1, p2] = read_f32s(&mut reader)?;
+		let ir_camera_params = IrCameraParams {
+			fx,
+			fy,
+			cx,
+			cy,
+			k1,
+			k2,
+			k3,
+			p1,
+			p2,
+		};
+		let color_camera_params = read_color_camera_params(&mut reader)?;
+		let [min_depth, max_depth] = read_f32s(&mut reader)?;
+		let depth_config = DepthConfig {
+			min_depth,
+			max_depth,
+			enable_bilateral_filter: read_bool(&mut reader)?,
+			enable_edge_aware_filter: read_bool(&mut reader)?,
+		};
+
+		Ok(Self {
+			header: Header {
+				serial_number,
+				firmware_version,
+				ir_camera_params,
+				color_camera_params,
+				depth_config,
+			},
+			version,
+			reader,
+			failed: false,
+		})
+	}
+
+	/// The properties of the device that the recording was captured with.
+	#[must_use]
+	pub fn header(&self) -> &Header {
+		&self.header
+	}
+
+	/// Erase the type of the reader, so that recordings from different sources can be stored together.
+	pub(crate) fn boxed(self) -> Recording<Box<dyn Read + Send>>
+	where
+		R: Send + 'static,
+	{
+		Recording {
+			header: self.header,
+			version: self.version,
+			reader: Box::new(self.reader),
+			failed: self.failed,
+		}
+	}
+
+	/// Read the next frame, or `None` at the end of the recording.
+	///
+	/// # Errors
+	///
+	/// Fails if reading fails, if the recording ends in the middle of a frame, or if the frame is invalid.
+	pub fn read_frame(&mut self) -> Result<Option<(Frame, FrameType)>, RecordingError> {
+		let mut ty = [0];
+		loop {
+			match self.reader.read(&mut ty) {
+				Ok(0) => return Ok(None),
+				Ok(_) => break,
+				Err(error) if error.kind() == std::io::ErrorKind::Interrupted => (),
+				Err(error) => return Err(error.into()),
+			}
+		}
+		let ty = match ty {
+			[0] => FrameType::Color,
+			[1] => FrameType::Ir,
+			[2] => FrameType::Depth,
+			_ => return Err(RecordingError::Invalid("unknown frame type")),
+		};
+		let (format, raw_format) = match read_array(&mut self.reader)? {
+			[0] => (FrameFormat::Invalid, sys::Fn2FrameFormat_Invalid),
+			[1] => (FrameFormat::Raw, sys::Fn2FrameFormat_Raw),
+			[2] => (FrameFormat::Float, sys::Fn2FrameFormat_Float),
+			[3] => (FrameFormat::Bgrx, sys::Fn2FrameFormat_Bgrx),
+			[4] => (FrameFormat::Rgbx, sys::Fn2FrameFormat_Rgbx),
+			[5] => (FrameFormat::Gray, sys::Fn2FrameFormat_Gray),
+			_ => return Err(RecordingError::Invalid("unknown frame format")),
+		};
+		let width = read_u32(&mut self.reader)? as usize;
+		let height = read_u32(&mut self.reader)? as usize;
+		let bytes_per_pixel = read_u32(&mut self.reader)? as usize;
+		let timestamp = read_u32(&mut self.reader)?;
+		let sequence = read_u32(&mut self.reader)?;
+		let [exposure, gain] = read_f32s(&mut self.reader)?;
+		let (gamma, status) = if self.version >= 2 {
+			let [gamma] = read_f32s(&mut self.reader)?;
+			(gamma, read_u32(&mut self.reader)?)
+		} else {
+			(0.0, 0)
+		};
+		let len = u64::from_le_bytes(read_array(&mut self.reader)?);
+
+		let expected_len = match format {
+			FrameFormat::Raw => Some(bytes_per_pixel),
+			_ => width
+				.checked_mul(height)
+				.and_then(|pixels| pixels.checked_mul(bytes_per_pixel)),
+		};
+		if len > MAX_FRAME_LEN {
+			return Err(RecordingError::Invalid("frame is too large"));
+		}
+		let len = usize::try_from(len)
+			.ok()
+			.filter(|&len| len > 0 && Some(len) == expected_len)
+			.ok_or(RecordingError::Invalid(
+				"frame length does not match its dimensions",
+			))?;
+
+		// allocate a frame that libfreenect2 can free, so that it behaves like one from a device
+		let mut raw = unsafe { sys::fn2_frame_new(len, 1, 1) };
+		raw.width = width;
+		raw.height = height;
+		raw.bytes_per_pixel = bytes_per_pixel;
+		raw.timestamp = timestamp;
+		raw.sequence = sequence;
+		raw.exposure = exposure;
+		raw.gain = gain;
+		raw.gamma = gamma;
+		raw.status = status;
+		raw.format = raw_format;
+		let mut frame = unsafe { Frame::from_sys(raw) };
+		self.reader.read_exact(frame.data_mut())?;
+		Ok(Some((frame, ty)))
+	}
+}
+
+impl<R: Read> Iterator for Recording<R> {
+	type Item = Result<(Frame, FrameType), RecordingError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.failed {
+			return None;
+		}
+		let result = self.read_frame().transpose();
+		self.failed = matches!(result, Some(Err(_)));
+		result
+	}
+}
@@ -0,0 +1,277 @@
+//! Provides [`Registration`], which combines depth and color frames using the cameras' parameters.
+
+use std::ptr::{addr_of_mut, NonNull};
+
+use freenect2_sys as sys;
+
+use crate::device::{ColorCameraParams, Device, IrCameraParams};
+use crate::{Frame, FrameFormat};
+
+pub use crate::frame::{COLOR_HEIGHT, COLOR_WIDTH, DEPTH_HEIGHT, DEPTH_WIDTH};
+
+/// The height of "bigdepth" frames, in pixels.
+///
+/// This is two more than [`COLOR_HEIGHT`] because there is an extra row of padding at the top and bottom.
+pub const BIGDEPTH_HEIGHT: usize = COLOR_HEIGHT + 2;
+
+/// Errors that can occur when passing frames to a [`Registration`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	/// The color frame is not a 1920 by 1080 frame in the Bgrx or Rgbx format.
+	InvalidColorFrame,
+	/// The depth frame is not a 512 by 424 frame in the Float format.
+	InvalidDepthFrame,
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		formatter.write_str(match self {
+			Self::InvalidColorFrame => "expected a 1920x1080 color frame in the BGRX or RGBX format",
+			Self::InvalidDepthFrame => "expected a 512x424 depth frame in the float format",
+		})
+	}
+}
+
+impl std::error::Error for Error {}
+
+fn is_color(frame: &Frame) -> bool {
+	frame.as_color().is_ok()
+}
+
+fn is_depth(frame: &Frame) -> bool {
+	frame.as_depth().is_ok()
+}
+
+/// The frames produced by [`Registration::apply`].
+#[derive(Debug)]
+pub struct Registered {
+	/// The depth frame with the lens distortion removed, in the Float format, in millimeters.
+	///
+	/// Pixels without a valid depth are zero.
+	pub undistorted: Frame,
+	/// The color of each pixel of `undistorted`, in the same format as the color frame.
+	///
+	/// Pixels without a color are zero.
+	pub registered: Frame,
+	/// The depth of each pixel of the color frame, in the Float format, in millimeters, if it was requested.
+	///
+	/// The frame is [`BIGDEPTH_HEIGHT`] pixels high; row `y + 1` corresponds to row `y` of the color frame.
+	/// Pixels without a depth are infinite.
+	pub bigdepth: Option<Frame>,
+}
+
+/// Combines depth and color frames using the intrinsic parameters of the IR camera and the extrinsic parameters of the color camera.
+#[derive(Debug)]
+pub struct Registration {
+	inner: NonNull<sys::Fn2Registration>,
+}
+
+// SAFETY: after construction, `libfreenect2::Registration` only has `const` methods that do not modify any shared state.
+unsafe impl Send for Registration {}
+unsafe impl Sync for Registration {}
+
+impl Registration {
+	/// Create a registration from the parameters of the cameras.
+	///
+	/// # Panics
+	///
+	/// Panics if the library returns nullptr.
+	#[must_use]
+	pub fn new(ir: IrCameraParams, color: ColorCameraParams) -> Self {
+		let raw = unsafe { sys::fn2_registration_new(ir.into(), color.into()) };
+		Self {
+			inner: NonNull::new(raw).expect("`fn2_registration_new` returned nullptr"),
+		}
+	}
+
+	/// Create a registration from the parameters that are stored on `device`.
+	#[must_use]
+	pub fn for_device(device: &Device) -> Self {
+		Self::new(device.ir_camera_params(), device.color_camera_params())
+	}
+
+	/// Map the pixel at (`x`, `y`) of an undistorted depth frame, with the depth `depth` in millimeters, to a position in the color frame.
+	///
+	/// # Panics
+	///
+	/// Panics if the pixel is outside of the depth frame.
+	#[must_use]
+	pub fn depth_to_color(&self, x: usize, y: usize, depth: f32) -> (f32, f32) {
+		assert!(x < DEPTH_WIDTH && y < DEPTH_HEIGHT, "pixel out of bounds");
+
+		let (mut color_x, mut color_y) = (0.0, 0.0);
+		unsafe {
+			sys::fn2_registration_apply_point(
+				self.inner.as_ptr(),
+				x.try_into().unwrap(),
+				y.try_into().unwrap(),
+				depth,
+				addr_of_mut!(color_x),
+				addr_of_mut!(color_y),
+			);
+		}
+		(color_x, color_y)
+	}
+
+	/// Remove the lens distortion from a depth frame.
+	///
+	/// # Errors
+	///
+	/// Fails if `depth` is not a depth frame.
+	pub fn undistort_depth(&self, depth: &Frame) -> Result<Frame, Error> {
+		if !is_depth(depth) {
+			return Err(Error::InvalidDepthFrame);
+		}
+
+		let mut undistorted = Frame::zeroed(depth, DEPTH_WIDTH, DEPTH_HEIGHT, 4, FrameFormat::Float);
+		unsafe {
+			sys::fn2_registration_undistort_depth(
+				self.inner.as_ptr(),
+				depth.view(),
+				undistorted.view_mut(),
+			);
+		}
+		Ok(undistorted)
+	}
+
+	/// Undistort `depth` and find the color of each of its pixels in `color`.
+	///
+	/// If `filter` is set, colors that are occluded from the depth camera's point of view are removed.
+	/// If `bigdepth` is set, the depth of each pixel of the color frame is also calculated.
+	///
+	/// # Errors
+	///
+	/// Fails if `color` is not a color frame or if `depth` is not a depth frame.
+	pub fn apply(
+		&self,
+		color: &Frame,
+		depth: &Frame,
+		filter: bool,
+		bigdepth: bool,
+	) -> Result<Registered, Error> {
+		if !is_color(color) {
+			return Err(Error::InvalidColorFrame);
+		}
+		if !is_depth(depth) {
+			return Err(Error::InvalidDepthFrame);
+		}
+
+		let mut undistorted = Frame::zeroed(depth, DEPTH_WIDTH, DEPTH_HEIGHT, 4, FrameFormat::Float);
+		let mut registered = Frame::zeroed(depth, DEPTH_WIDTH, DEPTH_HEIGHT, 4, color.format());
+		let mut bigdepth =
+			bigdepth.then(|| Frame::zeroed(depth, COLOR_WIDTH, BIGDEPTH_HEIGHT, 4, FrameFormat::Float));
+		let bigdepth_view = bigdepth.as_mut().map(Frame::view_mut);
+
+		unsafe {
+			sys::fn2_registration_apply(
+				self.inner.as_ptr(),
+				color.view(),
+				depth.view(),
+				undistorted.view_mut(),
+				registered.view_mut(),
+				filter,
+				bigdepth_view
+					.as_ref()
+					.map_or(std::ptr::null(), std::ptr::from_ref),
+			);
+		}
+
+		Ok(Registered {
+			undistorted,
+			registered,
+			bigdepth,
+		})
+	}
+
+	/// Get the 3D position of a pixel of an undistorted depth frame, in meters, relative to the IR camera.
+	///
+	/// Returns `None` if the pixel does not have a valid depth.
+	///
+	/// # Panics
+	///
+	/// Panics if `undistorted` is not a depth frame or if the pixel is outside of it.
+	#[must_use]
+	pub fn point_xyz(&self, undistorted: &Frame, row: usize, column: usize) -> Option<[f32; 3]> {
+		assert!(is_depth(undistorted), "{}", Error::InvalidDepthFrame);
+		assert!(
+			row < DEPTH_HEIGHT && column < DEPTH_WIDTH,
+			"pixel out of bounds"
+		);
+
+		let [mut x, mut y, mut z] = [0.0; 3];
+		unsafe {
+			sys::fn2_registration_get_point_xyz(
+				self.inner.as_ptr(),
+				undistorted.view(),
+				row.try_into().unwrap(),
+				column.try_into().unwrap(),
+				addr_of_mut!(x),
+				addr_of_mut!(y),
+				addr_of_mut!(z),
+			);
+		}
+		Some([x, y, z]).filter(|_| !z.is_nan())
+	}
+
+	/// Get the 3D position of a pixel of an undistorted depth frame, in meters, relative to the IR camera, along with its RGB color.
+	///
+	/// `undistorted` and `registered` should come from the same call to [`apply`](Self::apply).
+	///
+	/// Returns `None` if the pixel does not have a valid depth.
+	///
+	/// # Panics
+	///
+	/// Panics if `undistorted` is not a depth frame, if `registered` is not a registered frame, or if the pixel is outside of them.
+	#[must_use]
+	pub fn point_xyzrgb(
+		&self,
+		undistorted: &Frame,
+		registered: &Frame,
+		row: usize,
+		column: usize,
+	) -> Option<([f32; 3], [u8; 3])> {
+		assert!(is_depth(undistorted), "{}", Error::InvalidDepthFrame);
+		assert!(
+			registered.width() == DEPTH_WIDTH
+				&& registered.height() == DEPTH_HEIGHT
+				&& registered.bytes_per_pixel() == 4
+				&& matches!(registered.format(), FrameFormat::Bgrx | FrameFormat::Rgbx),
+			"expected a 512x424 registered frame in the BGRX or RGBX format",
+		);
+		assert!(
+			row < DEPTH_HEIGHT && column < DEPTH_WIDTH,
+			"pixel out of bounds"
+		);
+
+		let [mut x, mut y, mut z, mut rgb] = [0.0; 4];
+		unsafe {
+			sys::fn2_registration_get_point_xyzrgb(
+				self.inner.as_ptr(),
+				undistorted.view(),
+				registered.view(),
+				row.try_into().unwrap(),
+				column.try_into().unwrap(),
+				addr_of_mut!(x),
+				addr_of_mut!(y),
+				addr_of_mut!(z),
+				addr_of_mut!(rgb),
+			);
+		}
+
+		// the color is the four bytes of the registered pixel, reinterpreted as a float
+		let [first, green, third, _] = rgb.to_bits().to_ne_bytes();
+		let color = match registered.format() {
+			FrameFormat::Bgrx => [third, green, first],
+			_ => [first, green, third],
+		};
+		Some(([x, y, z], color)).filter(|_| !z.is_nan())
+	}
+}
+
+impl Drop for Registration {
+	fn drop(&mut self) {
+		unsafe {
+			sys::fn2_registration_free(self.inner.as_ptr());
+		}
+	}
+}
@@ -0,0 +1,44 @@
+//! Tests of saving the camera parameters of the fake devices of the `mock` feature and loading them back.
+
+#![cfg(all(feature = "mock", feature = "serde"))]
+
+use freenect2::calibration::{Calibration, CalibrationError};
+use freenect2::Context;
+
+#[test]
+fn round_trips_calibration() {
+	let mut context = Context::new();
+	let mut device = context.open_default_device().unwrap();
+	let factory = Calibration::for_device(&device);
+	assert_eq!(factory.serial_number, freenect2::mock::DEFAULT_SERIAL);
+	let mut calibration = factory.clone();
+
+	let directory =
+		std::env::temp_dir().join(format!("freenect2-calibration-{}", std::process::id()));
+	std::fs::create_dir_all(&directory).unwrap();
+	calibration.ir_camera_params.fx = 400.0;
+	calibration.color_camera_params.shift_m = 52.0;
+	for name in ["calibration.toml", "calibration.json"] {
+		let path = directory.join(name);
+		calibration.save(&path).unwrap();
+		factory.apply(&mut device).unwrap();
+		Calibration::load(&path)
+			.unwrap()
+			.apply(&mut device)
+			.unwrap();
+		assert_eq!(device.ir_camera_params().fx, 400.0);
+		assert_eq!(device.color_camera_params().shift_m, 52.0);
+	}
+
+	// the file's extension selects the format
+	std::fs::rename(
+		directory.join("calibration.json"),
+		directory.join("calibration.txt"),
+	)
+	.unwrap();
+	assert!(matches!(
+		Calibration::load(&directory.join("calibration.txt")),
+		Err(CalibrationError::Toml(_))
+	));
+	std::fs::remove_dir_all(&directory).unwrap();
+}
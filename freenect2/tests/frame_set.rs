@@ -0,0 +1,132 @@
+//! Tests of grouping frames that arrive out of order into sets.
+
+use freenect2::frame_set::{FrameSet, FrameSync, SyncConfig, Synced};
+use freenect2::Frame;
+use freenect2::FrameType::{self, Color, Depth, Ir};
+use freenect2_sys as sys;
+
+/// A one-pixel frame with the given timestamp.
+fn frame(timestamp: u32) -> Frame {
+	// SAFETY: the frame is new and its data is as long as its dimensions say
+	let mut raw = unsafe { sys::fn2_frame_new(1, 1, 4) };
+	raw.timestamp = timestamp;
+	raw.format = sys::Fn2FrameFormat_Float;
+	unsafe { Frame::from_sys(raw) }
+}
+
+/// The timestamps of the color, IR and depth frames of a set.
+type Timestamps = [Option<u32>; 3];
+
+fn timestamps(set: &FrameSet) -> Timestamps {
+	[&set.color, &set.ir, &set.depth].map(|frame| frame.as_ref().map(Frame::timestamp))
+}
+
+/// A [`Synced`], reduced to the timestamps of its frames.
+#[derive(Debug, PartialEq, Eq)]
+enum Emitted {
+	Complete(Timestamps),
+	Partial(Timestamps),
+}
+
+/// Push frames of the given types and timestamps, and collect what is emitted.
+fn push_all(sync: &mut FrameSync, frames: &[(FrameType, u32)]) -> Vec<Emitted> {
+	let mut emitted = Vec::new();
+	for &(ty, timestamp) in frames {
+		sync.push(frame(timestamp), ty, |synced| {
+			emitted.push(match synced {
+				Synced::Complete(set) => Emitted::Complete(timestamps(&set)),
+				Synced::Partial(set) => Emitted::Partial(timestamps(&set)),
+			});
+		});
+	}
+	emitted
+}
+
+#[test]
+fn matches_out_of_order_frames() {
+	let mut sync = FrameSync::new(SyncConfig::default());
+	assert_eq!(
+		push_all(&mut sync, &[(Depth, 1000), (Color, 1050), (Ir, 1000)]),
+		[Emitted::Complete([Some(1050), Some(1000), Some(1000)])]
+	);
+	// the color frame of the next set arrives last, although it was captured first
+	assert_eq!(
+		push_all(&mut sync, &[(Ir, 1333), (Depth, 1333), (Color, 1300)]),
+		[Emitted::Complete([Some(1300), Some(1333), Some(1333)])]
+	);
+	assert!(sync.flush().is_empty());
+}
+
+#[test]
+fn discards_unmatched_frames() {
+	let mut sync = FrameSync::new(SyncConfig::default());
+	assert_eq!(
+		push_all(
+			&mut sync,
+			&[
+				(Color, 1000),
+				// the depth and IR frames at 1000 were dropped
+				(Color, 1333),
+				(Ir, 1333),
+				(Depth, 1333),
+				// the color frame at 1666 was dropped
+				(Ir, 1666),
+				(Depth, 1666),
+				(Color, 2000),
+			]
+		),
+		[
+			Emitted::Partial([Some(1000), None, None]),
+			Emitted::Complete([Some(1333), Some(1333), Some(1333)]),
+			Emitted::Partial([None, Some(1666), Some(1666)]),
+		]
+	);
+	assert_eq!(timestamps(&sync.flush()), [Some(2000), None, None]);
+}
+
+#[test]
+fn matches_across_wrap_around() {
+	let mut sync = FrameSync::new(SyncConfig::default());
+	assert_eq!(
+		push_all(&mut sync, &[(Color, u32::MAX - 50), (Ir, 30), (Depth, 30)]),
+		[Emitted::Complete([Some(u32::MAX - 50), Some(30), Some(30)])]
+	);
+	// a frame from before the wrap is too old to match one long after it
+	assert_eq!(
+		push_all(&mut sync, &[(Color, u32::MAX - 400), (Depth, 100)]),
+		[Emitted::Partial([Some(u32::MAX - 400), None, None])]
+	);
+}
+
+#[test]
+fn matches_within_tolerance() {
+	let config = SyncConfig::default();
+	let mut sync = FrameSync::new(config);
+	let edge = 1000 + config.tolerance;
+	assert_eq!(
+		push_all(&mut sync, &[(Color, 1000), (Ir, edge), (Depth, edge)]),
+		[Emitted::Complete([Some(1000), Some(edge), Some(edge)])]
+	);
+	let beyond = 2000 + config.tolerance + 1;
+	assert_eq!(
+		push_all(&mut sync, &[(Color, 2000), (Ir, beyond), (Depth, beyond)]),
+		[Emitted::Partial([Some(2000), None, None])]
+	);
+	assert_eq!(
+		timestamps(&sync.flush()),
+		[None, Some(beyond), Some(beyond)]
+	);
+}
+
+#[test]
+fn ignores_unrequested_types() {
+	let mut sync = FrameSync::new(SyncConfig {
+		color: false,
+		ir: false,
+		..SyncConfig::default()
+	});
+	assert_eq!(
+		push_all(&mut sync, &[(Color, 1000), (Ir, 1000), (Depth, 1000)]),
+		[Emitted::Complete([None, None, Some(1000)])]
+	);
+}
@@ -0,0 +1,240 @@
+//! Tests of converting camera parameters to and from the calibration files of OpenCV and ROS.
+
+use freenect2::device::{ColorCameraParams, IrCameraParams};
+use freenect2::intrinsics::{Intrinsics, IntrinsicsError};
+
+const IR_PARAMS: IrCameraParams = IrCameraParams {
+	fx: 365.456,
+	fy: 365.456,
+	cx: 254.878,
+	cy: 205.395,
+	k1: 0.0905474,
+	k2: -0.26819,
+	k3: 0.0950862,
+	p1: 0.000_12,
+	p2: -0.000_34,
+};
+
+/// As written by OpenCV's camera calibration sample.
+const OPENCV_YAML: &str = "%YAML:1.0
+---
+calibration_time: \"Mon 12 Oct 2026 10:15:32 CEST\"
+image_width: 1920
+image_height: 1080
+camera_matrix: !!opencv-matrix
+   rows: 3
+   cols: 3
+   dt: d
+   data: [ 1.0601890000000001e+03, 0., 9.6428000000000000e+02, 0.,
+       1.0597700000000000e+03, 5.3846000000000000e+02, 0., 0., 1. ]
+distortion_coefficients: !!opencv-matrix
+   rows: 1
+   cols: 5
+   dt: d
+   data: [ 4.2e-02, -3.1e-02, 0., 0., 0. ]
+avg_reprojection_error: 2.1e-01
+";
+
+/// As written by ROS's `camera_calibration`.
+const ROS_YAML: &str = "image_width: 512
+image_height: 424
+camera_name: kinect2_ir
+camera_matrix:
+  rows: 3
+  cols: 3
+  data: [365.456, 0, 254.878, 0, 365.456, 205.395, 0, 0, 1]
+distortion_model: plumb_bob
+distortion_coefficients:
+  rows: 1
+  cols: 5
+  data: [0.0905474, -0.26819, 0.00012, -0.00034, 0.0950862]
+rectification_matrix:
+  rows: 3
+  cols: 3
+  data: [1, 0, 0, 0, 1, 0, 0, 0, 1]
+projection_matrix:
+  rows: 3
+  cols: 4
+  data: [365.456, 0, 254.878, 0, 0, 365.456, 205.395, 0, 0, 0, 1, 0]
+";
+
+fn assert_ir_params_eq(actual: IrCameraParams, expected: IrCameraParams) {
+	let fields = |params: IrCameraParams| {
+		[
+			params.fx, params.fy, params.cx, params.cy, params.k1, params.k2, params.k3, params.p1,
+			params.p2,
+		]
+	};
+	assert_eq!(fields(actual), fields(expected));
+}
+
+#[test]
+fn round_trips_ir_camera_params() {
+	let intrinsics = Intrinsics::from(IR_PARAMS);
+	assert_eq!(intrinsics.size, [512, 424]);
+	assert_eq!(
+		intrinsics.distortion,
+		[0.0905474, -0.26819, 0.000_12, -0.000_34, 0.0950862]
+	);
+
+	let opencv = Intrinsics::from_opencv_yaml(&intrinsics.to_opencv_yaml()).unwrap();
+	assert_ir_params_eq(opencv.to_ir_camera_params().unwrap(), IR_PARAMS);
+	let ros = Intrinsics::from_ros_yaml(&intrinsics.to_ros_yaml("kinect2_ir")).unwrap();
+	assert_ir_params_eq(ros.to_ir_camera_params().unwrap(), IR_PARAMS);
+
+	assert_ir_params_eq(
+		Intrinsics::from_ros_yaml(ROS_YAML)
+			.unwrap()
+			.to_ir_camera_params()
+			.unwrap(),
+		IR_PARAMS,
+	);
+}
+
+#[test]
+fn keeps_extrinsic_color_camera_params() {
+	let intrinsics = Intrinsics::from_opencv_yaml(OPENCV_YAML).unwrap();
+	assert_eq!(intrinsics.size, [1920, 1080]);
+	assert_eq!(intrinsics.distortion, [0.042, -0.031, 0.0, 0.0, 0.0]);
+	assert!(matches!(
+		intrinsics.to_ir_camera_params(),
+		Err(IntrinsicsError::WrongSize {
+			expected: [512, 424],
+			actual: [1920, 1080],
+		})
+	));
+
+	let device = ColorCameraParams {
+		fx: 1081.372,
+		fy: 1081.372,
+		cx: 959.5,
+		cy: 539.5,
+		shift_d: 863.0,
+		shift_m: 52.0,
+		mx_x3y0: 0.000_449,
+		mx_x0y3: 0.000_011,
+		mx_x2y1: 0.000_051,
+		mx_x1y2: 0.000_358,
+		mx_x2y0: -0.000_065,
+		mx_x0y2: 0.000_025,
+		mx_x1y1: 0.000_189,
+		mx_x1y0: 0.650_677,
+		mx_x0y1: 0.001_283,
+		mx_x0y0: 0.143_283,
+		my_x3y0: 0.000_023,
+		my_x0y3: 0.000_625,
+		my_x2y1: 0.000_319,
+		my_x1y2: 0.000_057,
+		my_x2y0: 0.000_037,
+		my_x0y2: -0.000_178,
+		my_x1y1: -0.000_048,
+		my_x1y0: -0.001_257,
+		my_x0y1: 0.650_677,
+		my_x0y0: 0.003_217,
+	};
+	let params = intrinsics.to_color_camera_params(device).unwrap();
+	assert_eq!(
+		[params.fx, params.fy, params.cx, params.cy],
+		[1060.189, 1059.77, 964.28, 538.46]
+	);
+	assert_eq!(
+		[
+			params.shift_d,
+			params.shift_m,
+			params.mx_x1y0,
+			params.my_x0y0
+		],
+		[
+			device.shift_d,
+			device.shift_m,
+			device.mx_x1y0,
+			device.my_x0y0
+		]
+	);
+
+	// the polynomial cannot be expressed, and the color camera has no distortion
+	let intrinsics = Intrinsics::from(device);
+	assert_eq!(intrinsics.distortion, [0.0; 5]);
+	let round_tripped = Intrinsics::from_ros_yaml(&intrinsics.to_ros_yaml("kinect2_color"))
+		.unwrap()
+		.to_color_camera_params(device)
+		.unwrap();
+	assert_eq!(round_tripped.fx, device.fx);
+	assert_eq!(round_tripped.cy, device.cy);
+}
+
+#[test]
+fn rejects_unsupported_files() {
+	let rational = ROS_YAML.replace("plumb_bob", "rational_polynomial");
+	assert!(matches!(
+		Intrinsics::from_ros_yaml(&rational),
+		Err(IntrinsicsError::UnsupportedDistortionModel(model)) if model == "rational_polynomial"
+	));
+
+	let eight = OPENCV_YAML
+		.replace("cols: 5", "cols: 8")
+		.replace("0., 0., 0. ]", "0., 0., 0., 0.1, 0., 0. ]");
+	assert!(matches!(
+		Intrinsics::from_opencv_yaml(&eight),
+		Err(IntrinsicsError::UnsupportedDistortionModel(_))
+	));
+
+	let missing = ROS_YAML.replace("image_height: 424\n", "");
+	assert!(matches!(
+		Intrinsics::from_ros_yaml(&missing),
+		Err(IntrinsicsError::Missing(key)) if key == "image_height"
+	));
+
+	let unterminated = OPENCV_YAML.replace("0., 0., 0. ]", "0., 0., 0.");
+	assert!(matches!(
+		Intrinsics::from_opencv_yaml(&unterminated),
+		Err(IntrinsicsError::Syntax { .. })
+	));
+}
+
+#[test]
+fn quotes_camera_names() {
+	let name = "kinect \"left\" # 2\\3\n\u{7}";
+	let yaml = Intrinsics::from(IR_PARAMS).to_ros_yaml(name);
+	assert!(yaml.contains("\ncamera_name: \"kinect \\\"left\\\" # 2\\\\3\\n\\u0007\"\n"));
+	assert_ir_params_eq(
+		Intrinsics::from_ros_yaml(&yaml)
+			.unwrap()
+			.to_ir_camera_params()
+			.unwrap(),
+		IR_PARAMS,
+	);
+
+	let escaped = ROS_YAML.replace("plumb_bob", "\"plumb\\x5fbob\" # \\\"");
+	assert!(Intrinsics::from_ros_yaml(&escaped).is_ok());
+	let single = ROS_YAML.replace("plumb_bob", "'plumb_bob' # 'it''s'");
+	assert!(Intrinsics::from_ros_yaml(&single).is_ok());
+	let invalid = ROS_YAML.replace("kinect2_ir", "\"kinect2\\q\"");
+	assert!(matches!(
+		Intrinsics::from_ros_yaml(&invalid),
+		Err(IntrinsicsError::Syntax { line: 3, .. })
+	));
+}
+
+#[test]
+fn round_trips_non_finite_values() {
+	let mut intrinsics = Intrinsics::from(IR_PARAMS);
+	intrinsics.distortion = [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, 0.0, 0.0];
+
+	let opencv = intrinsics.to_opencv_yaml();
+	assert!(opencv.contains("data: [.Nan, .Inf, -.Inf, 0.0, 0.0]"));
+	let ros = intrinsics.to_ros_yaml("kinect2_ir");
+	assert!(ros.contains("data: [.nan, .inf, -.inf, 0.0, 0.0]"));
+
+	for read in [
+		Intrinsics::from_opencv_yaml(&opencv).unwrap(),
+		Intrinsics::from_ros_yaml(&ros).unwrap(),
+	] {
+		let [k1, k2, p1, p2, k3] = read.distortion;
+		assert!(k1.is_nan());
+		assert_eq!(
+			[k2, p1, p2, k3],
+			[f32::INFINITY, f32::NEG_INFINITY, 0.0, 0.0]
+		);
+	}
+}
@@ -0,0 +1,295 @@
+//! Tests against the fake devices of the `mock` feature.
+
+#![cfg(feature = "mock")]
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use freenect2::context::OpenError;
+use freenect2::device::{
+	DropPolicy, Error as DeviceError, FrameCounts, OpenDevice, QueueConfig, StreamingDevice,
+};
+use freenect2::frame_set::{FrameSync, SyncConfig, Synced};
+use freenect2::mock::{self, MockDevice};
+use freenect2::registration::Registration;
+use freenect2::{Context, Device, FrameFormat, FrameStatus, FrameType};
+
+/// The fake devices are global, so tests that configure them must not run concurrently.
+static DEVICES: Mutex<()> = Mutex::new(());
+
+fn set_devices(serials: &[&str]) -> MutexGuard<'static, ()> {
+	let guard = DEVICES.lock().unwrap_or_else(PoisonError::into_inner);
+	mock::set_devices(serials.iter().copied().map(MockDevice::new).collect());
+	guard
+}
+
+#[test]
+fn enumerates_devices() {
+	let _guard = set_devices(&["first", "second"]);
+	let mut context = Context::new();
+	let serials: Vec<_> = context
+		.devices()
+		.into_iter()
+		.map(|device| device.serial)
+		.collect();
+	assert_eq!(serials, ["first", "second"]);
+
+	mock::set_devices(Vec::new());
+	assert_eq!(context.refresh(), 0);
+	assert!(matches!(
+		context.open_default_device(),
+		Err(OpenError::NoDevices)
+	));
+}
+
+#[test]
+fn opens_devices_once() {
+	let _guard = set_devices(&["first", "second"]);
+	let mut context = Context::new();
+	let device = context.open_device_by_serial("second").unwrap();
+	assert_eq!(device.serial_number(), "second");
+	assert_eq!(
+		device.ir_camera_params().fx,
+		MockDevice::new("").ir_params.fx
+	);
+	assert!(matches!(
+		context.open_device(1),
+		Err(OpenError::AlreadyOpen)
+	));
+	assert!(matches!(
+		context.open_device_by_serial("third"),
+		Err(OpenError::NoSuchSerial(_))
+	));
+
+	device.close().unwrap();
+	context.open_device(1).unwrap();
+}
+
+#[test]
+fn tracks_streaming_in_types() {
+	let _guard = set_devices(&["typestate"]);
+	let mut context = Context::new();
+	let device = context.open_default_device().unwrap();
+	let device = StreamingDevice::try_from(device).unwrap_err();
+	let mut open = OpenDevice::try_from(device).unwrap();
+
+	let mut params = open.ir_camera_params();
+	params.fx = 400.0;
+	open.set_ir_camera_params(params);
+	let frames = open.frames(QueueConfig::default());
+	let streaming = open.start_streams(false, true).unwrap();
+	frames.recv_timeout(Duration::from_secs(1)).unwrap();
+	let streaming = OpenDevice::try_from(Device::from(streaming)).unwrap_err();
+	let mut open = StreamingDevice::try_from(streaming)
+		.unwrap()
+		.stop()
+		.unwrap();
+	assert_eq!(open.ir_camera_params().fx, 400.0);
+
+	// the device can be reconfigured and started again after stopping
+	let mut params = open.color_camera_params();
+	params.shift_m = 52.0;
+	open.set_color_camera_params(params);
+	let frames = open.frames(QueueConfig::default());
+	let streaming = open.start().unwrap();
+	frames.recv_timeout(Duration::from_secs(1)).unwrap();
+	let open = streaming.stop().unwrap();
+	assert_eq!(open.color_camera_params().shift_m, 52.0);
+	open.close().unwrap();
+}
+
+#[test]
+fn streams_frames() {
+	let _guard = set_devices(&["streaming"]);
+	let mut context = Context::new();
+	let mut device = context.open_default_device().unwrap();
+	let frames = device.frames(QueueConfig::default());
+	device.start_streams(false, true).unwrap();
+
+	let mut types = Vec::new();
+	for _ in 0..4 {
+		let (frame, ty) = frames.recv_timeout(Duration::from_secs(1)).unwrap();
+		assert_eq!(frame.format(), FrameFormat::Float);
+		assert_eq!((frame.width(), frame.height()), (512, 424));
+		assert_eq!(frame.data().as_ptr().align_offset(8), 0);
+		if ty == FrameType::Depth {
+			let depth = frame.as_depth().unwrap();
+			assert!(depth.pixels().iter().any(|&depth| depth > 0.0));
+		}
+		types.push(ty);
+	}
+	assert!(types.contains(&FrameType::Ir));
+	assert!(types.contains(&FrameType::Depth));
+	assert!(!types.contains(&FrameType::Color));
+
+	device.stop().unwrap();
+	drop(device);
+	assert!(frames.recv().is_none());
+}
+
+#[test]
+fn counts_frame_errors() {
+	let _guard = DEVICES.lock().unwrap_or_else(PoisonError::into_inner);
+	mock::set_devices(vec![MockDevice {
+		error_interval: 3,
+		drop_interval: 5,
+		..MockDevice::new("faulty")
+	}]);
+	let mut context = Context::new();
+	let mut device = context.open_default_device().unwrap();
+	let frames = device.frames(QueueConfig {
+		capacity: 64,
+		policy: DropPolicy::Block,
+	});
+	device.start_streams(false, true).unwrap();
+	let mut depth = Vec::new();
+	while depth.len() < 10 {
+		let (frame, ty) = frames.recv_timeout(Duration::from_secs(1)).unwrap();
+		if ty == FrameType::Depth {
+			depth.push(frame);
+		}
+	}
+	// with a blocking queue, stopping waits for the listener, which may be waiting for room
+	drop(frames);
+	device.stop().unwrap();
+
+	// every fifth frame is dropped and every third fails to decode
+	for frame in &depth {
+		assert_ne!(frame.sequence() % 5, 4);
+		let expected = if frame.sequence() % 3 == 2 {
+			FrameStatus::DecodeFailed
+		} else {
+			FrameStatus::Ok
+		};
+		assert_eq!(frame.status(), expected);
+	}
+	// the listener may have received more frames before the device stopped
+	let first = depth[0].sequence();
+	let last = depth[depth.len() - 1].sequence();
+	let counts = device.frame_counts(FrameType::Depth);
+	assert!(counts.received >= depth.len() as u64);
+	assert!(counts.errors >= depth.iter().filter(|frame| frame.errors_occurred()).count() as u64);
+	assert!(counts.skipped >= u64::from(last - first + 1) - depth.len() as u64);
+	assert!(counts.errors > 0 && counts.skipped > 0);
+
+	device.reset_frame_counts();
+	assert_eq!(
+		device.frame_counts(FrameType::Depth),
+		FrameCounts::default()
+	);
+}
+
+#[test]
+fn reports_listener_panics() {
+	let _guard = set_devices(&["panicking"]);
+	let mut context = Context::new();
+	let mut device = context.open_default_device().unwrap();
+	device.set_frame_listener(|_, _| panic!("listener failed"));
+	device.start_streams(false, true).unwrap();
+	let deadline = Instant::now() + Duration::from_secs(5);
+	while device.frame_counts(FrameType::Depth).received < 2 {
+		assert!(Instant::now() < deadline, "no frames arrived");
+		std::thread::sleep(Duration::from_millis(10));
+	}
+
+	assert!(matches!(device.stop(), Err(DeviceError::ListenerPanicked)));
+	let payload = device.take_listener_panic().unwrap();
+	assert_eq!(payload.downcast_ref::<&str>(), Some(&"listener failed"));
+	assert!(device.take_listener_panic().is_none());
+
+	// the listener is not called again, so the device can be restarted
+	device.start_streams(false, true).unwrap();
+	std::thread::sleep(Duration::from_millis(100));
+	device.stop().unwrap();
+}
+
+#[test]
+fn registers_synthetic_frames() {
+	let _guard = set_devices(&["registering"]);
+	let mut context = Context::new();
+	let mut device = context.open_default_device().unwrap();
+	let (sender, receiver) = std::sync::mpsc::sync_channel(1);
+	device.set_frame_set_listener(FrameSync::new(SyncConfig::default()), move |synced| {
+		if let Synced::Complete(frames) = synced {
+			let _ = sender.try_send(frames);
+		}
+	});
+	device.start().unwrap();
+	let frames = receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+	device.stop().unwrap();
+
+	let registration = Registration::for_device(&device);
+	let (color, depth) = (frames.color.unwrap(), frames.depth.unwrap());
+	assert_eq!(color.status(), FrameStatus::Ok);
+	assert!((1.0..=6.4).contains(&color.gamma()));
+	let registered = registration.apply(&color, &depth, true, true).unwrap();
+
+	// the orange object is in front of the background in both frames
+	let depths = registered.undistorted.as_depth().unwrap();
+	let near: Vec<_> = (0..depths.height())
+		.flat_map(|row| (0..depths.width()).map(move |column| (row, column)))
+		.filter(|&(row, column)| depths.get(column, row).unwrap() < 1000.0)
+		.collect();
+	let (row, column) = near[near.len() / 2];
+	let ([_, _, z], [r, g, b]) = registration
+		.point_xyzrgb(&registered.undistorted, &registered.registered, row, column)
+		.unwrap();
+	assert!((z - 0.9).abs() < 0.001);
+	assert!(r > g && g > b);
+
+	let bigdepth = registered.bigdepth.unwrap();
+	assert!(bigdepth
+		.data()
+		.chunks_exact(4)
+		.map(|depth| f32::from_ne_bytes(depth.try_into().unwrap()))
+		.any(|depth| (depth - 2500.0).abs() < 1.0));
+}
+
+/// Let a queue of frames overflow without receiving from it, then stop the device and return the sequence numbers of the frames left in the queue, the number of frames that the queue dropped, and the number of frames the device sent.
+fn overflow(config: QueueConfig) -> (Vec<u32>, u64, u64) {
+	let _guard = set_devices(&["overflowing"]);
+	let mut context = Context::new();
+	let mut device = context.open_default_device().unwrap();
+	let frames = device.frames(config);
+	device.start_streams(false, true).unwrap();
+
+	let deadline = Instant::now() + Duration::from_secs(10);
+	while frames.dropped() < 4 {
+		assert!(Instant::now() < deadline, "the queue did not overflow");
+		std::thread::sleep(Duration::from_millis(10));
+	}
+	// dropping frames never blocks the listener, so stopping is safe without receiving
+	device.stop().unwrap();
+
+	let sequences = std::iter::from_fn(|| frames.try_recv().ok())
+		.map(|(frame, _)| frame.sequence())
+		.collect();
+	let sent =
+		device.frame_counts(FrameType::Ir).received + device.frame_counts(FrameType::Depth).received;
+	(sequences, frames.dropped(), sent)
+}
+
+#[test]
+fn drops_oldest_frames() {
+	let config = QueueConfig {
+		capacity: 4,
+		policy: DropPolicy::DropOldest,
+	};
+	let (sequences, dropped, sent) = overflow(config);
+	assert_eq!(dropped, sent - 4);
+	// the IR and depth frames of the last two instants are kept
+	let last = u32::try_from(sent / 2).unwrap() - 1;
+	assert_eq!(sequences, [last - 1, last - 1, last, last]);
+}
+
+#[test]
+fn drops_newest_frames() {
+	let config = QueueConfig {
+		capacity: 4,
+		policy: DropPolicy::DropNewest,
+	};
+	let (sequences, dropped, sent) = overflow(config);
+	assert_eq!(dropped, sent - 4);
+	// the IR and depth frames of the first two instants are kept
+	assert_eq!(sequences, [0, 0, 1, 1]);
+}
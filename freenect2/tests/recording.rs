@@ -0,0 +1,111 @@
+//! Tests of recording frames from the fake devices of the `mock` feature and playing them back.
+
+#![cfg(feature = "mock")]
+
+use std::io::Cursor;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use freenect2::device::{DepthConfig, DropPolicy, FrameCounts, PlaybackDevice, QueueConfig, Speed};
+use freenect2::mock::{self, MockDevice};
+use freenect2::recording::{Header, Recorder, Recording, RecordingError};
+use freenect2::{Context, FrameType};
+
+/// The fake devices are global, so tests that configure them must not run concurrently.
+static DEVICES: Mutex<()> = Mutex::new(());
+
+/// Record `count` depth and IR frames from a fake device, every other one of which fails to decode.
+fn record(count: usize) -> Vec<u8> {
+	let _guard = DEVICES.lock().unwrap_or_else(PoisonError::into_inner);
+	mock::set_devices(vec![MockDevice {
+		error_interval: 2,
+		..MockDevice::new(mock::DEFAULT_SERIAL)
+	}]);
+	let mut context = Context::new();
+	let mut device = context.open_default_device().unwrap();
+	let header = Header::for_device(&device, DepthConfig::default());
+	let mut recorder = Recorder::new(Vec::new(), &header).unwrap();
+
+	let frames = device.frames(QueueConfig {
+		capacity: count,
+		policy: DropPolicy::Block,
+	});
+	device.start_streams(false, true).unwrap();
+	for _ in 0..count {
+		let (frame, ty) = frames.recv_timeout(Duration::from_secs(1)).unwrap();
+		recorder.write(&frame, ty).unwrap();
+	}
+	// with a blocking queue, stopping waits for the listener, which may be waiting for room
+	drop(frames);
+	device.stop().unwrap();
+	recorder.finish().unwrap()
+}
+
+#[test]
+fn reads_back_recorded_frames() {
+	let data = record(6);
+	let recording = Recording::new(Cursor::new(&data)).unwrap();
+	assert_eq!(
+		recording.header().serial_number,
+		freenect2::mock::DEFAULT_SERIAL
+	);
+
+	let frames: Vec<_> = recording.map(Result::unwrap).collect();
+	assert_eq!(frames.len(), 6);
+	for pair in frames.chunks_exact(2) {
+		let [(ir, FrameType::Ir), (depth, FrameType::Depth)] = pair else {
+			panic!("expected an IR frame and then a depth frame");
+		};
+		assert_eq!(ir.timestamp(), depth.timestamp());
+		assert_eq!(depth.errors_occurred(), depth.sequence() % 2 == 1);
+		assert!(depth.as_depth().is_ok());
+	}
+
+	let truncated = Recording::new(Cursor::new(&data[..data.len() - 1]))
+		.unwrap()
+		.last()
+		.unwrap();
+	assert!(matches!(truncated, Err(RecordingError::Io(_))));
+	assert!(matches!(
+		Recording::new(Cursor::new(b"not a recording")),
+		Err(RecordingError::NotARecording)
+	));
+}
+
+#[test]
+fn plays_back_frames() {
+	let data = record(6);
+	let original: Vec<_> = Recording::new(Cursor::new(data.clone()))
+		.unwrap()
+		.map(|frame| frame.unwrap().0.timestamp())
+		.collect();
+
+	let mut device = PlaybackDevice::new(Recording::new(Cursor::new(data.clone())).unwrap());
+	device.set_speed(Speed::AsFastAsPossible).unwrap();
+	let frames = device.frames(QueueConfig {
+		capacity: 1,
+		policy: DropPolicy::Block,
+	});
+	device.start().unwrap();
+	let played: Vec<_> = frames.map(|(frame, _)| frame.timestamp()).collect();
+	assert_eq!(played, original);
+	assert!(device.finished());
+	device.stop().unwrap();
+	assert_eq!(
+		device.frame_counts(FrameType::Depth),
+		FrameCounts {
+			received: 3,
+			errors: 1,
+			skipped: 0,
+		}
+	);
+
+	// real-time playback takes as long as the recording did
+	let mut device = PlaybackDevice::new(Recording::new(Cursor::new(data)).unwrap());
+	let frames = device.frames(QueueConfig::default());
+	let start = Instant::now();
+	device.start_streams(false, true).unwrap();
+	assert_eq!(frames.count(), original.len());
+	let duration = Duration::from_micros(u64::from(original[5] - original[0]) * 100);
+	assert!(start.elapsed() >= duration);
+}
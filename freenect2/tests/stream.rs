@@ -0,0 +1,65 @@
+//! Tests of polling the asynchronous stream of frames from the fake devices of the `mock` feature.
+
+#![cfg(all(feature = "mock", feature = "futures"))]
+
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll, Wake, Waker};
+use std::time::{Duration, Instant};
+
+use freenect2::device::{FrameStream, QueueConfig};
+use freenect2::{Context, FrameType};
+use futures_core::Stream;
+
+/// A waker that counts how many times it has been woken.
+#[derive(Default)]
+struct CountingWaker(AtomicUsize);
+
+impl CountingWaker {
+	fn count(&self) -> usize {
+		self.0.load(Ordering::SeqCst)
+	}
+}
+
+impl Wake for CountingWaker {
+	fn wake(self: Arc<Self>) {
+		self.0.fetch_add(1, Ordering::SeqCst);
+	}
+}
+
+fn poll(stream: &mut FrameStream, waker: &Waker) -> Poll<Option<FrameType>> {
+	Pin::new(stream)
+		.poll_next(&mut TaskContext::from_waker(waker))
+		.map(|item| item.map(|(_, ty)| ty))
+}
+
+#[test]
+fn wakes_on_frames_and_close() {
+	let counter = Arc::new(CountingWaker::default());
+	let waker = Waker::from(Arc::clone(&counter));
+
+	let mut context = Context::new();
+	let mut device = context.open_default_device().unwrap();
+	let mut stream = device.frame_stream(QueueConfig::default());
+	assert_eq!(poll(&mut stream, &waker), Poll::Pending);
+	assert_eq!(counter.count(), 0);
+
+	// the first frame wakes the task
+	device.start_streams(false, true).unwrap();
+	let deadline = Instant::now() + Duration::from_secs(5);
+	while counter.count() == 0 {
+		assert!(Instant::now() < deadline, "the stream was not woken");
+		std::thread::sleep(Duration::from_millis(1));
+	}
+	assert!(matches!(poll(&mut stream, &waker), Poll::Ready(Some(_))));
+
+	device.stop().unwrap();
+	while poll(&mut stream, &waker).is_ready() {}
+	let woken = counter.count();
+
+	// dropping the device drops the listener, which ends the stream
+	drop(device);
+	assert_eq!(counter.count(), woken + 1);
+	assert_eq!(poll(&mut stream, &waker), Poll::Ready(None));
+}
@@ -0,0 +1,286 @@
+//! Interactive calibration of the mapping from depth image space to the screen.
+//!
+//! A target is shown near each corner of the screen in turn.
+//! The user holds their hand still while pointing at the target until the target fills up, and the hand position is recorded.
+//! The four recorded positions are then used to fit a [`Homography`].
+
+use std::path::Path;
+
+use freenect2::Frame;
+use glam::Vec2;
+use x11rb::connection::Connection;
+use x11rb::errors::{ConnectError, ConnectionError, ReplyError, ReplyOrIdError};
+use x11rb::protocol::xproto::{
+	Arc, ConnectionExt as _, CreateGCAux, CreateWindowAux, Gcontext, Window, WindowClass,
+};
+use x11rb::rust_connection::RustConnection;
+use x11rb::COPY_DEPTH_FROM_PARENT;
+
+use crate::mapping::{self, Calibration, Homography};
+use crate::tracker::{HandTracker, TrackerConfig};
+
+/// How long the hand must be held still to record a position, in units of 100 microseconds.
+const HOLD_DURATION: u32 = 15_000;
+/// How far the hand may move while being held still, in depth image pixels.
+const HOLD_TOLERANCE: f32 = 3.0;
+/// How far a recorded position must be from all previously recorded positions, in depth image pixels.
+const MIN_SEPARATION: f32 = 20.0;
+/// How far the targets are inset from the edges of the screen, as a proportion of its size.
+const TARGET_INSET: f32 = 0.1;
+/// The diameter of a target, in pixels.
+const TARGET_SIZE: u16 = 64;
+
+/// Errors that can occur during calibration.
+#[derive(Debug)]
+pub enum Error {
+	/// Could not connect to the X server.
+	Connect(ConnectError),
+	/// An error occurred while communicating with the X server.
+	X11(ReplyOrIdError),
+	/// The recorded positions could not be used to fit a mapping.
+	Degenerate,
+	/// The frame stream ended before calibration was complete.
+	Interrupted,
+	/// The calibration could not be saved.
+	Save(mapping::Error),
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Connect(error) => write!(formatter, "could not connect to the X server: {error}"),
+			Self::X11(error) => write!(formatter, "X server error: {error}"),
+			Self::Degenerate => {
+				formatter.write_str("the recorded hand positions are degenerate; please try again")
+			}
+			Self::Interrupted => formatter.write_str("calibration was interrupted"),
+			Self::Save(error) => write!(formatter, "could not save calibration: {error}"),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+impl From<ConnectError> for Error {
+	fn from(error: ConnectError) -> Self {
+		Self::Connect(error)
+	}
+}
+
+impl From<ConnectionError> for Error {
+	fn from(error: ConnectionError) -> Self {
+		Self::X11(error.into())
+	}
+}
+
+impl From<ReplyError> for Error {
+	fn from(error: ReplyError) -> Self {
+		Self::X11(error.into())
+	}
+}
+
+impl From<ReplyOrIdError> for Error {
+	fn from(error: ReplyOrIdError) -> Self {
+		Self::X11(error)
+	}
+}
+
+/// A fullscreen window that shows the current target.
+struct TargetWindow {
+	connection: RustConnection,
+	window: Window,
+	gc: Gcontext,
+	width: u16,
+	height: u16,
+}
+
+impl TargetWindow {
+	fn open() -> Result<Self, Error> {
+		let (connection, screen_num) = x11rb::connect(None)?;
+		let screen = &connection.setup().roots[screen_num];
+		let (root, width, height) = (screen.root, screen.width_in_pixels, screen.height_in_pixels);
+		let (black, white) = (screen.black_pixel, screen.white_pixel);
+
+		let window = connection.generate_id()?;
+		connection.create_window(
+			COPY_DEPTH_FROM_PARENT,
+			window,
+			root,
+			0,
+			0,
+			width,
+			height,
+			0,
+			WindowClass::INPUT_OUTPUT,
+			0,
+			&CreateWindowAux::new()
+				.background_pixel(black)
+				.override_redirect(1),
+		)?;
+
+		let gc = connection.generate_id()?;
+		connection.create_gc(gc, window, &CreateGCAux::new().foreground(white))?;
+
+		connection.map_window(window)?;
+		connection.flush()?;
+
+		Ok(Self {
+			connection,
+			window,
+			gc,
+			width,
+			height,
+		})
+	}
+
+	fn screen_size(&self) -> Vec2 {
+		Vec2::new(self.width.into(), self.height.into())
+	}
+
+	/// Draw a target centered on `center`, filled in proportion to `progress`, which ranges from 0.0 to 1.0.
+	fn draw(&self, center: Vec2, progress: f32) -> Result<(), Error> {
+		let half_size = f32::from(TARGET_SIZE / 2);
+		let arc = |angle2: f32| Arc {
+			x: az::saturating_cast(center.x - half_size),
+			y: az::saturating_cast(center.y - half_size),
+			width: TARGET_SIZE,
+			height: TARGET_SIZE,
+			angle1: 90 * 64,
+			angle2: az::saturating_cast(angle2 * 64.0),
+		};
+
+		self.connection.clear_area(false, self.window, 0, 0, 0, 0)?;
+		self
+			.connection
+			.poly_arc(self.window, self.gc, &[arc(360.0)])?;
+		self
+			.connection
+			.poly_fill_arc(self.window, self.gc, &[arc(-360.0 * progress)])?;
+		self.connection.flush()?;
+		Ok(())
+	}
+}
+
+impl Drop for TargetWindow {
+	fn drop(&mut self) {
+		let _ = self.connection.destroy_window(self.window);
+		let _ = self.connection.flush();
+	}
+}
+
+/// Detects when the hand has been held still.
+#[derive(Debug, Default)]
+struct HoldDetector {
+	samples: Vec<(u32, Vec2)>,
+}
+
+impl HoldDetector {
+	/// Add a sample and return the proportion of the hold that is complete, from 0.0 to 1.0.
+	fn update(&mut self, timestamp: u32, position: Vec2) -> f32 {
+		self.samples.push((timestamp, position));
+
+		// restart the hold from the first sample that is close enough to the current position
+		let start = self
+			.samples
+			.iter()
+			.rposition(|(_, sample)| sample.distance(position) > HOLD_TOLERANCE)
+			.map_or(0, |index| index + 1);
+		self.samples.drain(..start);
+
+		let elapsed = timestamp.wrapping_sub(self.samples[0].0);
+		#[allow(clippy::cast_precision_loss)]
+		(elapsed as f32 / HOLD_DURATION as f32).min(1.0)
+	}
+
+	fn mean(&self) -> Vec2 {
+		#[allow(clippy::cast_precision_loss)]
+		let len = self.samples.len() as f32;
+		self
+			.samples
+			.iter()
+			.fold(Vec2::ZERO, |sum, (_, sample)| sum + *sample)
+			/ len
+	}
+
+	fn reset(&mut self) {
+		self.samples.clear();
+	}
+}
+
+/// Run interactive calibration using depth frames from `frames` and the hand tracker parameters `tracker`, then save the result to `path`.
+///
+/// # Errors
+///
+/// Fails if the target window cannot be shown, if `frames` ends early, or if the result cannot be saved.
+///
+/// # Panics
+///
+/// Panics if a frame is not a 512x424 depth frame.
+pub fn run(
+	frames: impl Iterator<Item = Frame>,
+	tracker: TrackerConfig,
+	path: &Path,
+) -> Result<Calibration, Error> {
+	let window = TargetWindow::open()?;
+	let screen_size = window.screen_size();
+
+	let inset = screen_size * TARGET_INSET;
+	let targets = [
+		inset,
+		Vec2::new(screen_size.x - inset.x, inset.y),
+		screen_size - inset,
+		Vec2::new(inset.x, screen_size.y - inset.y),
+	];
+
+	let mut tracker = HandTracker::new(tracker);
+	let mut hold = HoldDetector::default();
+	let mut recorded = Vec::with_capacity(targets.len());
+
+	window.draw(targets[0], 0.0)?;
+	for frame in frames {
+		let depth = match frame.as_depth() {
+			Ok(depth) => depth,
+			Err(error) => {
+				log::warn!("skipping invalid depth frame: {error}");
+				continue;
+			}
+		};
+		let Some(hand) = tracker.track(depth.pixels().try_into().unwrap()) else {
+			hold.reset();
+			continue;
+		};
+
+		if recorded
+			.iter()
+			.any(|position: &Vec2| position.distance(hand.centroid) < MIN_SEPARATION)
+		{
+			hold.reset();
+			continue;
+		}
+
+		let target = targets[recorded.len()];
+		let progress = hold.update(frame.timestamp(), hand.centroid);
+		window.draw(target, progress)?;
+
+		if progress >= 1.0 {
+			let position = hold.mean();
+			log::info!("recorded hand position {position} for target {target}");
+			recorded.push(position);
+			hold.reset();
+
+			if let Some(&next) = targets.get(recorded.len()) {
+				window.draw(next, 0.0)?;
+			} else {
+				break;
+			}
+		}
+	}
+
+	let recorded: [Vec2; 4] = recorded.try_into().map_err(|_| Error::Interrupted)?;
+	let homography = Homography::fit(recorded, targets).ok_or(Error::Degenerate)?;
+	let calibration = Calibration::new([window.width, window.height], homography);
+	calibration.save(path).map_err(Error::Save)?;
+	log::info!("saved calibration to {}", path.display());
+
+	Ok(calibration)
+}
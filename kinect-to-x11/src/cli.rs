@@ -0,0 +1,188 @@
+//! Command-line arguments.
+
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use freenect2::PacketPipeline;
+use kinect_to_x11::config::{Config, DeviceConfig};
+use kinect_to_x11::filter::FilterConfig;
+use kinect_to_x11::output::OutputKind;
+
+/// Use a Kinect v2 to control the mouse.
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+	/// Only show log messages at or above this level.
+	#[arg(long, global = true, default_value_t = log::LevelFilter::Info)]
+	pub log_level: log::LevelFilter,
+	/// Only show log messages from libfreenect2 at or above this level.
+	#[arg(long, global = true, default_value_t = log::LevelFilter::Warn)]
+	pub libfreenect2_log_level: log::LevelFilter,
+	/// The configuration file to use, rather than `~/.config/kinect-to-x11/config.toml`.
+	#[arg(long, global = true)]
+	pub config: Option<PathBuf>,
+
+	#[command(subcommand)]
+	pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+	/// Control the pointer with your hand until interrupted.
+	Run {
+		#[command(flatten)]
+		device: DeviceArgs,
+		/// Where to send pointer events: `xtest`, `uinput`, `record` (to standard output), or `record:<path>`.
+		#[arg(long, default_value = "xtest")]
+		output: OutputKind,
+		/// How to smooth the pointer position: `none`, `one-euro`, or `kalman`.
+		#[arg(long)]
+		filter: Option<FilterConfig>,
+		/// The calibration file to use.
+		#[arg(long)]
+		calibration: Option<PathBuf>,
+	},
+	/// Map hand positions to the screen by pointing at targets.
+	Calibrate {
+		#[command(flatten)]
+		device: DeviceArgs,
+		/// Where to save the calibration.
+		#[arg(long)]
+		calibration: Option<PathBuf>,
+	},
+	/// Save images from a single color frame and a single depth frame.
+	Snapshot {
+		#[command(flatten)]
+		device: DeviceArgs,
+		/// Which streams to capture.
+		#[arg(long, value_enum, default_value_t = Streams::All)]
+		streams: Streams,
+		/// Where to save the color image.
+		#[arg(long, default_value = "color.png")]
+		color_path: PathBuf,
+		/// Where to save the depth image, mapped onto the color image.
+		#[arg(long, default_value = "depth.png")]
+		depth_path: PathBuf,
+		/// Where to save the raw depth image.
+		#[arg(long, default_value = "depth-distorted.png")]
+		depth_distorted_path: PathBuf,
+	},
+	/// List connected devices and the available packet pipelines.
+	List,
+	/// Show the serial number, firmware version, and camera parameters of a device.
+	Info {
+		#[command(flatten)]
+		device: DeviceArgs,
+		/// Also save the camera parameters to this file, as JSON if it ends in `.json` and as TOML otherwise.
+		#[arg(long)]
+		save_calibration: Option<PathBuf>,
+	},
+}
+
+/// Which streams to start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Streams {
+	/// The color and depth streams.
+	All,
+	/// Only the color stream.
+	Color,
+	/// Only the depth stream.
+	Depth,
+}
+
+impl Streams {
+	pub fn color(self) -> bool {
+		matches!(self, Self::All | Self::Color)
+	}
+
+	pub fn depth(self) -> bool {
+		matches!(self, Self::All | Self::Depth)
+	}
+}
+
+/// Options for selecting and configuring the device.
+#[derive(Debug, Args)]
+pub struct DeviceArgs {
+	/// The serial number of the device to open, rather than the default device.
+	#[arg(long)]
+	pub serial: Option<String>,
+	/// How to decode packets from the device: `cpu`, `opengl`, `opencl`, `cuda`, or `dump`, rather than the best available.
+	#[arg(long)]
+	pub pipeline: Option<PacketPipeline>,
+	/// Clip depth at this minimum, in meters.
+	#[arg(long)]
+	pub min_depth: Option<f32>,
+	/// Clip depth at this maximum, in meters.
+	#[arg(long)]
+	pub max_depth: Option<f32>,
+	/// Disable the bilateral filter, which removes some "flying pixels".
+	#[arg(long)]
+	pub no_bilateral_filter: bool,
+	/// Disable the edge-aware filter, which removes noisy pixels on edges.
+	#[arg(long)]
+	pub no_edge_aware_filter: bool,
+	/// Use the camera parameters saved in this file by `info --save-calibration`, rather than the device's factory calibration.
+	#[arg(long)]
+	pub camera_calibration: Option<PathBuf>,
+}
+
+impl DeviceArgs {
+	fn override_config(&self, config: &mut DeviceConfig) {
+		if let Some(min_depth) = self.min_depth {
+			config.min_depth = min_depth;
+		}
+		if let Some(max_depth) = self.max_depth {
+			config.max_depth = max_depth;
+		}
+		if self.no_bilateral_filter {
+			config.bilateral_filter = false;
+		}
+		if self.no_edge_aware_filter {
+			config.edge_aware_filter = false;
+		}
+	}
+}
+
+impl Command {
+	/// The options for selecting and configuring the device, if this command uses one.
+	pub fn device(&self) -> Option<&DeviceArgs> {
+		match self {
+			Self::Run { device, .. }
+			| Self::Calibrate { device, .. }
+			| Self::Snapshot { device, .. }
+			| Self::Info { device, .. } => Some(device),
+			Self::List => None,
+		}
+	}
+
+	/// Replace the values in `config` that were given on the command line.
+	pub fn override_config(&self, config: &mut Config) {
+		if let Some(device) = self.device() {
+			device.override_config(&mut config.device);
+		}
+
+		match self {
+			Self::Run {
+				filter,
+				calibration,
+				..
+			} => {
+				// keep the parameters from the configuration file if it selects the same filter
+				if let Some(filter) = filter {
+					if std::mem::discriminant(filter) != std::mem::discriminant(&config.filter) {
+						config.filter = *filter;
+					}
+				}
+				if calibration.is_some() {
+					config.mapping.calibration.clone_from(calibration);
+				}
+			}
+			Self::Calibrate { calibration, .. } => {
+				if calibration.is_some() {
+					config.mapping.calibration.clone_from(calibration);
+				}
+			}
+			Self::Snapshot { .. } | Self::List | Self::Info { .. } => (),
+		}
+	}
+}
@@ -0,0 +1,400 @@
+//! Loads tunable parameters from a TOML file and watches it for changes.
+//!
+//! Every section and field is optional; missing values take their defaults.
+//! For example:
+//!
+//! ```toml
+//! [device]
+//! max_depth = 2.0
+//!
+//! [tracker]
+//! max_depth = 1200.0
+//!
+//! [filter]
+//! kind = "kalman"
+//! measurement_noise = 50.0
+//!
+//! [click]
+//! push_distance = 80.0
+//! ```
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use freenect2::device::DepthConfig;
+use inotify::{Inotify, WatchMask};
+use serde::{Deserialize, Serialize};
+
+use crate::filter::FilterConfig;
+use crate::gesture::ClickConfig;
+use crate::tracker::TrackerConfig;
+
+/// The directory in which configuration and calibration files are stored by default.
+///
+/// This is `$XDG_CONFIG_HOME/kinect-to-x11`, or `~/.config/kinect-to-x11` if `XDG_CONFIG_HOME` is not set.
+#[must_use]
+pub fn config_dir() -> PathBuf {
+	let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+		.map(PathBuf::from)
+		.or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+		.unwrap_or_default();
+	config_dir.join("kinect-to-x11")
+}
+
+/// Depth processing options for the device, applied with [`freenect2::Device::set_depth_config`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeviceConfig {
+	/// Clip depth at this minimum, in meters.
+	pub min_depth: f32,
+	/// Clip depth at this maximum, in meters.
+	pub max_depth: f32,
+	/// Remove some "flying pixels".
+	pub bilateral_filter: bool,
+	/// Remove noisy pixels on edges.
+	pub edge_aware_filter: bool,
+}
+
+impl Default for DeviceConfig {
+	fn default() -> Self {
+		DepthConfig::default().into()
+	}
+}
+
+impl From<DepthConfig> for DeviceConfig {
+	fn from(config: DepthConfig) -> Self {
+		Self {
+			min_depth: config.min_depth,
+			max_depth: config.max_depth,
+			bilateral_filter: config.enable_bilateral_filter,
+			edge_aware_filter: config.enable_edge_aware_filter,
+		}
+	}
+}
+
+impl From<DeviceConfig> for DepthConfig {
+	fn from(config: DeviceConfig) -> Self {
+		Self {
+			min_depth: config.min_depth,
+			max_depth: config.max_depth,
+			enable_bilateral_filter: config.bilateral_filter,
+			enable_edge_aware_filter: config.edge_aware_filter,
+		}
+	}
+}
+
+/// How hand positions are mapped to the screen.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MappingConfig {
+	/// The calibration file, or `None` for [`Calibration::default_path`](crate::mapping::Calibration::default_path).
+	pub calibration: Option<PathBuf>,
+	/// The screen size used by output backends that cannot query it, if there is no calibration.
+	pub screen_size: [u16; 2],
+}
+
+impl Default for MappingConfig {
+	fn default() -> Self {
+		Self {
+			calibration: None,
+			screen_size: [1920, 1080],
+		}
+	}
+}
+
+/// The range of depths spanned by the colors of depth snapshots.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colormap {
+	/// The depth that is colored red, in millimeters.
+	pub near: f32,
+	/// The depth that is colored blue, in millimeters.
+	pub far: f32,
+}
+
+impl Default for Colormap {
+	fn default() -> Self {
+		Self {
+			near: 0.0,
+			far: 4000.0,
+		}
+	}
+}
+
+/// All tunable parameters.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+	/// How the device processes depth, in the `[device]` section.
+	pub device: DeviceConfig,
+	/// How the hand is found in each depth frame, in the `[tracker]` section.
+	pub tracker: TrackerConfig,
+	/// How the pointer position is smoothed, in the `[filter]` section.
+	pub filter: FilterConfig,
+	/// How pushes are recognized as clicks and drags, in the `[click]` section.
+	pub click: ClickConfig,
+	/// How hand positions are mapped to the screen, in the `[mapping]` section.
+	pub mapping: MappingConfig,
+	/// How depth snapshots are colored, in the `[colormap]` section.
+	pub colormap: Colormap,
+}
+
+/// An error that occurred while loading a [`Config`].
+#[derive(Debug)]
+pub enum Error {
+	/// The file could not be read.
+	Io(std::io::Error),
+	/// The file is not valid TOML or does not match the expected structure.
+	Parse(toml::de::Error),
+	/// The file is well-formed, but a value is out of range.
+	Invalid(&'static str),
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Io(error) => write!(formatter, "could not read configuration: {error}"),
+			Self::Parse(error) => write!(formatter, "could not parse configuration: {error}"),
+			Self::Invalid(reason) => write!(formatter, "invalid configuration: {reason}"),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::Io(error) => Some(error),
+			Self::Parse(error) => Some(error),
+			Self::Invalid(_) => None,
+		}
+	}
+}
+
+/// Fail with `reason` unless `condition` holds.
+///
+/// Conditions are written so that NaN fails them.
+fn ensure(condition: bool, reason: &'static str) -> Result<(), Error> {
+	if condition {
+		Ok(())
+	} else {
+		Err(Error::Invalid(reason))
+	}
+}
+
+impl Config {
+	/// The default location of the configuration file, `config.toml` in [`config_dir`].
+	#[must_use]
+	pub fn default_path() -> PathBuf {
+		config_dir().join("config.toml")
+	}
+
+	/// Parse and validate a configuration.
+	///
+	/// # Errors
+	///
+	/// Fails if `raw` is not a valid configuration.
+	pub fn parse(raw: &str) -> Result<Self, Error> {
+		let config: Self = toml::from_str(raw).map_err(Error::Parse)?;
+		config.validate()?;
+		Ok(config)
+	}
+
+	/// Load a configuration from `path`.
+	///
+	/// # Errors
+	///
+	/// Fails if the file cannot be read or is not a valid configuration.
+	pub fn load(path: &Path) -> Result<Self, Error> {
+		let raw = std::fs::read_to_string(path).map_err(Error::Io)?;
+		Self::parse(&raw)
+	}
+
+	/// Load a configuration from `path`, or use the defaults if it does not exist.
+	///
+	/// # Errors
+	///
+	/// Fails if the file exists but cannot be read or is not a valid configuration.
+	pub fn load_or_default(path: &Path) -> Result<Self, Error> {
+		match Self::load(path) {
+			Err(Error::Io(error)) if error.kind() == std::io::ErrorKind::NotFound => {
+				log::debug!(
+					"{} does not exist; using the default configuration",
+					path.display()
+				);
+				Ok(Self::default())
+			}
+			result => result,
+		}
+	}
+
+	/// Check that every value is in range.
+	///
+	/// # Errors
+	///
+	/// Fails with a description of the first value that is out of range.
+	pub fn validate(&self) -> Result<(), Error> {
+		let Self {
+			device,
+			tracker,
+			filter,
+			click,
+			mapping,
+			colormap,
+		} = self;
+
+		ensure(
+			device.min_depth >= 0.0,
+			"`device.min_depth` must not be negative",
+		)?;
+		ensure(
+			device.min_depth < device.max_depth,
+			"`device.min_depth` must be less than `device.max_depth`",
+		)?;
+
+		ensure(
+			tracker.min_depth >= 0.0,
+			"`tracker.min_depth` must not be negative",
+		)?;
+		ensure(
+			tracker.min_depth < tracker.max_depth,
+			"`tracker.min_depth` must be less than `tracker.max_depth`",
+		)?;
+		ensure(
+			tracker.hand_depth > 0.0,
+			"`tracker.hand_depth` must be positive",
+		)?;
+		ensure(tracker.min_area > 0, "`tracker.min_area` must be positive")?;
+
+		match filter {
+			FilterConfig::None => (),
+			FilterConfig::OneEuro(one_euro) => {
+				ensure(
+					one_euro.min_cutoff > 0.0,
+					"`filter.min_cutoff` must be positive",
+				)?;
+				ensure(one_euro.beta >= 0.0, "`filter.beta` must not be negative")?;
+				ensure(
+					one_euro.derivative_cutoff > 0.0,
+					"`filter.derivative_cutoff` must be positive",
+				)?;
+			}
+			FilterConfig::Kalman(kalman) => {
+				ensure(
+					kalman.process_noise > 0.0,
+					"`filter.process_noise` must be positive",
+				)?;
+				ensure(
+					kalman.measurement_noise > 0.0,
+					"`filter.measurement_noise` must be positive",
+				)?;
+			}
+		}
+
+		ensure(
+			click.push_distance > 0.0,
+			"`click.push_distance` must be positive",
+		)?;
+		ensure(
+			click.push_duration > 0,
+			"`click.push_duration` must be positive",
+		)?;
+		ensure(
+			click.release_distance > 0.0,
+			"`click.release_distance` must be positive",
+		)?;
+
+		ensure(
+			mapping.screen_size[0] > 0 && mapping.screen_size[1] > 0,
+			"`mapping.screen_size` must not be empty",
+		)?;
+
+		ensure(
+			colormap.near < colormap.far,
+			"`colormap.near` must be less than `colormap.far`",
+		)?;
+
+		Ok(())
+	}
+}
+
+/// Watches a configuration file for changes using inotify.
+///
+/// The containing directory is watched rather than the file itself, so that editors that save by replacing the file are noticed.
+pub struct ConfigWatcher {
+	path: PathBuf,
+	file_name: OsString,
+	inotify: Inotify,
+	buffer: Box<[u8]>,
+}
+
+impl ConfigWatcher {
+	/// Start watching `path`.
+	///
+	/// The directory containing `path` is created if it does not exist, so that the file can be created later.
+	///
+	/// # Errors
+	///
+	/// Fails if inotify is unavailable or if the directory containing `path` cannot be created or watched.
+	pub fn new(path: &Path) -> std::io::Result<Self> {
+		let file_name = path
+			.file_name()
+			.ok_or_else(|| {
+				std::io::Error::new(
+					std::io::ErrorKind::InvalidInput,
+					"configuration path has no file name",
+				)
+			})?
+			.to_owned();
+		let dir = match path.parent() {
+			Some(dir) if !dir.as_os_str().is_empty() => dir,
+			_ => Path::new("."),
+		};
+
+		std::fs::create_dir_all(dir)?;
+		let inotify = Inotify::init()?;
+		inotify
+			.watches()
+			.add(dir, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO)?;
+
+		Ok(Self {
+			path: path.to_owned(),
+			file_name,
+			inotify,
+			buffer: vec![0; 4096].into_boxed_slice(),
+		})
+	}
+
+	/// The path of the watched file.
+	#[must_use]
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Check, without blocking, whether the file has been written since the last call.
+	///
+	/// Returns `None` if it has not, or the result of reloading it if it has.
+	pub fn poll(&mut self) -> Option<Result<Config, Error>> {
+		let mut changed = false;
+		loop {
+			match self.inotify.read_events(&mut self.buffer) {
+				Ok(events) => {
+					let mut any = false;
+					for event in events {
+						any = true;
+						changed |= event.name == Some(self.file_name.as_os_str());
+					}
+					if !any {
+						break;
+					}
+				}
+				Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => break,
+				Err(error) => {
+					log::warn!("could not read inotify events: {error}");
+					break;
+				}
+			}
+		}
+
+		changed.then(|| Config::load(&self.path))
+	}
+}
@@ -0,0 +1,313 @@
+//! Smooths the pointer position to remove jitter.
+//!
+//! Filters are stepped using frame timestamps rather than the wall clock, so that they behave the same when frames are replayed.
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// Frame timestamps are in units of 100 microseconds.
+const TIMESTAMPS_PER_SECOND: f32 = 10_000.0;
+
+/// Convert the difference between two frame timestamps to seconds.
+///
+/// Timestamps wrap around, so `later` is taken to be before `earlier` if it is more than half the range of timestamps after it, in which case this returns `None`.
+fn seconds_between(earlier: u32, later: u32) -> Option<f32> {
+	let difference = later.wrapping_sub(earlier);
+	(difference <= u32::MAX / 2).then(|| az::cast::<_, f32>(difference) / TIMESTAMPS_PER_SECOND)
+}
+
+/// A stage that smooths a stream of positions.
+pub trait Filter {
+	/// Add a position observed at `timestamp` and return the smoothed position.
+	///
+	/// If `timestamp` is before that of the previous position, the filter starts again from this position, as after [`reset`](Self::reset).
+	fn filter(&mut self, timestamp: u32, position: Vec2) -> Vec2;
+
+	/// Forget all previous positions, for example because the hand was lost.
+	fn reset(&mut self);
+}
+
+/// Selects a [`Filter`] and its parameters.
+///
+/// In configuration files, the filter is selected by the `kind` field, which takes the same names as [`FromStr`](std::str::FromStr), and its parameters are given alongside.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum FilterConfig {
+	/// Do not smooth at all.
+	None,
+	/// Use a [`OneEuroFilter`].
+	OneEuro(OneEuroConfig),
+	/// Use a [`KalmanFilter`].
+	Kalman(KalmanConfig),
+}
+
+impl Default for FilterConfig {
+	fn default() -> Self {
+		Self::OneEuro(OneEuroConfig::default())
+	}
+}
+
+impl FilterConfig {
+	/// Create the filter that this configuration describes.
+	#[must_use]
+	pub fn build(self) -> Box<dyn Filter + Send> {
+		match self {
+			Self::None => Box::new(Passthrough),
+			Self::OneEuro(config) => Box::new(OneEuroFilter::new(config)),
+			Self::Kalman(config) => Box::new(KalmanFilter::new(config)),
+		}
+	}
+}
+
+/// Error returned when parsing a [`FilterConfig`] from an unknown filter name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownFilter;
+
+impl std::fmt::Display for UnknownFilter {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		formatter.write_str("unknown filter; expected `none`, `one-euro`, or `kalman`")
+	}
+}
+
+impl std::error::Error for UnknownFilter {}
+
+impl std::str::FromStr for FilterConfig {
+	type Err = UnknownFilter;
+
+	/// Select a filter by name, with its default parameters.
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		Ok(match name {
+			"none" => Self::None,
+			"one-euro" => Self::OneEuro(OneEuroConfig::default()),
+			"kalman" => Self::Kalman(KalmanConfig::default()),
+			_ => return Err(UnknownFilter),
+		})
+	}
+}
+
+/// A filter that returns positions unchanged.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Passthrough;
+
+impl Filter for Passthrough {
+	fn filter(&mut self, _timestamp: u32, position: Vec2) -> Vec2 {
+		position
+	}
+
+	fn reset(&mut self) {}
+}
+
+/// Tunable parameters for [`OneEuroFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OneEuroConfig {
+	/// The cutoff frequency when the position is not changing, in hertz.
+	///
+	/// Lower values remove more jitter while the hand is still.
+	pub min_cutoff: f32,
+	/// How much the cutoff frequency increases with speed.
+	///
+	/// Higher values reduce lag while the hand is moving.
+	pub beta: f32,
+	/// The cutoff frequency used to smooth the speed, in hertz.
+	pub derivative_cutoff: f32,
+}
+
+impl Default for OneEuroConfig {
+	fn default() -> Self {
+		Self {
+			min_cutoff: 1.0,
+			beta: 0.01,
+			derivative_cutoff: 1.0,
+		}
+	}
+}
+
+/// The smoothing factor of a first-order low-pass filter with the given cutoff frequency and time step.
+fn smoothing_factor(cutoff: f32, dt: f32) -> f32 {
+	let time_constant = 1.0 / (std::f32::consts::TAU * cutoff);
+	1.0 / (1.0 + time_constant / dt)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct OneEuroState {
+	timestamp: u32,
+	position: Vec2,
+	derivative: Vec2,
+}
+
+/// The [1€ filter](https://gery.casiez.net/1euro/), an adaptive low-pass filter that smooths heavily at low speeds and lightly at high speeds.
+#[derive(Debug, Clone, Copy)]
+pub struct OneEuroFilter {
+	config: OneEuroConfig,
+	state: Option<OneEuroState>,
+}
+
+impl OneEuroFilter {
+	/// Create a filter with the given parameters.
+	#[must_use]
+	pub fn new(config: OneEuroConfig) -> Self {
+		Self {
+			config,
+			state: None,
+		}
+	}
+}
+
+impl Filter for OneEuroFilter {
+	fn filter(&mut self, timestamp: u32, position: Vec2) -> Vec2 {
+		let previous = self
+			.state
+			.and_then(|state| Some((state, seconds_between(state.timestamp, timestamp)?)));
+		let Some((state, dt)) = previous else {
+			self.state = Some(OneEuroState {
+				timestamp,
+				position,
+				derivative: Vec2::ZERO,
+			});
+			return position;
+		};
+
+		if dt <= 0.0 {
+			return state.position;
+		}
+
+		let derivative = (position - state.position) / dt;
+		let derivative = state.derivative.lerp(
+			derivative,
+			smoothing_factor(self.config.derivative_cutoff, dt),
+		);
+
+		let cutoff = self.config.min_cutoff + self.config.beta * derivative.length();
+		let position = state.position.lerp(position, smoothing_factor(cutoff, dt));
+
+		self.state = Some(OneEuroState {
+			timestamp,
+			position,
+			derivative,
+		});
+		position
+	}
+
+	fn reset(&mut self) {
+		self.state = None;
+	}
+}
+
+/// Tunable parameters for [`KalmanFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KalmanConfig {
+	/// The variance of the acceleration of the hand, in square pixels per second to the fourth.
+	///
+	/// Higher values follow changes in speed more quickly.
+	pub process_noise: f32,
+	/// The variance of the measured position, in square pixels.
+	///
+	/// Higher values remove more jitter.
+	pub measurement_noise: f32,
+}
+
+impl Default for KalmanConfig {
+	fn default() -> Self {
+		Self {
+			process_noise: 50_000.0,
+			measurement_noise: 25.0,
+		}
+	}
+}
+
+/// The state of the Kalman filter along one axis.
+#[derive(Debug, Clone, Copy)]
+struct KalmanAxis {
+	position: f32,
+	velocity: f32,
+	covariance: [[f32; 2]; 2],
+}
+
+impl KalmanAxis {
+	fn new(position: f32, measurement_noise: f32) -> Self {
+		Self {
+			position,
+			velocity: 0.0,
+			covariance: [[measurement_noise, 0.0], [0.0, measurement_noise]],
+		}
+	}
+
+	fn step(&mut self, config: KalmanConfig, dt: f32, measured: f32) -> f32 {
+		// predict, assuming constant velocity
+		self.position += self.velocity * dt;
+		let [[p00, p01], [p10, p11]] = self.covariance;
+		let q = config.process_noise;
+		let (dt2, dt3, dt4) = (dt * dt, dt * dt * dt, dt * dt * dt * dt);
+		let p00 = p00 + dt * (p10 + p01) + dt2 * p11 + q * dt4 / 4.0;
+		let p01 = p01 + dt * p11 + q * dt3 / 2.0;
+		let p10 = p10 + dt * p11 + q * dt3 / 2.0;
+		let p11 = p11 + q * dt2;
+
+		// update with the measured position
+		let innovation = measured - self.position;
+		let innovation_covariance = p00 + config.measurement_noise;
+		let gain = [p00 / innovation_covariance, p10 / innovation_covariance];
+		self.position += gain[0] * innovation;
+		self.velocity += gain[1] * innovation;
+		self.covariance = [
+			[(1.0 - gain[0]) * p00, (1.0 - gain[0]) * p01],
+			[p10 - gain[1] * p00, p11 - gain[1] * p01],
+		];
+
+		self.position
+	}
+}
+
+/// A Kalman filter that models the hand as moving at a constant velocity, independently along each axis.
+#[derive(Debug, Clone, Copy)]
+pub struct KalmanFilter {
+	config: KalmanConfig,
+	state: Option<(u32, [KalmanAxis; 2])>,
+}
+
+impl KalmanFilter {
+	/// Create a filter with the given parameters.
+	#[must_use]
+	pub fn new(config: KalmanConfig) -> Self {
+		Self {
+			config,
+			state: None,
+		}
+	}
+}
+
+impl Filter for KalmanFilter {
+	fn filter(&mut self, timestamp: u32, position: Vec2) -> Vec2 {
+		let previous = self
+			.state
+			.and_then(|(last_timestamp, axes)| Some((axes, seconds_between(last_timestamp, timestamp)?)));
+		let Some((mut axes, dt)) = previous else {
+			let noise = self.config.measurement_noise;
+			self.state = Some((
+				timestamp,
+				[
+					KalmanAxis::new(position.x, noise),
+					KalmanAxis::new(position.y, noise),
+				],
+			));
+			return position;
+		};
+
+		if dt <= 0.0 {
+			return Vec2::new(axes[0].position, axes[1].position);
+		}
+
+		let position = Vec2::new(
+			axes[0].step(self.config, dt, position.x),
+			axes[1].step(self.config, dt, position.y),
+		);
+		self.state = Some((timestamp, axes));
+		position
+	}
+
+	fn reset(&mut self) {
+		self.state = None;
+	}
+}
@@ -0,0 +1,187 @@
+//! Detects clicks and drags from the depth of the hand over time.
+//!
+//! Pushing the hand quickly toward the sensor presses the button.
+//! Pulling it back releases it again.
+//! If the hand is kept pushed for long enough, the press becomes a drag that lasts until the hand is pulled back.
+//!
+//! All times are in units of 100 microseconds, the same as [`freenect2::Frame::timestamp`].
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+/// Tunable parameters for [`ClickDetector`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClickConfig {
+	/// How far the hand must move toward the sensor to count as a push, in millimeters.
+	pub push_distance: f32,
+	/// The longest time that a push may take.
+	pub push_duration: u32,
+	/// How far the hand must be pulled back from its closest depth during the push to release the button, in millimeters.
+	///
+	/// This should be smaller than `push_distance` to provide some hysteresis.
+	pub release_distance: f32,
+	/// How long the hand must stay pushed for the press to become a drag.
+	pub drag_delay: u32,
+	/// How long to ignore pushes after the button is released.
+	pub debounce: u32,
+}
+
+impl Default for ClickConfig {
+	fn default() -> Self {
+		Self {
+			push_distance: 60.0,
+			push_duration: 3_000,
+			release_distance: 40.0,
+			drag_delay: 5_000,
+			debounce: 2_500,
+		}
+	}
+}
+
+/// A gesture recognized by [`ClickDetector`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+	/// The hand was pushed and pulled back again; the button should be pressed and released.
+	Click,
+	/// The hand was pushed and held; the button should be pressed.
+	DragStart,
+	/// The hand was pulled back after a drag; the button should be released.
+	DragEnd,
+}
+
+#[derive(Debug)]
+enum State {
+	/// Waiting for a push.
+	Idle,
+	/// The hand has been pushed but it is not yet known whether this is a click or a drag.
+	Pushed {
+		/// When the push was recognized.
+		since: u32,
+		/// The closest depth of the hand since the push was recognized.
+		closest: f32,
+	},
+	/// The button is held down.
+	Dragging {
+		/// The closest depth of the hand since the push was recognized.
+		closest: f32,
+	},
+}
+
+/// Turns a sequence of timestamped hand depths into [`Gesture`]s.
+#[derive(Debug)]
+pub struct ClickDetector {
+	config: ClickConfig,
+	state: State,
+	/// Recent samples while idle, used to recognize pushes.
+	history: VecDeque<(u32, f32)>,
+	last_release: Option<u32>,
+}
+
+impl ClickDetector {
+	/// Create a detector with the given parameters.
+	#[must_use]
+	pub fn new(config: ClickConfig) -> Self {
+		Self {
+			config,
+			state: State::Idle,
+			history: VecDeque::new(),
+			last_release: None,
+		}
+	}
+
+	/// The configuration of the detector.
+	#[must_use]
+	pub fn config(&self) -> ClickConfig {
+		self.config
+	}
+
+	/// Change the parameters used for subsequent samples.
+	///
+	/// A gesture in progress continues with the new parameters.
+	pub fn set_config(&mut self, config: ClickConfig) {
+		self.config = config;
+	}
+
+	/// Feed the mean depth of the hand, in millimeters, at `timestamp`.
+	pub fn update(&mut self, timestamp: u32, depth: f32) -> Option<Gesture> {
+		match &mut self.state {
+			State::Idle => {
+				while let Some(&(oldest, _)) = self.history.front() {
+					if timestamp.wrapping_sub(oldest) > self.config.push_duration {
+						self.history.pop_front();
+					} else {
+						break;
+					}
+				}
+				self.history.push_back((timestamp, depth));
+
+				let debouncing = self
+					.last_release
+					.is_some_and(|last_release| timestamp.wrapping_sub(last_release) < self.config.debounce);
+				let farthest = self
+					.history
+					.iter()
+					.map(|&(_, depth)| depth)
+					.fold(f32::NEG_INFINITY, f32::max);
+				if !debouncing && farthest - depth >= self.config.push_distance {
+					log::debug!("push recognized at depth {depth}");
+					self.history.clear();
+					self.state = State::Pushed {
+						since: timestamp,
+						closest: depth,
+					};
+				}
+				None
+			}
+			State::Pushed { since, closest } => {
+				*closest = closest.min(depth);
+				if depth - *closest >= self.config.release_distance {
+					self.release(timestamp);
+					Some(Gesture::Click)
+				} else if timestamp.wrapping_sub(*since) >= self.config.drag_delay {
+					self.state = State::Dragging { closest: *closest };
+					Some(Gesture::DragStart)
+				} else {
+					None
+				}
+			}
+			State::Dragging { closest } => {
+				*closest = closest.min(depth);
+				if depth - *closest >= self.config.release_distance {
+					self.release(timestamp);
+					Some(Gesture::DragEnd)
+				} else {
+					None
+				}
+			}
+		}
+	}
+
+	/// Forget the hand, for example because it could no longer be tracked.
+	///
+	/// Returns [`Gesture::DragEnd`] if a drag was in progress, so that the button is not left pressed.
+	pub fn reset(&mut self, timestamp: u32) -> Option<Gesture> {
+		match self.state {
+			State::Idle => {
+				self.history.clear();
+				None
+			}
+			State::Pushed { .. } => {
+				self.release(timestamp);
+				None
+			}
+			State::Dragging { .. } => {
+				self.release(timestamp);
+				Some(Gesture::DragEnd)
+			}
+		}
+	}
+
+	fn release(&mut self, timestamp: u32) {
+		self.state = State::Idle;
+		self.history.clear();
+		self.last_release = Some(timestamp);
+	}
+}
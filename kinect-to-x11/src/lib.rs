@@ -0,0 +1,37 @@
+//! Use a Kinect v2 to control the mouse.
+//!
+//! The pipeline is: [`stream`] receives depth frames, [`tracker`] finds the hand in each frame, [`mapping`] maps its position to the screen, [`filter`] smooths it, [`gesture`] recognizes clicks, and [`output`] sends the resulting events to the desktop.
+//! The parameters of each stage are loaded from a [`config`] file.
+
+#![deny(
+	absolute_paths_not_starting_with_crate,
+	future_incompatible,
+	keyword_idents,
+	macro_use_extern_crate,
+	meta_variable_misuse,
+	missing_abi,
+	missing_copy_implementations,
+	non_ascii_idents,
+	nonstandard_style,
+	noop_method_call,
+	pointer_structural_match,
+	private_in_public,
+	rust_2018_idioms,
+	unused_qualifications
+)]
+#![warn(clippy::pedantic)]
+#![allow(clippy::let_underscore_drop)]
+#![forbid(unsafe_code)]
+
+pub mod calibration;
+pub mod config;
+pub mod filter;
+pub mod gesture;
+pub mod mapping;
+pub mod output;
+pub mod snapshot;
+pub mod stream;
+pub mod tracker;
+pub mod transformer;
+
+pub use freenect2::frame::{DEPTH_HEIGHT, DEPTH_WIDTH};
@@ -18,148 +18,384 @@
 #![allow(clippy::let_underscore_drop)]
 #![forbid(unsafe_code)]
 
-use bytemuck::zeroed_box;
-use freenect2::{Context, Device, FrameFormat, FrameType};
-
-mod transformer;
-use self::transformer::Transformer;
-
-fn init_logging() {
-	simplelog::TermLogger::init(
-		log::LevelFilter::Trace,
-		simplelog::ConfigBuilder::new()
-			.add_filter_allow_str("kinect_to_x11")
-			.build(),
-		simplelog::TerminalMode::Stderr,
-		simplelog::ColorChoice::Auto,
-	)
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use clap::Parser;
+use freenect2::calibration::{Calibration as CameraCalibration, CalibrationError};
+use freenect2::{Context, Device, DeviceInfo, FrameType, PacketPipeline};
+use glam::Vec2;
+use kinect_to_x11::config::{Config, ConfigWatcher};
+use kinect_to_x11::gesture::ClickDetector;
+use kinect_to_x11::mapping::{self, Calibration, Homography};
+use kinect_to_x11::output::{self, Button, Output};
+use kinect_to_x11::stream::DepthFrames;
+use kinect_to_x11::tracker::HandTracker;
+use kinect_to_x11::{calibration, snapshot, DEPTH_HEIGHT, DEPTH_WIDTH};
+
+mod cli;
+use self::cli::{Cli, Command};
+
+/// The pointer button that is pressed by gestures.
+const BUTTON: Button = Button::Left;
+
+/// Show messages from this crate up to `level` and messages from libfreenect2 up to `libfreenect2_level`.
+fn init_logging(level: log::LevelFilter, libfreenect2_level: log::LevelFilter) {
+	let logger = |level, target| {
+		simplelog::TermLogger::new(
+			level,
+			simplelog::ConfigBuilder::new()
+				.add_filter_allow_str(target)
+				.build(),
+			simplelog::TerminalMode::Stderr,
+			simplelog::ColorChoice::Auto,
+		)
+	};
+	simplelog::CombinedLogger::init(vec![
+		logger(level, "kinect_to_x11"),
+		logger(libfreenect2_level, "libfreenect2"),
+	])
 	.unwrap();
+	freenect2::logger::set_native_level(Some(libfreenect2_level));
 }
 
 fn main() {
-	init_logging();
+	let cli = Cli::parse();
+	init_logging(cli.log_level, cli.libfreenect2_log_level);
 
 	let mut ctx = Context::new();
-	if let Some(device) = ctx.open_default_device() {
-		log::info!("opened device");
+	let Some(device_args) = cli.command.device() else {
+		list(&ctx);
+		return;
+	};
 
-		run(device);
-	} else {
-		log::error!("no devices available");
+	let config_path = cli.config.clone().unwrap_or_else(Config::default_path);
+	let config = Config::load_or_default(&config_path).and_then(|mut config| {
+		cli.command.override_config(&mut config);
+		config.validate().map(|()| config)
+	});
+	let config = match config {
+		Ok(config) => config,
+		Err(error) => {
+			log::error!("{}: {error}", config_path.display());
+			return;
+		}
+	};
+
+	let opened = match (&device_args.serial, device_args.pipeline) {
+		(Some(serial), Some(pipeline)) => ctx.open_by_serial_with_pipeline(serial, pipeline),
+		(None, Some(pipeline)) => ctx.open_default_device_with_pipeline(pipeline),
+		(Some(serial), None) => ctx.open_device_by_serial(serial),
+		(None, None) => ctx.open_default_device(),
+	};
+	let mut device = match opened {
+		Ok(device) => device,
+		Err(error) => {
+			log::error!("{error}");
+			return;
+		}
+	};
+	log::info!("opened device {}", device.serial_number());
+	device.set_depth_config(config.device.into());
+	if let Some(path) = &device_args.camera_calibration {
+		if let Err(error) = apply_camera_calibration(&mut device, path) {
+			log::error!("{}: {error}", path.display());
+			return;
+		}
 	}
-}
 
-fn run(mut device: Device) {
-	let (sender, recv) = std::sync::mpsc::sync_channel(4);
+	let calibration_path = config
+		.mapping
+		.calibration
+		.clone()
+		.unwrap_or_else(Calibration::default_path);
+	match &cli.command {
+		Command::List => unreachable!(),
+		Command::Info {
+			save_calibration, ..
+		} => info(&device, save_calibration.as_deref()),
+		Command::Snapshot {
+			streams,
+			color_path,
+			depth_path,
+			depth_distorted_path,
+			..
+		} => snapshot::run(
+			device,
+			streams.color(),
+			streams.depth(),
+			&snapshot::Paths {
+				color: color_path.clone(),
+				depth: depth_path.clone(),
+				depth_distorted: depth_distorted_path.clone(),
+			},
+			config.colormap,
+		),
+		Command::Calibrate { .. } => {
+			with_depth_frames(&mut device, |_, frames| {
+				if let Err(error) = calibration::run(frames, config.tracker, &calibration_path) {
+					log::error!("{error}");
+				}
+			});
+		}
+		Command::Run { output, .. } => {
+			let screen_size = Calibration::load(&calibration_path)
+				.map_or(config.mapping.screen_size, |calibration| {
+					calibration.screen_size
+				})
+				.into();
+			let mut watcher = match ConfigWatcher::new(&config_path) {
+				Ok(watcher) => Some(watcher),
+				Err(error) => {
+					log::warn!(
+						"not watching {} for changes: {error}",
+						config_path.display()
+					);
+					None
+				}
+			};
+			let reload = || reload_config(watcher.as_mut()?, &cli.command);
 
-	log::debug!("setting frame listener");
-	device.set_frame_listener(move |frame, ty| {
-		log::debug!("frame listener got frame");
-		let _ = sender.try_send((frame, ty));
+			match output.open(screen_size) {
+				Ok(mut output) => with_depth_frames(&mut device, |device, frames| {
+					run(device, frames, &mut *output, config, reload);
+				}),
+				Err(error) => log::error!("could not open output: {error}"),
+			}
+		}
+	}
+}
+
+/// Reload the configuration if it has changed, applying the command-line options of `command` on top.
+///
+/// Returns `None` if the configuration has not changed or if the new one is invalid, in which case the previous one should be kept.
+fn reload_config(watcher: &mut ConfigWatcher, command: &Command) -> Option<Config> {
+	let result = watcher.poll()?.and_then(|mut config| {
+		command.override_config(&mut config);
+		config.validate().map(|()| config)
 	});
+	match result {
+		Ok(config) => {
+			log::info!("reloaded configuration from {}", watcher.path().display());
+			Some(config)
+		}
+		Err(error) => {
+			log::error!(
+				"{}: {error}; keeping the previous configuration",
+				watcher.path().display()
+			);
+			None
+		}
+	}
+}
 
-	log::info!("starting device");
-	device.start().unwrap();
+fn list(ctx: &Context) {
+	let devices = ctx.devices();
+	if devices.is_empty() {
+		println!("no devices found");
+	}
+	for DeviceInfo { index, serial } in devices {
+		println!("{index}: {serial}");
+	}
 
-	let transformer = Transformer::for_device(&device);
+	let pipelines: Vec<_> = PacketPipeline::ALL
+		.into_iter()
+		.filter(|pipeline| pipeline.is_available())
+		.map(PacketPipeline::name)
+		.collect();
+	println!("available packet pipelines: {}", pipelines.join(", "));
+}
 
-	let mut color_thread = None;
-	let mut depth_threads = None;
+fn info(device: &Device, save_calibration: Option<&Path>) {
+	println!("serial number: {}", device.serial_number());
+	println!("firmware version: {}", device.firmware_version());
+	println!("IR camera parameters: {:#?}", device.ir_camera_params());
+	println!(
+		"color camera parameters: {:#?}",
+		device.color_camera_params()
+	);
 
-	log::debug!("starting frame loop");
-	while let Ok((mut frame, ty)) = recv.recv() {
-		log::debug!("receiver got message");
-		log::debug!("message is a frame");
-
-		match ty {
-			FrameType::Color => {
-				assert_eq!(frame.width(), 1920);
-				assert_eq!(frame.height(), 1080);
-				assert_eq!(frame.bytes_per_pixel(), 4);
-
-				match frame.format() {
-					FrameFormat::Rgbx => (),
-					FrameFormat::Bgrx => {
-						for rgbx in frame.data_mut().chunks_exact_mut(4) {
-							rgbx.swap(0, 2);
-						}
-					}
-					_ => unreachable!(),
-				}
+	if let Some(path) = save_calibration {
+		match CameraCalibration::for_device(device).save(path) {
+			Ok(()) => log::info!("saved calibration to {}", path.display()),
+			Err(error) => log::error!("{}: {error}", path.display()),
+		}
+	}
+}
 
-				if color_thread.is_none() {
-					color_thread = Some(std::thread::spawn(move || {
-						let mut image =
-							image::ImageBuffer::<image::Rgba<u8>, _>::from_raw(1920, 1080, frame.into_data())
-								.unwrap();
-						image::imageops::flip_vertical_in_place(&mut image);
-						image.save("color.png").unwrap();
-					}));
-				}
+/// Replace the camera parameters of `device` with those saved in `path`.
+fn apply_camera_calibration(device: &mut Device, path: &Path) -> Result<(), CalibrationError> {
+	let calibration = CameraCalibration::load(path)?;
+	if calibration.serial_number != device.serial_number() {
+		log::warn!(
+			"{}: calibration is for device {}",
+			path.display(),
+			calibration.serial_number
+		);
+	}
+	// the streams have not been started yet
+	calibration.apply(device).unwrap();
+	Ok(())
+}
+
+/// Start the depth stream, pass the device and the frames to `f` until interrupted, then stop the device.
+fn with_depth_frames(device: &mut Device, f: impl FnOnce(&Device, DepthFrames)) {
+	let running = Arc::new(AtomicBool::new(true));
+	{
+		let running = Arc::clone(&running);
+		ctrlc::set_handler(move || running.store(false, Ordering::SeqCst))
+			.expect("could not set interrupt handler");
+	}
+
+	match DepthFrames::start(device, running) {
+		Ok(frames) => f(device, frames),
+		Err(error) => {
+			log::error!("could not start device: {error}");
+			return;
+		}
+	}
+
+	log::info!("stopping device");
+	match device.stop() {
+		Ok(()) => (),
+		Err(freenect2::device::Error::ListenerPanicked) => {
+			log::error!("the frame listener panicked");
+			std::panic::resume_unwind(device.take_listener_panic().unwrap());
+		}
+		Err(error) => log::error!("could not stop device: {error}"),
+	}
+
+	let counts = device.frame_counts(FrameType::Depth);
+	let level = if counts.errors > 0 || counts.skipped > 0 {
+		log::Level::Warn
+	} else {
+		log::Level::Info
+	};
+	log::log!(
+		level,
+		"received {} depth frames, {} of which could not be decoded, and skipped {}",
+		counts.received,
+		counts.errors,
+		counts.skipped
+	);
+}
+
+fn load_mapping(path: &Path, screen_size: Vec2) -> Homography {
+	let uncalibrated = Homography::scale(
+		Vec2::new(az::cast(DEPTH_WIDTH), az::cast(DEPTH_HEIGHT)),
+		screen_size,
+	);
+
+	match Calibration::load(path) {
+		Ok(calibration) => {
+			if Vec2::from(calibration.screen_size.map(f32::from)) != screen_size {
+				log::warn!(
+					"calibration is for a screen size of {:?}, but the screen is {screen_size}; consider recalibrating with `kinect-to-x11 calibrate`",
+					calibration.screen_size,
+				);
 			}
-			FrameType::Depth => {
-				assert_eq!(frame.width(), 512);
-				assert_eq!(frame.height(), 424);
-				assert_eq!(frame.bytes_per_pixel(), 4);
-				assert_eq!(frame.format(), FrameFormat::Float);
-
-				let raw_depth: &[f32] = bytemuck::cast_slice(frame.data());
-				let mut depth_frame = zeroed_box::<[f32; 1920 * 1080]>();
-				transformer.depth_to_color(raw_depth, &mut *depth_frame);
-				let raw_depth = frame.into_data();
-
-				if depth_threads.is_none() {
-					depth_threads = Some((
-						std::thread::spawn(move || {
-							let image = image::ImageBuffer::from_fn(512, 424, |x, y| {
-								let depth = f32::from_ne_bytes(
-									raw_depth[az::cast::<_, usize>(y * 512 + x) * 4..][..4]
-										.try_into()
-										.unwrap(),
-								);
-								let proportion = depth / 4000.0;
-								let coolor::Rgb { r, g, b } = coolor::Hsl {
-									h: proportion * 240.0,
-									s: 1.0,
-									l: 0.5,
-								}
-								.to_rgb();
-								image::Rgb([r, g, b])
-							});
-							image.save("depth-distorted.png").unwrap();
-						}),
-						std::thread::spawn(move || {
-							let image = image::ImageBuffer::from_fn(1920, 1080, |x, y| {
-								let depth = depth_frame[az::cast::<_, usize>(y * 1920 + x)];
-								let proportion = depth / 4000.0;
-								let coolor::Rgb { r, g, b } = coolor::Hsl {
-									h: proportion * 240.0,
-									s: 1.0,
-									l: 0.5,
-								}
-								.to_rgb();
-								image::Rgb([r, g, b])
-							});
-							image.save("depth.png").unwrap();
-						}),
-					));
-				}
+			calibration.homography()
+		}
+		Err(mapping::Error::Io(error)) if error.kind() == std::io::ErrorKind::NotFound => {
+			log::warn!(
+				"not calibrated; run `kinect-to-x11 calibrate` to map hand positions to the screen accurately"
+			);
+			uncalibrated
+		}
+		Err(error) => {
+			log::error!(
+				"could not load calibration from {}: {error}",
+				path.display()
+			);
+			uncalibrated
+		}
+	}
+}
+
+/// Control `output` using the frames from `device`, applying each configuration returned by `reload` as it arrives.
+fn run(
+	device: &Device,
+	frames: DepthFrames,
+	output: &mut dyn Output,
+	mut config: Config,
+	mut reload: impl FnMut() -> Option<Config>,
+) {
+	let (screen_width, screen_height) = output.screen_size();
+	let screen_size = Vec2::new(screen_width.into(), screen_height.into());
+	let calibration_path = |config: &Config| {
+		config
+			.mapping
+			.calibration
+			.clone()
+			.unwrap_or_else(Calibration::default_path)
+	};
+	let mut mapping = load_mapping(&calibration_path(&config), screen_size);
+	let mut tracker = HandTracker::new(config.tracker);
+	let mut filter = config.filter.build();
+	let mut clicks = ClickDetector::new(config.click);
+	// the time of the last frame, at which the button is released when the loop ends
+	let mut timestamp = 0;
+
+	log::debug!("starting frame loop");
+	for frame in frames {
+		timestamp = frame.timestamp();
+		if let Some(new_config) = reload() {
+			if new_config.device != config.device {
+				device.set_depth_config(new_config.device.into());
 			}
-			FrameType::Ir => (),
+			if new_config.mapping != config.mapping {
+				mapping = load_mapping(&calibration_path(&new_config), screen_size);
+			}
+			if new_config.filter != config.filter {
+				filter = new_config.filter.build();
+			}
+			tracker.set_config(new_config.tracker);
+			clicks.set_config(new_config.click);
+			config = new_config;
 		}
 
-		if color_thread.is_some() && depth_threads.is_some() {
+		let depth = match frame.as_depth() {
+			Ok(depth) => depth,
+			Err(error) => {
+				log::warn!("skipping invalid depth frame: {error}");
+				continue;
+			}
+		};
+		let located = tracker
+			.track(depth.pixels().try_into().unwrap())
+			.and_then(|hand| {
+				log::trace!("tracked hand: {hand:?}");
+				let position = mapping.apply(hand.centroid);
+				if position.is_none() {
+					log::trace!(
+						"hand position {} is not mapped to the screen",
+						hand.centroid
+					);
+				}
+				Some((hand, position?))
+			});
+		let result = if let Some((hand, position)) = located {
+			let screen = filter.filter(timestamp, position);
+			let screen_x: i16 = az::saturating_cast(screen.x);
+			let screen_y: i16 = az::saturating_cast(screen.y);
+			log::trace!("moving pointer to ({screen_x}, {screen_y})");
+			output.move_to(screen_x, screen_y).and_then(|()| {
+				output::apply_gesture(output, BUTTON, clicks.update(timestamp, hand.mean_depth))
+			})
+		} else {
+			filter.reset();
+			output::apply_gesture(output, BUTTON, clicks.reset(timestamp))
+		};
+
+		if let Err(error) = result {
+			log::error!("could not control pointer: {error}");
 			break;
 		}
 	}
 	log::info!("frame loop finished");
 
-	log::info!("stopping device");
-	device.stop().unwrap();
-
-	log::info!("waiting for threads");
-	color_thread.unwrap().join().unwrap();
-	let depth_threads = depth_threads.unwrap();
-	depth_threads.0.join().unwrap();
-	depth_threads.1.join().unwrap();
+	if let Err(error) = output::apply_gesture(output, BUTTON, clicks.reset(timestamp)) {
+		log::error!("could not release pointer button: {error}");
+	}
 }
@@ -0,0 +1,182 @@
+//! Maps hand positions in depth image space to pixels on the screen.
+
+use std::path::{Path, PathBuf};
+
+use glam::{DMat3, DVec3, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// A projective transformation between two planes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Homography(DMat3);
+
+impl Homography {
+	/// A homography that linearly scales the rectangle from the origin to `from` onto the rectangle from the origin to `to`.
+	#[must_use]
+	pub fn scale(from: Vec2, to: Vec2) -> Self {
+		let factor = to.as_dvec2() / from.as_dvec2();
+		Self(DMat3::from_diagonal(factor.extend(1.0)))
+	}
+
+	/// Find the homography that maps each of `from` onto the corresponding point in `to`.
+	///
+	/// Returns `None` if the points are degenerate, for example if three of them are collinear.
+	#[must_use]
+	pub fn fit(from: [Vec2; 4], to: [Vec2; 4]) -> Option<Self> {
+		// Each correspondence gives two rows of the linear system `A h = b`, where `h` is the first eight entries of the row-major matrix and the ninth is fixed at 1.
+		let mut system = [[0.0f64; 9]; 8];
+		for (index, (from, to)) in from.iter().zip(&to).enumerate() {
+			let (x, y) = (f64::from(from.x), f64::from(from.y));
+			let (u, v) = (f64::from(to.x), f64::from(to.y));
+			system[index * 2] = [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, u];
+			system[index * 2 + 1] = [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, v];
+		}
+
+		let h = solve(system)?;
+		let rows = [
+			DVec3::new(h[0], h[1], h[2]),
+			DVec3::new(h[3], h[4], h[5]),
+			DVec3::new(h[6], h[7], 1.0),
+		];
+		Some(Self::from_rows(rows))
+	}
+
+	fn from_rows(rows: [DVec3; 3]) -> Self {
+		Self(DMat3::from_cols(rows[0], rows[1], rows[2]).transpose())
+	}
+
+	fn rows(&self) -> [DVec3; 3] {
+		let transposed = self.0.transpose();
+		[transposed.x_axis, transposed.y_axis, transposed.z_axis]
+	}
+
+	/// Transform a single point.
+	///
+	/// Returns `None` if the point has no finite image, for example because it lies on the line that is mapped to infinity.
+	#[allow(clippy::cast_possible_truncation)]
+	#[must_use]
+	pub fn apply(&self, point: Vec2) -> Option<Vec2> {
+		let projected = self.0 * point.as_dvec2().extend(1.0);
+		Some((projected.truncate() / projected.z).as_vec2()).filter(|point| point.is_finite())
+	}
+
+	/// Whether every point off a line has a finite image, which is not the case if the matrix has non-finite entries or is singular.
+	fn is_valid(&self) -> bool {
+		self.0.is_finite() && self.0.determinant() != 0.0
+	}
+}
+
+/// Solve an 8x8 linear system given as an augmented matrix using Gaussian elimination with partial pivoting.
+fn solve(mut system: [[f64; 9]; 8]) -> Option<[f64; 8]> {
+	const EPSILON: f64 = 1e-12;
+
+	for column in 0..8 {
+		let pivot =
+			(column..8).max_by(|&a, &b| system[a][column].abs().total_cmp(&system[b][column].abs()))?;
+		if system[pivot][column].abs() < EPSILON {
+			return None;
+		}
+		system.swap(column, pivot);
+
+		let pivot_row = system[column];
+		for (index, row) in system.iter_mut().enumerate() {
+			if index != column {
+				let factor = row[column] / pivot_row[column];
+				for (entry, pivot_entry) in row.iter_mut().zip(pivot_row).skip(column) {
+					*entry -= factor * pivot_entry;
+				}
+			}
+		}
+	}
+
+	let mut ret = [0.0; 8];
+	for (index, value) in ret.iter_mut().enumerate() {
+		*value = system[index][8] / system[index][index];
+	}
+	Some(ret)
+}
+
+/// The result of calibration, as stored on disk.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Calibration {
+	/// The size of the screen that was calibrated against, in pixels.
+	pub screen_size: [u16; 2],
+	/// The row-major homography from depth image space to screen space.
+	homography: [[f64; 3]; 3],
+}
+
+/// Errors that can occur while loading or saving a [`Calibration`].
+#[derive(Debug)]
+pub enum Error {
+	/// The file could not be read or written.
+	Io(std::io::Error),
+	/// The file is not a valid calibration.
+	Parse(toml::de::Error),
+	/// The calibration could not be serialized.
+	Serialize(toml::ser::Error),
+	/// The homography of the calibration has non-finite entries or is singular, so it does not map hand positions to the screen.
+	Degenerate,
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Io(error) => write!(formatter, "{error}"),
+			Self::Parse(error) => write!(formatter, "invalid calibration file: {error}"),
+			Self::Serialize(error) => write!(formatter, "could not serialize calibration: {error}"),
+			Self::Degenerate => formatter.write_str("the calibration's homography is degenerate"),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+impl Calibration {
+	/// Create a calibration of a screen of `screen_size` pixels with `homography` from depth image space to screen space.
+	#[must_use]
+	pub fn new(screen_size: [u16; 2], homography: Homography) -> Self {
+		Self {
+			screen_size,
+			homography: homography.rows().map(|row| row.to_array()),
+		}
+	}
+
+	/// The homography from depth image space to screen space.
+	#[must_use]
+	pub fn homography(&self) -> Homography {
+		Homography::from_rows(self.homography.map(DVec3::from_array))
+	}
+
+	/// The default location of the calibration file, in the user's configuration directory.
+	#[must_use]
+	pub fn default_path() -> PathBuf {
+		crate::config::config_dir().join("calibration.toml")
+	}
+
+	/// Load a calibration from `path`.
+	///
+	/// # Errors
+	///
+	/// Fails if the file cannot be read or is not a valid calibration, including if its homography is degenerate.
+	pub fn load(path: &Path) -> Result<Self, Error> {
+		let raw = std::fs::read_to_string(path).map_err(Error::Io)?;
+		let calibration: Self = toml::from_str(&raw).map_err(Error::Parse)?;
+		if calibration.homography().is_valid() {
+			Ok(calibration)
+		} else {
+			Err(Error::Degenerate)
+		}
+	}
+
+	/// Save the calibration to `path`, creating parent directories as necessary.
+	///
+	/// # Errors
+	///
+	/// Fails if the file cannot be written.
+	pub fn save(&self, path: &Path) -> Result<(), Error> {
+		let raw = toml::to_string(self).map_err(Error::Serialize)?;
+		if let Some(parent) = path.parent() {
+			std::fs::create_dir_all(parent).map_err(Error::Io)?;
+		}
+		std::fs::write(path, raw).map_err(Error::Io)
+	}
+}
@@ -0,0 +1,208 @@
+//! Sends pointer and keyboard events to the desktop.
+//!
+//! [`Output`] is implemented by several backends:
+//! - [`XTest`] injects events into an X11 server using the XTEST extension.
+//! - [`Uinput`] creates a virtual input device with the Linux uinput subsystem, which works without X11, for example under Wayland.
+//! - [`Recorder`] records events in memory or writes them out as JSON lines, which is useful for testing.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::gesture::Gesture;
+
+mod recording;
+mod uinput;
+mod xtest;
+
+pub use self::recording::{Event, Recorder};
+pub use self::uinput::Uinput;
+pub use self::xtest::XTest;
+
+/// A pointer button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Button {
+	/// The primary button.
+	Left,
+	/// The middle button, or a pressed scroll wheel.
+	Middle,
+	/// The secondary button.
+	Right,
+}
+
+/// A key, identified by its Linux evdev key code, as found in `linux/input-event-codes.h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Key(pub u16);
+
+/// Errors that can occur while sending events.
+#[derive(Debug)]
+pub enum Error {
+	/// An error from the XTEST backend.
+	XTest(xtest::Error),
+	/// An I/O error, from the uinput device or while writing recorded events.
+	Io(std::io::Error),
+	/// A recorded event could not be serialized.
+	Serialize(serde_json::Error),
+	/// The backend cannot send the key.
+	UnsupportedKey(Key),
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::XTest(error) => write!(formatter, "{error}"),
+			Self::Io(error) => write!(formatter, "I/O error: {error}"),
+			Self::Serialize(error) => write!(formatter, "could not serialize event: {error}"),
+			Self::UnsupportedKey(Key(code)) => {
+				write!(formatter, "key code {code} is not supported by this output")
+			}
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+impl From<xtest::Error> for Error {
+	fn from(error: xtest::Error) -> Self {
+		Self::XTest(error)
+	}
+}
+
+impl From<std::io::Error> for Error {
+	fn from(error: std::io::Error) -> Self {
+		Self::Io(error)
+	}
+}
+
+/// A destination for pointer and keyboard events.
+pub trait Output {
+	/// The size of the screen, in pixels.
+	fn screen_size(&self) -> (u16, u16);
+
+	/// Move the pointer to the given position on the screen.
+	///
+	/// # Errors
+	///
+	/// Fails if the backend fails to deliver the event.
+	fn move_to(&mut self, x: i16, y: i16) -> Result<(), Error>;
+
+	/// Press a pointer button.
+	///
+	/// # Errors
+	///
+	/// See [`Output::move_to`].
+	fn press(&mut self, button: Button) -> Result<(), Error>;
+
+	/// Release a pointer button.
+	///
+	/// # Errors
+	///
+	/// See [`Output::move_to`].
+	fn release(&mut self, button: Button) -> Result<(), Error>;
+
+	/// Scroll by the given number of steps.
+	///
+	/// Positive values scroll right and down.
+	///
+	/// # Errors
+	///
+	/// See [`Output::move_to`].
+	fn scroll(&mut self, horizontal: i32, vertical: i32) -> Result<(), Error>;
+
+	/// Press or release a key.
+	///
+	/// # Errors
+	///
+	/// See [`Output::move_to`].
+	fn key(&mut self, key: Key, pressed: bool) -> Result<(), Error>;
+}
+
+/// Send the button presses and releases for a recognized gesture, if any.
+///
+/// # Errors
+///
+/// See [`Output::move_to`].
+pub fn apply_gesture(
+	output: &mut dyn Output,
+	button: Button,
+	gesture: Option<Gesture>,
+) -> Result<(), Error> {
+	match gesture {
+		Some(Gesture::Click) => {
+			log::debug!("click");
+			output.press(button)?;
+			output.release(button)
+		}
+		Some(Gesture::DragStart) => {
+			log::debug!("drag start");
+			output.press(button)
+		}
+		Some(Gesture::DragEnd) => {
+			log::debug!("drag end");
+			output.release(button)
+		}
+		None => Ok(()),
+	}
+}
+
+/// Selects an [`Output`] backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputKind {
+	/// Use [`XTest`].
+	XTest,
+	/// Use [`Uinput`].
+	Uinput,
+	/// Use [`Recorder`], writing to the given file or to standard output if there is none.
+	Record(Option<PathBuf>),
+}
+
+/// Error returned when parsing an [`OutputKind`] from an unknown name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownOutput;
+
+impl std::fmt::Display for UnknownOutput {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		formatter.write_str("unknown output; expected `xtest`, `uinput`, `record`, or `record:<path>`")
+	}
+}
+
+impl std::error::Error for UnknownOutput {}
+
+impl std::str::FromStr for OutputKind {
+	type Err = UnknownOutput;
+
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		Ok(match name {
+			"xtest" => Self::XTest,
+			"uinput" => Self::Uinput,
+			"record" => Self::Record(None),
+			_ => match name.strip_prefix("record:") {
+				Some(path) => Self::Record(Some(path.into())),
+				None => return Err(UnknownOutput),
+			},
+		})
+	}
+}
+
+impl OutputKind {
+	/// Open the backend.
+	///
+	/// Backends that cannot query the screen size themselves use `screen_size` instead.
+	///
+	/// # Errors
+	///
+	/// Fails if the backend cannot be opened.
+	pub fn open(&self, screen_size: (u16, u16)) -> Result<Box<dyn Output>, Error> {
+		Ok(match self {
+			Self::XTest => Box::new(XTest::connect()?),
+			Self::Uinput => Box::new(Uinput::new(screen_size)?),
+			Self::Record(None) => Box::new(Recorder::to_writer(screen_size, std::io::stdout())),
+			Self::Record(Some(path)) => Box::new(Recorder::to_writer(
+				screen_size,
+				std::io::BufWriter::new(std::fs::File::create(path)?),
+			)),
+		})
+	}
+}
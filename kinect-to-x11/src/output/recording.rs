@@ -0,0 +1,106 @@
+//! Records events instead of sending them anywhere.
+
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Button, Error, Key, Output};
+
+/// An event sent to an [`Output`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+	/// See [`Output::move_to`].
+	Move { x: i16, y: i16 },
+	/// See [`Output::press`].
+	Press { button: Button },
+	/// See [`Output::release`].
+	Release { button: Button },
+	/// See [`Output::scroll`].
+	Scroll { horizontal: i32, vertical: i32 },
+	/// See [`Output::key`].
+	Key { key: Key, pressed: bool },
+}
+
+/// An [`Output`] that records events, either in memory or by writing each as a line of JSON.
+pub struct Recorder {
+	screen_size: (u16, u16),
+	sink: Sink,
+}
+
+enum Sink {
+	Memory(Vec<Event>),
+	Writer(Box<dyn Write>),
+}
+
+impl Recorder {
+	/// Record events in memory, to be retrieved with [`events`](Self::events).
+	#[must_use]
+	pub fn in_memory(screen_size: (u16, u16)) -> Self {
+		Self {
+			screen_size,
+			sink: Sink::Memory(Vec::new()),
+		}
+	}
+
+	/// Write events to `writer` as JSON lines, without keeping them in memory.
+	#[must_use]
+	pub fn to_writer(screen_size: (u16, u16), writer: impl Write + 'static) -> Self {
+		Self {
+			screen_size,
+			sink: Sink::Writer(Box::new(writer)),
+		}
+	}
+
+	/// All events recorded so far, oldest first.
+	///
+	/// This is always empty for a recorder created with [`to_writer`](Self::to_writer).
+	#[must_use]
+	pub fn events(&self) -> &[Event] {
+		match &self.sink {
+			Sink::Memory(events) => events,
+			Sink::Writer(_) => &[],
+		}
+	}
+
+	fn record(&mut self, event: Event) -> Result<(), Error> {
+		match &mut self.sink {
+			Sink::Memory(events) => events.push(event),
+			Sink::Writer(writer) => {
+				serde_json::to_writer(&mut *writer, &event).map_err(Error::Serialize)?;
+				writer.write_all(b"\n")?;
+				writer.flush()?;
+			}
+		}
+		Ok(())
+	}
+}
+
+impl Output for Recorder {
+	fn screen_size(&self) -> (u16, u16) {
+		self.screen_size
+	}
+
+	fn move_to(&mut self, x: i16, y: i16) -> Result<(), Error> {
+		self.record(Event::Move { x, y })
+	}
+
+	fn press(&mut self, button: Button) -> Result<(), Error> {
+		self.record(Event::Press { button })
+	}
+
+	fn release(&mut self, button: Button) -> Result<(), Error> {
+		self.record(Event::Release { button })
+	}
+
+	fn scroll(&mut self, horizontal: i32, vertical: i32) -> Result<(), Error> {
+		self.record(Event::Scroll {
+			horizontal,
+			vertical,
+		})
+	}
+
+	fn key(&mut self, key: Key, pressed: bool) -> Result<(), Error> {
+		self.record(Event::Key { key, pressed })
+	}
+}
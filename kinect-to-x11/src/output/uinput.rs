@@ -0,0 +1,138 @@
+//! Creates a virtual input device using the Linux uinput subsystem.
+//!
+//! This works regardless of the display server, as long as the user has write access to `/dev/uinput`.
+
+use evdev::uinput::{VirtualDevice, VirtualDeviceBuilder};
+use evdev::{
+	AbsInfo, AbsoluteAxisType, AttributeSet, EventType, InputEvent, RelativeAxisType, UinputAbsSetup,
+};
+
+use super::{Button, Error, Key, Output};
+
+/// The highest key code that the virtual device supports, not including buttons.
+///
+/// Higher codes are mostly buttons of other kinds of devices, which would make the display server treat the device as one of those.
+const MAX_KEY: u16 = 0xff;
+
+/// A virtual absolute pointer and keyboard.
+pub struct Uinput {
+	device: VirtualDevice,
+	width: u16,
+	height: u16,
+}
+
+impl Uinput {
+	/// Create the virtual device.
+	///
+	/// The absolute axes of the device span `screen_size`; the display server scales them to the actual screen.
+	///
+	/// # Errors
+	///
+	/// Fails if `/dev/uinput` cannot be opened, usually because of insufficient permissions.
+	pub fn new(screen_size: (u16, u16)) -> Result<Self, Error> {
+		let (width, height) = screen_size;
+
+		let mut keys = AttributeSet::<evdev::Key>::new();
+		for code in 1..=MAX_KEY {
+			keys.insert(evdev::Key::new(code));
+		}
+		for button in [Button::Left, Button::Middle, Button::Right] {
+			keys.insert(button_key(button));
+		}
+
+		let mut relative_axes = AttributeSet::<RelativeAxisType>::new();
+		relative_axes.insert(RelativeAxisType::REL_WHEEL);
+		relative_axes.insert(RelativeAxisType::REL_HWHEEL);
+
+		let axis = |axis, size: u16| {
+			UinputAbsSetup::new(
+				axis,
+				AbsInfo::new(0, 0, i32::from(size.saturating_sub(1)), 0, 0, 0),
+			)
+		};
+
+		let device = VirtualDeviceBuilder::new()?
+			.name("kinect-to-x11")
+			.with_keys(&keys)?
+			.with_relative_axes(&relative_axes)?
+			.with_absolute_axis(&axis(AbsoluteAxisType::ABS_X, width))?
+			.with_absolute_axis(&axis(AbsoluteAxisType::ABS_Y, height))?
+			.build()?;
+
+		Ok(Self {
+			device,
+			width,
+			height,
+		})
+	}
+}
+
+fn button_key(button: Button) -> evdev::Key {
+	match button {
+		Button::Left => evdev::Key::BTN_LEFT,
+		Button::Middle => evdev::Key::BTN_MIDDLE,
+		Button::Right => evdev::Key::BTN_RIGHT,
+	}
+}
+
+impl Output for Uinput {
+	fn screen_size(&self) -> (u16, u16) {
+		(self.width, self.height)
+	}
+
+	fn move_to(&mut self, x: i16, y: i16) -> Result<(), Error> {
+		self.device.emit(&[
+			InputEvent::new(EventType::ABSOLUTE, AbsoluteAxisType::ABS_X.0, x.into()),
+			InputEvent::new(EventType::ABSOLUTE, AbsoluteAxisType::ABS_Y.0, y.into()),
+		])?;
+		Ok(())
+	}
+
+	fn press(&mut self, button: Button) -> Result<(), Error> {
+		self.device.emit(&[InputEvent::new(
+			EventType::KEY,
+			button_key(button).code(),
+			1,
+		)])?;
+		Ok(())
+	}
+
+	fn release(&mut self, button: Button) -> Result<(), Error> {
+		self.device.emit(&[InputEvent::new(
+			EventType::KEY,
+			button_key(button).code(),
+			0,
+		)])?;
+		Ok(())
+	}
+
+	fn scroll(&mut self, horizontal: i32, vertical: i32) -> Result<(), Error> {
+		// evdev wheel values are positive when scrolling up
+		self.device.emit(&[
+			InputEvent::new(
+				EventType::RELATIVE,
+				RelativeAxisType::REL_HWHEEL.0,
+				horizontal,
+			),
+			InputEvent::new(
+				EventType::RELATIVE,
+				RelativeAxisType::REL_WHEEL.0,
+				vertical.saturating_neg(),
+			),
+		])?;
+		Ok(())
+	}
+
+	/// Press or release a key.
+	///
+	/// Only key codes up to 255 are supported; others fail with [`Error::UnsupportedKey`].
+	fn key(&mut self, key: Key, pressed: bool) -> Result<(), Error> {
+		if !(1..=MAX_KEY).contains(&key.0) {
+			return Err(Error::UnsupportedKey(key));
+		}
+		self
+			.device
+			.emit(&[InputEvent::new(EventType::KEY, key.0, pressed.into())])?;
+		Ok(())
+	}
+}
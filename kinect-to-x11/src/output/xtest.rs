@@ -0,0 +1,164 @@
+//! Injects events into the X server using the XTEST extension.
+
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::errors::{ConnectError, ConnectionError, ReplyError};
+use x11rb::protocol::xproto::{
+	Window, BUTTON_PRESS_EVENT, BUTTON_RELEASE_EVENT, KEY_PRESS_EVENT, KEY_RELEASE_EVENT,
+	MOTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::xtest::{self, ConnectionExt as _};
+use x11rb::rust_connection::RustConnection;
+
+use super::{Button, Key, Output};
+
+/// X11 keycodes are offset from evdev key codes by this amount.
+const EVDEV_KEYCODE_OFFSET: u16 = 8;
+
+/// Errors that can occur while controlling the pointer.
+#[derive(Debug)]
+pub enum Error {
+	/// Could not connect to the X server.
+	Connect(ConnectError),
+	/// The connection to the X server failed.
+	Connection(ConnectionError),
+	/// The X server returned an error in response to a request.
+	Reply(ReplyError),
+	/// The X server does not support the XTEST extension.
+	MissingXTest,
+	/// The key cannot be represented as an X11 keycode.
+	InvalidKey(Key),
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Connect(error) => write!(formatter, "could not connect to the X server: {error}"),
+			Self::Connection(error) => write!(formatter, "X server connection failed: {error}"),
+			Self::Reply(error) => write!(formatter, "X server returned an error: {error}"),
+			Self::MissingXTest => {
+				formatter.write_str("the X server does not support the XTEST extension")
+			}
+			Self::InvalidKey(Key(code)) => write!(formatter, "key code {code} has no X11 keycode"),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+impl From<ConnectError> for Error {
+	fn from(error: ConnectError) -> Self {
+		Self::Connect(error)
+	}
+}
+
+impl From<ConnectionError> for Error {
+	fn from(error: ConnectionError) -> Self {
+		Self::Connection(error)
+	}
+}
+
+impl From<ReplyError> for Error {
+	fn from(error: ReplyError) -> Self {
+		Self::Reply(error)
+	}
+}
+
+/// A connection to the X server that controls the pointer and keyboard on the default screen.
+pub struct XTest {
+	connection: RustConnection,
+	root: Window,
+	width: u16,
+	height: u16,
+}
+
+impl XTest {
+	/// Connect to the display named by `$DISPLAY`.
+	///
+	/// # Errors
+	///
+	/// Fails if the connection fails or if the X server does not support XTEST.
+	pub fn connect() -> Result<Self, Error> {
+		let (connection, screen_num) = x11rb::connect(None)?;
+		if connection
+			.extension_information(xtest::X11_EXTENSION_NAME)?
+			.is_none()
+		{
+			return Err(Error::MissingXTest);
+		}
+
+		let screen = &connection.setup().roots[screen_num];
+		let (root, width, height) = (screen.root, screen.width_in_pixels, screen.height_in_pixels);
+
+		Ok(Self {
+			connection,
+			root,
+			width,
+			height,
+		})
+	}
+
+	fn fake_input(&self, event: u8, detail: u8, x: i16, y: i16) -> Result<(), Error> {
+		self
+			.connection
+			.xtest_fake_input(event, detail, x11rb::CURRENT_TIME, self.root, x, y, 0)?
+			.check()?;
+		Ok(())
+	}
+
+	fn click(&self, button: u8, count: u32) -> Result<(), Error> {
+		for _ in 0..count {
+			self.fake_input(BUTTON_PRESS_EVENT, button, 0, 0)?;
+			self.fake_input(BUTTON_RELEASE_EVENT, button, 0, 0)?;
+		}
+		Ok(())
+	}
+}
+
+fn button_number(button: Button) -> u8 {
+	match button {
+		Button::Left => 1,
+		Button::Middle => 2,
+		Button::Right => 3,
+	}
+}
+
+impl Output for XTest {
+	fn screen_size(&self) -> (u16, u16) {
+		(self.width, self.height)
+	}
+
+	fn move_to(&mut self, x: i16, y: i16) -> Result<(), super::Error> {
+		Ok(self.fake_input(MOTION_NOTIFY_EVENT, 0, x, y)?)
+	}
+
+	fn press(&mut self, button: Button) -> Result<(), super::Error> {
+		Ok(self.fake_input(BUTTON_PRESS_EVENT, button_number(button), 0, 0)?)
+	}
+
+	fn release(&mut self, button: Button) -> Result<(), super::Error> {
+		Ok(self.fake_input(BUTTON_RELEASE_EVENT, button_number(button), 0, 0)?)
+	}
+
+	fn scroll(&mut self, horizontal: i32, vertical: i32) -> Result<(), super::Error> {
+		// X11 represents scrolling as clicks of buttons 4 (up), 5 (down), 6 (left), and 7 (right)
+		let vertical_button = if vertical < 0 { 4 } else { 5 };
+		let horizontal_button = if horizontal < 0 { 6 } else { 7 };
+		self.click(vertical_button, vertical.unsigned_abs())?;
+		self.click(horizontal_button, horizontal.unsigned_abs())?;
+		Ok(())
+	}
+
+	fn key(&mut self, key: Key, pressed: bool) -> Result<(), super::Error> {
+		let keycode = key
+			.0
+			.checked_add(EVDEV_KEYCODE_OFFSET)
+			.and_then(|keycode| u8::try_from(keycode).ok())
+			.ok_or(Error::InvalidKey(key))?;
+		let event = if pressed {
+			KEY_PRESS_EVENT
+		} else {
+			KEY_RELEASE_EVENT
+		};
+		Ok(self.fake_input(event, keycode, 0, 0)?)
+	}
+}
@@ -0,0 +1,121 @@
+//! Saves a single color frame and a single depth frame as images.
+
+use std::path::PathBuf;
+
+use bytemuck::zeroed_box;
+use freenect2::frame_set::{FrameSync, SyncConfig, Synced};
+use freenect2::{Device, FrameFormat};
+
+use crate::config::Colormap;
+use crate::transformer::Transformer;
+
+/// Where to save the images.
+#[derive(Debug, Clone)]
+pub struct Paths {
+	/// The color image.
+	pub color: PathBuf,
+	/// The depth image, mapped onto the color image.
+	pub depth: PathBuf,
+	/// The raw depth image.
+	pub depth_distorted: PathBuf,
+}
+
+/// Color a depth value, in millimeters, on a scale from red at `colormap.near` to blue at `colormap.far`.
+fn depth_color(colormap: Colormap, depth: f32) -> image::Rgb<u8> {
+	let proportion = ((depth - colormap.near) / (colormap.far - colormap.near)).clamp(0.0, 1.0);
+	let coolor::Rgb { r, g, b } = coolor::Hsl {
+		h: proportion * 240.0,
+		s: 1.0,
+		l: 0.5,
+	}
+	.to_rgb();
+	image::Rgb([r, g, b])
+}
+
+/// Capture a frame from each of the selected streams and save them as images, coloring depth images using `colormap`.
+///
+/// The color and depth frames are taken from the same instant.
+///
+/// # Panics
+///
+/// Panics if neither stream is selected, if the device cannot be started, or if the images cannot be saved.
+pub fn run(mut device: Device, color: bool, depth: bool, paths: &Paths, colormap: Colormap) {
+	let (sender, recv) = std::sync::mpsc::sync_channel(4);
+
+	log::debug!("setting frame set listener");
+	let sync = FrameSync::new(SyncConfig {
+		color,
+		ir: false,
+		depth,
+		..SyncConfig::default()
+	});
+	device.set_frame_set_listener(sync, move |synced| match synced {
+		Synced::Complete(frames) => {
+			log::debug!("frame set listener got a complete set");
+			let _ = sender.try_send(frames);
+		}
+		Synced::Partial(frames) => {
+			log::debug!("discarding {} unmatched frames", frames.frames().count());
+		}
+	});
+
+	log::info!("starting device");
+	device.start_streams(color, depth).unwrap();
+
+	let transformer = Transformer::for_device(&device);
+
+	log::debug!("waiting for a frame set");
+	let frames = recv.recv().unwrap();
+	log::info!("got a frame set");
+
+	log::info!("stopping device");
+	device.stop().unwrap();
+
+	let color_thread = frames.color.map(|mut frame| {
+		if frame.as_color().unwrap().format() == FrameFormat::Bgrx {
+			for rgbx in frame.data_mut().chunks_exact_mut(4) {
+				rgbx.swap(0, 2);
+			}
+		}
+
+		let path = paths.color.clone();
+		std::thread::spawn(move || {
+			let mut image =
+				image::ImageBuffer::<image::Rgba<u8>, _>::from_raw(1920, 1080, frame.data_mut()).unwrap();
+			image::imageops::flip_vertical_in_place(&mut image);
+			image.save(path).unwrap();
+		})
+	});
+
+	let depth_threads = frames.depth.map(|frame| {
+		let mut depth_frame = zeroed_box::<[f32; 1920 * 1080]>();
+		transformer.depth_to_color(frame.as_depth().unwrap().pixels(), &mut *depth_frame);
+
+		let distorted_path = paths.depth_distorted.clone();
+		let path = paths.depth.clone();
+		(
+			std::thread::spawn(move || {
+				let raw_depth = frame.as_depth().unwrap();
+				let image = image::ImageBuffer::from_fn(512, 424, |x, y| {
+					depth_color(colormap, raw_depth.get(az::cast(x), az::cast(y)).unwrap())
+				});
+				image.save(distorted_path).unwrap();
+			}),
+			std::thread::spawn(move || {
+				let image = image::ImageBuffer::from_fn(1920, 1080, |x, y| {
+					depth_color(colormap, depth_frame[az::cast::<_, usize>(y * 1920 + x)])
+				});
+				image.save(path).unwrap();
+			}),
+		)
+	});
+
+	log::info!("waiting for threads");
+	if let Some(color_thread) = color_thread {
+		color_thread.join().unwrap();
+	}
+	if let Some(depth_threads) = depth_threads {
+		depth_threads.0.join().unwrap();
+		depth_threads.1.join().unwrap();
+	}
+}
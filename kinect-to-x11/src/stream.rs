@@ -0,0 +1,69 @@
+//! Receives depth frames from a device.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::time::Duration;
+
+use freenect2::device::{Frames, QueueConfig};
+use freenect2::{device, Device, Frame, FrameType};
+
+/// An iterator over depth frames that ends when `running` is cleared or the device stops sending frames.
+///
+/// Only the most recent frames are kept, so that processing that falls behind catches up instead of adding latency.
+/// Frames that cannot be viewed as depth images, such as the raw frames of the dump pipeline, are logged and skipped.
+pub struct DepthFrames {
+	frames: Frames,
+	dropped: u64,
+	running: Arc<AtomicBool>,
+}
+
+impl DepthFrames {
+	/// Set the frame listener of `device` and start its depth stream.
+	///
+	/// # Errors
+	///
+	/// Fails if the depth stream cannot be started.
+	pub fn start(device: &mut Device, running: Arc<AtomicBool>) -> Result<Self, device::Error> {
+		log::debug!("setting frame listener");
+		let frames = device.frames(QueueConfig::default());
+
+		log::info!("starting device");
+		device.start_streams(false, true)?;
+
+		Ok(Self {
+			frames,
+			dropped: 0,
+			running,
+		})
+	}
+}
+
+impl Iterator for DepthFrames {
+	type Item = Frame;
+
+	fn next(&mut self) -> Option<Frame> {
+		while self.running.load(Ordering::SeqCst) {
+			match self.frames.recv_timeout(Duration::from_millis(100)) {
+				Ok((frame, FrameType::Depth)) => {
+					let dropped = self.frames.dropped();
+					if dropped > self.dropped {
+						log::debug!(
+							"processing is falling behind; dropped {} frames",
+							dropped - self.dropped
+						);
+						self.dropped = dropped;
+					}
+
+					match frame.as_depth() {
+						Ok(_) => return Some(frame),
+						Err(error) => log::warn!("skipping invalid depth frame: {error}"),
+					}
+				}
+				Ok(_) | Err(RecvTimeoutError::Timeout) => (),
+				Err(RecvTimeoutError::Disconnected) => break,
+			}
+		}
+		None
+	}
+}
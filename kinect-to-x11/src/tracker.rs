@@ -0,0 +1,168 @@
+//! Finds the user's hand in a depth frame.
+//!
+//! The hand is assumed to be the closest object to the sensor, so the tracker segments the closest connected blob of pixels within a depth band.
+
+use std::collections::VecDeque;
+
+use glam::{DVec2, UVec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{DEPTH_HEIGHT, DEPTH_WIDTH};
+
+/// A raw depth frame as received from the device, in millimeters.
+pub type DepthFrame = [f32; DEPTH_WIDTH * DEPTH_HEIGHT];
+
+/// Tunable parameters for [`HandTracker`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrackerConfig {
+	/// Ignore everything closer than this, in millimeters.
+	pub min_depth: f32,
+	/// Ignore everything farther than this, in millimeters.
+	pub max_depth: f32,
+	/// How far behind the closest point of a blob the rest of the blob may extend, in millimeters.
+	///
+	/// This stops the blob from growing from the hand into the arm and body.
+	pub hand_depth: f32,
+	/// Blobs with fewer pixels than this are considered noise and skipped.
+	pub min_area: u32,
+}
+
+impl Default for TrackerConfig {
+	fn default() -> Self {
+		Self {
+			min_depth: 500.0,
+			max_depth: 1500.0,
+			hand_depth: 100.0,
+			min_area: 200,
+		}
+	}
+}
+
+/// The inclusive bounds of a blob, in depth image pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundingBox {
+	/// The top-left corner.
+	pub min: UVec2,
+	/// The bottom-right corner.
+	pub max: UVec2,
+}
+
+/// A hand found in a depth frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hand {
+	/// The mean position of the blob's pixels, in depth image pixels.
+	pub centroid: Vec2,
+	/// The number of pixels in the blob.
+	pub area: u32,
+	/// The bounds of the blob.
+	pub bounding_box: BoundingBox,
+	/// The mean depth of the blob's pixels, in millimeters.
+	pub mean_depth: f32,
+}
+
+/// Segments the closest blob in each depth frame.
+#[derive(Debug)]
+pub struct HandTracker {
+	config: TrackerConfig,
+	visited: Box<[bool]>,
+	queue: VecDeque<usize>,
+	candidates: Vec<usize>,
+}
+
+impl HandTracker {
+	/// Create a tracker with the given parameters.
+	#[must_use]
+	pub fn new(config: TrackerConfig) -> Self {
+		Self {
+			config,
+			visited: vec![false; DEPTH_WIDTH * DEPTH_HEIGHT].into_boxed_slice(),
+			queue: VecDeque::new(),
+			candidates: Vec::new(),
+		}
+	}
+
+	/// The configuration of the tracker.
+	#[must_use]
+	pub fn config(&self) -> TrackerConfig {
+		self.config
+	}
+
+	/// Change the parameters used for subsequent frames.
+	pub fn set_config(&mut self, config: TrackerConfig) {
+		self.config = config;
+	}
+
+	fn in_band(&self, depth: f32) -> bool {
+		(self.config.min_depth..=self.config.max_depth).contains(&depth)
+	}
+
+	/// Find the closest blob in `depth` that is large enough to be a hand.
+	///
+	/// Returns `None` if there is no such blob.
+	pub fn track(&mut self, depth: &DepthFrame) -> Option<Hand> {
+		self.visited.fill(false);
+
+		let mut candidates = std::mem::take(&mut self.candidates);
+		candidates.clear();
+		candidates.extend((0..depth.len()).filter(|&index| self.in_band(depth[index])));
+		candidates.sort_unstable_by(|&a, &b| depth[a].total_cmp(&depth[b]));
+
+		let hand = candidates.iter().find_map(|&seed| {
+			if self.visited[seed] {
+				return None;
+			}
+			Some(self.flood_fill(depth, seed)).filter(|hand| hand.area >= self.config.min_area)
+		});
+
+		self.candidates = candidates;
+		hand
+	}
+
+	fn flood_fill(&mut self, depth: &DepthFrame, seed: usize) -> Hand {
+		let limit = depth[seed] + self.config.hand_depth;
+
+		let mut area = 0u32;
+		let mut position_sum = DVec2::ZERO;
+		let mut depth_sum = 0.0f64;
+		let mut min = UVec2::new(u32::MAX, u32::MAX);
+		let mut max = UVec2::ZERO;
+
+		self.queue.clear();
+		self.queue.push_back(seed);
+		self.visited[seed] = true;
+
+		while let Some(index) = self.queue.pop_front() {
+			let x = index % DEPTH_WIDTH;
+			let y = index / DEPTH_WIDTH;
+			let position = UVec2::new(az::cast(x), az::cast(y));
+
+			area += 1;
+			position_sum += position.as_dvec2();
+			depth_sum += f64::from(depth[index]);
+			min = min.min(position);
+			max = max.max(position);
+
+			let neighbors = [
+				(x > 0).then(|| index - 1),
+				(x + 1 < DEPTH_WIDTH).then(|| index + 1),
+				(y > 0).then(|| index - DEPTH_WIDTH),
+				(y + 1 < DEPTH_HEIGHT).then(|| index + DEPTH_WIDTH),
+			];
+			for neighbor in neighbors.into_iter().flatten() {
+				if !self.visited[neighbor] && self.in_band(depth[neighbor]) && depth[neighbor] <= limit {
+					self.visited[neighbor] = true;
+					self.queue.push_back(neighbor);
+				}
+			}
+		}
+
+		#[allow(clippy::cast_possible_truncation)]
+		Hand {
+			centroid: (position_sum / f64::from(area)).as_vec2(),
+			area,
+			bounding_box: BoundingBox { min, max },
+			mean_depth: (depth_sum / f64::from(area)) as f32,
+		}
+	}
+}
@@ -1,4 +1,5 @@
 use bytemuck::zeroed_box;
+use freenect2::calibration::Calibration;
 use freenect2::device::{ColorCameraParams, Device, IrCameraParams};
 use glam::{IVec2, Vec2};
 
@@ -109,6 +110,7 @@ pub struct Transformer {
 }
 
 impl Transformer {
+	#[must_use]
 	pub fn new(ir_params: IrCameraParams, color_params: ColorCameraParams) -> Self {
 		let mut depth_to_color_map: DepthBox<MapEntry> = zeroed_box();
 
@@ -149,10 +151,20 @@ impl Transformer {
 		}
 	}
 
+	#[must_use]
 	pub fn for_device(device: &Device) -> Self {
 		Self::new(device.ir_camera_params(), device.color_camera_params())
 	}
 
+	/// Build a transformer from a saved calibration, without a device.
+	#[must_use]
+	pub fn for_calibration(calibration: &Calibration) -> Self {
+		Self::new(
+			calibration.ir_camera_params,
+			calibration.color_camera_params,
+		)
+	}
+
 	/// `raw_depth` is a 512x424 raw depth frame.
 	/// `output` is a 1920x1080 buffer for the undistorted, scaled depth frame.
 	pub fn depth_to_color(&self, raw_depth: &[f32], output: &mut [f32]) {
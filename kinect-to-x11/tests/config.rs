@@ -0,0 +1,161 @@
+//! Tests of parsing, validating and watching configuration files.
+
+use std::time::{Duration, Instant};
+
+use kinect_to_x11::config::{Config, ConfigWatcher, Error};
+use kinect_to_x11::filter::{FilterConfig, KalmanConfig};
+
+#[test]
+fn parses_defaults() {
+	assert_eq!(Config::parse("").unwrap(), Config::default());
+	Config::default().validate().unwrap();
+
+	// the defaults round-trip through TOML
+	let raw = toml::to_string(&Config::default()).unwrap();
+	assert_eq!(Config::parse(&raw).unwrap(), Config::default());
+}
+
+#[test]
+fn parses_partial_sections() {
+	let config = Config::parse(
+		r#"
+			[device]
+			max_depth = 2.0
+
+			[tracker]
+			max_depth = 1200.0
+
+			[filter]
+			kind = "kalman"
+			measurement_noise = 50.0
+
+			[click]
+			push_distance = 80.0
+
+			[mapping]
+			screen_size = [2560, 1440]
+		"#,
+	)
+	.unwrap();
+
+	let default = Config::default();
+	assert_eq!(config.device.max_depth, 2.0);
+	assert_eq!(config.device.min_depth, default.device.min_depth);
+	assert_eq!(config.tracker.max_depth, 1200.0);
+	assert_eq!(config.tracker.min_area, default.tracker.min_area);
+	assert_eq!(
+		config.filter,
+		FilterConfig::Kalman(KalmanConfig {
+			measurement_noise: 50.0,
+			..KalmanConfig::default()
+		})
+	);
+	assert_eq!(config.click.push_distance, 80.0);
+	assert_eq!(config.click.debounce, default.click.debounce);
+	assert_eq!(config.mapping.screen_size, [2560, 1440]);
+	assert_eq!(config.mapping.calibration, None);
+	assert_eq!(config.colormap, default.colormap);
+}
+
+#[test]
+fn rejects_malformed_files() {
+	for raw in [
+		"[device",
+		"[devices]\nmax_depth = 2.0",
+		"[device]\nmaximum_depth = 2.0",
+		"[device]\nmax_depth = \"far\"",
+		"[filter]\nkind = \"median\"",
+		"[filter]\nkind = \"kalman\"\nbeta = 0.1",
+		"[mapping]\nscreen_size = [1920]",
+	] {
+		assert!(
+			matches!(Config::parse(raw), Err(Error::Parse(_))),
+			"{raw:?} was accepted"
+		);
+	}
+}
+
+#[test]
+fn rejects_out_of_range_values() {
+	for (raw, reason) in [
+		(
+			"[device]\nmin_depth = -0.1",
+			"`device.min_depth` must not be negative",
+		),
+		(
+			"[device]\nmin_depth = 3.0\nmax_depth = 2.0",
+			"`device.min_depth` must be less than `device.max_depth`",
+		),
+		(
+			"[tracker]\nmin_depth = 1000.0\nmax_depth = 1000.0",
+			"`tracker.min_depth` must be less than `tracker.max_depth`",
+		),
+		(
+			"[tracker]\nhand_depth = 0.0",
+			"`tracker.hand_depth` must be positive",
+		),
+		(
+			"[tracker]\nmin_area = 0",
+			"`tracker.min_area` must be positive",
+		),
+		(
+			"[filter]\nkind = \"one-euro\"\nmin_cutoff = nan",
+			"`filter.min_cutoff` must be positive",
+		),
+		(
+			"[filter]\nkind = \"one-euro\"\nbeta = -1.0",
+			"`filter.beta` must not be negative",
+		),
+		(
+			"[filter]\nkind = \"kalman\"\nprocess_noise = 0.0",
+			"`filter.process_noise` must be positive",
+		),
+		(
+			"[click]\npush_duration = 0",
+			"`click.push_duration` must be positive",
+		),
+		(
+			"[click]\nrelease_distance = -5.0",
+			"`click.release_distance` must be positive",
+		),
+		(
+			"[mapping]\nscreen_size = [0, 1080]",
+			"`mapping.screen_size` must not be empty",
+		),
+		(
+			"[colormap]\nnear = 4000.0\nfar = nan",
+			"`colormap.near` must be less than `colormap.far`",
+		),
+	] {
+		match Config::parse(raw) {
+			Err(Error::Invalid(actual)) => assert_eq!(actual, reason, "for {raw:?}"),
+			result => panic!("{raw:?} gave {result:?}"),
+		}
+	}
+}
+
+#[test]
+fn watches_missing_directory() {
+	let directory = std::env::temp_dir().join(format!("kinect-to-x11-config-{}", std::process::id()));
+	let path = directory.join("nested").join("config.toml");
+	assert!(!directory.exists());
+
+	let mut watcher = ConfigWatcher::new(&path).unwrap();
+	assert!(watcher.poll().is_none());
+
+	// other files in the directory are ignored
+	std::fs::write(path.with_file_name("other.toml"), "").unwrap();
+	std::fs::write(&path, "[click]\npush_distance = 80.0\n").unwrap();
+	let deadline = Instant::now() + Duration::from_secs(5);
+	let config = loop {
+		if let Some(result) = watcher.poll() {
+			break result.unwrap();
+		}
+		assert!(Instant::now() < deadline, "the change was not noticed");
+		std::thread::sleep(Duration::from_millis(10));
+	};
+	assert_eq!(config.click.push_distance, 80.0);
+	assert!(watcher.poll().is_none());
+
+	std::fs::remove_dir_all(&directory).unwrap();
+}
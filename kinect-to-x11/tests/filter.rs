@@ -0,0 +1,126 @@
+//! Tests of smoothing fixed sequences of positions.
+
+use glam::Vec2;
+use kinect_to_x11::filter::{
+	Filter, FilterConfig, KalmanConfig, KalmanFilter, OneEuroConfig, OneEuroFilter,
+};
+
+/// The time between frames at 30 frames per second, in units of 100 microseconds.
+const FRAME: u32 = 333;
+
+/// Run `filter` over `positions`, one per frame, starting at `start`.
+fn run(filter: &mut dyn Filter, start: u32, positions: &[Vec2]) -> Vec<Vec2> {
+	(0..)
+		.map(|index: u32| start.wrapping_add(index * FRAME))
+		.zip(positions)
+		.map(|(timestamp, &position)| filter.filter(timestamp, position))
+		.collect()
+}
+
+/// A hand moving around a circle, with some jitter that is the same every time.
+fn jittery_circle() -> Vec<Vec2> {
+	(0..90_u16)
+		.map(|index| {
+			let angle = f32::from(index) * 0.1;
+			let jitter = (f32::from(index) * 12.9898).sin() * 5.0;
+			Vec2::new(
+				500.0 + 200.0 * angle.cos() + jitter,
+				400.0 + 200.0 * angle.sin() - jitter,
+			)
+		})
+		.collect()
+}
+
+fn filters() -> [FilterConfig; 2] {
+	[
+		FilterConfig::OneEuro(OneEuroConfig::default()),
+		FilterConfig::Kalman(KalmanConfig::default()),
+	]
+}
+
+#[test]
+fn is_deterministic() {
+	let positions = jittery_circle();
+	for config in filters() {
+		let mut filter = config.build();
+		let first = run(&mut *filter, 1_000, &positions);
+		filter.reset();
+		let replayed = run(&mut *filter, 1_000, &positions);
+		assert_eq!(first, replayed);
+		// only the intervals between timestamps matter, even across wrap-around
+		let wrapped = run(&mut *config.build(), u32::MAX - 10 * FRAME, &positions);
+		assert_eq!(first, wrapped);
+		assert_eq!(first[0], positions[0]);
+	}
+}
+
+#[test]
+fn smooths_jitter() {
+	let positions: Vec<_> = (0..60_u16)
+		.map(|index| Vec2::splat(100.0 + if index % 2 == 0 { 5.0 } else { -5.0 }))
+		.collect();
+	for config in filters() {
+		let smoothed = run(&mut *config.build(), 0, &positions);
+		for position in &smoothed[30..] {
+			assert!(
+				position.abs_diff_eq(Vec2::splat(100.0), 2.0),
+				"{config:?} gave {position}"
+			);
+		}
+	}
+}
+
+#[test]
+fn one_euro_settles_after_step() {
+	let mut positions = vec![Vec2::ZERO; 10];
+	positions.resize(100, Vec2::new(100.0, 0.0));
+	let smoothed = run(
+		&mut OneEuroFilter::new(OneEuroConfig::default()),
+		0,
+		&positions,
+	);
+
+	// the filter lags behind the step without overshooting, then settles
+	for pair in smoothed[10..].windows(2) {
+		assert!(pair[0].x <= pair[1].x && pair[1].x <= 100.0);
+	}
+	assert!(smoothed[10].x < 100.0);
+	assert!(smoothed[99].abs_diff_eq(Vec2::new(100.0, 0.0), 1.0));
+}
+
+#[test]
+fn kalman_tracks_constant_velocity() {
+	// 300 pixels per second to the right
+	let positions: Vec<_> = (0..60_u16)
+		.map(|index| Vec2::new(f32::from(index) * 10.0, 50.0))
+		.collect();
+	let smoothed = run(
+		&mut KalmanFilter::new(KalmanConfig::default()),
+		0,
+		&positions,
+	);
+	for (position, smoothed) in positions.iter().zip(&smoothed).skip(30) {
+		assert!(
+			smoothed.abs_diff_eq(*position, 1.0),
+			"{smoothed} lags {position}"
+		);
+	}
+}
+
+#[test]
+fn restarts_when_time_goes_backwards() {
+	for config in filters() {
+		let mut filter = config.build();
+		let smoothed = run(&mut *filter, 100_000, &jittery_circle());
+		let last = smoothed[smoothed.len() - 1];
+
+		// a repeated timestamp changes nothing
+		let timestamp = 100_000 + 89 * FRAME;
+		assert_eq!(filter.filter(timestamp, Vec2::ZERO), last);
+
+		// an earlier timestamp starts again from the new position
+		let position = Vec2::new(10.0, 20.0);
+		assert_eq!(filter.filter(timestamp - FRAME, position), position);
+		assert_eq!(filter.filter(50_000, position * 2.0), position * 2.0);
+	}
+}
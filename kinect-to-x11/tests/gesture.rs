@@ -0,0 +1,113 @@
+//! Tests of recognizing gestures from synthetic sequences of hand depths.
+
+use kinect_to_x11::gesture::{ClickConfig, ClickDetector, Gesture};
+
+/// The time between frames at 30 frames per second, in units of 100 microseconds.
+const FRAME: u32 = 333;
+
+/// Feed one depth per frame, starting at `start`, and return the gestures along with the indices of the frames that caused them.
+fn feed(detector: &mut ClickDetector, start: u32, depths: &[f32]) -> Vec<(usize, Gesture)> {
+	depths
+		.iter()
+		.enumerate()
+		.filter_map(|(index, &depth)| {
+			let timestamp = start.wrapping_add(FRAME * u32::try_from(index).unwrap());
+			Some((index, detector.update(timestamp, depth)?))
+		})
+		.collect()
+}
+
+/// A hand resting at 1000 mm, then pushed quickly to 930 mm.
+const PUSH: [f32; 9] = [
+	1000.0, 1000.0, 1000.0, 1000.0, 1000.0, 1000.0, 970.0, 940.0, 930.0,
+];
+
+#[test]
+fn clicks() {
+	let mut detector = ClickDetector::new(ClickConfig::default());
+	let mut depths = PUSH.to_vec();
+	// pulling back by less than the release distance is not enough
+	depths.extend([960.0, 975.0]);
+	assert_eq!(feed(&mut detector, 0, &depths), [(10, Gesture::Click)]);
+}
+
+#[test]
+fn drags() {
+	let config = ClickConfig::default();
+	let mut detector = ClickDetector::new(config);
+	let mut depths = PUSH.to_vec();
+	// the push is recognized at 940 mm, at index 7
+	let drag_start = 7 + config.drag_delay.div_ceil(FRAME);
+	depths.resize(usize::try_from(drag_start).unwrap() + 1, 930.0);
+	// jitter closer and back does not end the drag, but pulling back from the closest depth does
+	depths.extend([925.0, 950.0, 920.0, 955.0, 965.0]);
+	assert_eq!(
+		feed(&mut detector, 0, &depths),
+		[
+			(usize::try_from(drag_start).unwrap(), Gesture::DragStart),
+			(depths.len() - 1, Gesture::DragEnd),
+		]
+	);
+}
+
+#[test]
+fn ignores_slow_movement() {
+	let mut detector = ClickDetector::new(ClickConfig::default());
+	// 200 mm over two seconds is too slow to be a push
+	let depths: Vec<_> = (0..60_u16)
+		.map(|index| 1000.0 - f32::from(index) * 3.4)
+		.collect();
+	assert_eq!(feed(&mut detector, 0, &depths), []);
+}
+
+#[test]
+fn debounces() {
+	let config = ClickConfig::default();
+	let mut detector = ClickDetector::new(config);
+	let mut depths = PUSH.to_vec();
+	depths.push(975.0);
+	// pushing again straight after the click is ignored
+	depths.extend([975.0, 940.0, 910.0]);
+	// until the debounce time has passed
+	depths.extend([975.0; 6]);
+	let pushed = depths.len() + 1;
+	assert!(u32::try_from(pushed - 9).unwrap() * FRAME >= config.debounce);
+	depths.extend([940.0, 910.0, 975.0]);
+	assert_eq!(
+		feed(&mut detector, 0, &depths),
+		[(9, Gesture::Click), (pushed + 1, Gesture::Click)]
+	);
+}
+
+#[test]
+fn handles_wrapping_timestamps() {
+	let mut detector = ClickDetector::new(ClickConfig::default());
+	let mut depths = PUSH.to_vec();
+	depths.push(975.0);
+	assert_eq!(
+		feed(&mut detector, u32::MAX - 5 * FRAME, &depths),
+		[(9, Gesture::Click)]
+	);
+}
+
+#[test]
+fn releases_on_reset() {
+	let config = ClickConfig::default();
+	let mut detector = ClickDetector::new(config);
+	assert_eq!(feed(&mut detector, 0, &PUSH), []);
+	// a push that is not yet a drag does not press the button
+	assert_eq!(detector.reset(9 * FRAME), None);
+
+	let start = 9 * FRAME + config.debounce;
+	let mut depths = PUSH.to_vec();
+	depths.resize(
+		PUSH.len() + usize::try_from(config.drag_delay / FRAME).unwrap(),
+		930.0,
+	);
+	assert_eq!(
+		feed(&mut detector, start, &depths),
+		[(depths.len() - 1, Gesture::DragStart)]
+	);
+	assert_eq!(detector.reset(start + 100 * FRAME), Some(Gesture::DragEnd));
+	assert_eq!(detector.reset(start + 101 * FRAME), None);
+}
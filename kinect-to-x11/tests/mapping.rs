@@ -0,0 +1,114 @@
+//! Tests of mapping hand positions to the screen.
+
+use glam::Vec2;
+use kinect_to_x11::mapping::{Calibration, Error, Homography};
+
+fn assert_near(actual: Vec2, expected: Vec2) {
+	assert!(
+		actual.abs_diff_eq(expected, 1e-3),
+		"expected {expected}, got {actual}"
+	);
+}
+
+#[test]
+fn fits_point_pairs() {
+	let from = [
+		Vec2::new(100.0, 80.0),
+		Vec2::new(400.0, 90.0),
+		Vec2::new(420.0, 330.0),
+		Vec2::new(90.0, 340.0),
+	];
+	let to = [
+		Vec2::new(0.0, 0.0),
+		Vec2::new(1920.0, 0.0),
+		Vec2::new(1920.0, 1080.0),
+		Vec2::new(0.0, 1080.0),
+	];
+	let homography = Homography::fit(from, to).unwrap();
+	for (from, to) in from.into_iter().zip(to) {
+		assert_near(homography.apply(from).unwrap(), to);
+	}
+
+	// the inverse fit maps the points back
+	let inverse = Homography::fit(to, from).unwrap();
+	for point in [Vec2::new(960.0, 540.0), Vec2::new(100.0, 900.0)] {
+		assert_near(
+			homography.apply(inverse.apply(point).unwrap()).unwrap(),
+			point,
+		);
+	}
+}
+
+#[test]
+fn fits_scale() {
+	let size = Vec2::new(512.0, 424.0);
+	let screen = Vec2::new(1920.0, 1080.0);
+	let corners = |size: Vec2| {
+		[
+			Vec2::ZERO,
+			Vec2::new(size.x, 0.0),
+			size,
+			Vec2::new(0.0, size.y),
+		]
+	};
+	let homography = Homography::fit(corners(size), corners(screen)).unwrap();
+	let scale = Homography::scale(size, screen);
+	for point in [Vec2::new(256.0, 212.0), Vec2::new(10.0, 400.0)] {
+		assert_near(
+			homography.apply(point).unwrap(),
+			scale.apply(point).unwrap(),
+		);
+	}
+}
+
+#[test]
+fn rejects_degenerate_points() {
+	let to = [
+		Vec2::new(0.0, 0.0),
+		Vec2::new(1920.0, 0.0),
+		Vec2::new(1920.0, 1080.0),
+		Vec2::new(0.0, 1080.0),
+	];
+	// three of the points are collinear
+	let collinear = [
+		Vec2::new(0.0, 0.0),
+		Vec2::new(100.0, 100.0),
+		Vec2::new(200.0, 200.0),
+		Vec2::new(0.0, 300.0),
+	];
+	assert_eq!(Homography::fit(collinear, to), None);
+	assert_eq!(Homography::fit([Vec2::ONE; 4], to), None);
+}
+
+#[test]
+fn maps_nothing_to_infinity() {
+	let homography = Homography::scale(Vec2::ZERO, Vec2::ONE);
+	assert_eq!(homography.apply(Vec2::ONE), None);
+}
+
+#[test]
+fn rejects_degenerate_calibration() {
+	let directory =
+		std::env::temp_dir().join(format!("kinect-to-x11-mapping-{}", std::process::id()));
+	let path = directory.join("calibration.toml");
+
+	let homography = Homography::scale(Vec2::new(512.0, 424.0), Vec2::new(1920.0, 1080.0));
+	Calibration::new([1920, 1080], homography)
+		.save(&path)
+		.unwrap();
+	assert_eq!(Calibration::load(&path).unwrap().homography(), homography);
+
+	for rows in [
+		"[[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, nan]]",
+		"[[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, inf]]",
+		"[[1.0, 2.0, 0.0], [2.0, 4.0, 0.0], [0.0, 0.0, 1.0]]",
+	] {
+		std::fs::write(
+			&path,
+			format!("screen_size = [1920, 1080]\nhomography = {rows}\n"),
+		)
+		.unwrap();
+		assert!(matches!(Calibration::load(&path), Err(Error::Degenerate)));
+	}
+	std::fs::remove_dir_all(&directory).unwrap();
+}
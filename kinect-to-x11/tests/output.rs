@@ -0,0 +1,151 @@
+//! Tests of turning synthetic hand sequences into output events.
+
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+
+use kinect_to_x11::gesture::{ClickConfig, ClickDetector};
+use kinect_to_x11::output::{self, Button, Event, Key, Output, Recorder};
+
+/// The time between frames at 30 frames per second, in units of 100 microseconds.
+const FRAME: u32 = 333;
+
+/// A hand resting at 1000 mm, then pushed quickly to 930 mm.
+const PUSH: [f32; 9] = [
+	1000.0, 1000.0, 1000.0, 1000.0, 1000.0, 1000.0, 970.0, 940.0, 930.0,
+];
+
+/// Move the pointer to `(index, index)` for each frame and apply the gestures recognized from the depths, then lose the hand, as the pointer loop does.
+fn drive(output: &mut dyn Output, depths: &[f32]) {
+	let mut clicks = ClickDetector::new(ClickConfig::default());
+	let mut timestamp = 0;
+	for (index, &depth) in depths.iter().enumerate() {
+		timestamp = FRAME * u32::try_from(index).unwrap();
+		let position = i16::try_from(index).unwrap();
+		output.move_to(position, position).unwrap();
+		output::apply_gesture(output, Button::Left, clicks.update(timestamp, depth)).unwrap();
+	}
+	output::apply_gesture(output, Button::Left, clicks.reset(timestamp)).unwrap();
+}
+
+/// The move events for frames `range`.
+fn moves(range: std::ops::Range<i16>) -> impl Iterator<Item = Event> {
+	range.map(|position| Event::Move {
+		x: position,
+		y: position,
+	})
+}
+
+#[test]
+fn records_click() {
+	let mut depths = PUSH.to_vec();
+	depths.extend([960.0, 975.0, 975.0]);
+	let mut recorder = Recorder::in_memory((1920, 1080));
+	drive(&mut recorder, &depths);
+
+	// the click is recognized when the hand is pulled back, at frame 10
+	let expected: Vec<_> = moves(0..11)
+		.chain([
+			Event::Press {
+				button: Button::Left,
+			},
+			Event::Release {
+				button: Button::Left,
+			},
+		])
+		.chain(moves(11..12))
+		.collect();
+	assert_eq!(recorder.events(), expected);
+}
+
+#[test]
+fn records_drag() {
+	let config = ClickConfig::default();
+	let mut depths = PUSH.to_vec();
+	// the push is recognized at 940 mm, at frame 7
+	let drag_start = 7 + usize::try_from(config.drag_delay.div_ceil(FRAME)).unwrap();
+	depths.resize(drag_start + 5, 930.0);
+	depths.extend([975.0, 975.0]);
+	let mut recorder = Recorder::in_memory((1920, 1080));
+	drive(&mut recorder, &depths);
+
+	let drag_start = i16::try_from(drag_start).unwrap();
+	let drag_end = drag_start + 5;
+	let expected: Vec<_> = moves(0..drag_start + 1)
+		.chain([Event::Press {
+			button: Button::Left,
+		}])
+		.chain(moves(drag_start + 1..drag_end + 1))
+		.chain([Event::Release {
+			button: Button::Left,
+		}])
+		.chain(moves(drag_end + 1..drag_end + 2))
+		.collect();
+	assert_eq!(recorder.events(), expected);
+}
+
+#[test]
+fn releases_drag_when_hand_is_lost() {
+	let config = ClickConfig::default();
+	let mut depths = PUSH.to_vec();
+	depths.resize(
+		8 + usize::try_from(config.drag_delay.div_ceil(FRAME)).unwrap(),
+		930.0,
+	);
+	let mut recorder = Recorder::in_memory((1920, 1080));
+	drive(&mut recorder, &depths);
+
+	let events = recorder.events();
+	assert_eq!(
+		events[events.len() - 2..],
+		[
+			Event::Press {
+				button: Button::Left,
+			},
+			Event::Release {
+				button: Button::Left,
+			},
+		]
+	);
+}
+
+/// A writer whose output can be inspected after it is given to a [`Recorder`].
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.0.borrow_mut().write(buf)
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		Ok(())
+	}
+}
+
+#[test]
+fn writes_json_lines() {
+	let buffer = SharedBuffer::default();
+	let mut recorder = Recorder::to_writer((1920, 1080), buffer.clone());
+	let mut depths = PUSH.to_vec();
+	depths.extend([960.0, 975.0]);
+	drive(&mut recorder, &depths);
+	recorder.scroll(1, i32::MIN).unwrap();
+	recorder.key(Key(30), true).unwrap();
+
+	// events are written out rather than kept
+	assert_eq!(recorder.events(), []);
+	let output = String::from_utf8(buffer.0.take()).unwrap();
+	let lines: Vec<_> = output.lines().collect();
+	assert_eq!(lines.len(), 15);
+	assert_eq!(lines[0], r#"{"event":"move","x":0,"y":0}"#);
+	assert_eq!(
+		lines[11..],
+		[
+			r#"{"event":"press","button":"left"}"#,
+			r#"{"event":"release","button":"left"}"#,
+			r#"{"event":"scroll","horizontal":1,"vertical":-2147483648}"#,
+			r#"{"event":"key","key":30,"pressed":true}"#,
+		]
+	);
+}
@@ -0,0 +1,73 @@
+//! Tests of the whole pipeline, from the depth frames of a fake device to output events.
+
+#![cfg(feature = "mock")]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use freenect2::Context;
+use glam::Vec2;
+use kinect_to_x11::gesture::{ClickConfig, ClickDetector};
+use kinect_to_x11::mapping::Homography;
+use kinect_to_x11::output::{self, Button, Event, Output, Recorder};
+use kinect_to_x11::stream::DepthFrames;
+use kinect_to_x11::tracker::{HandTracker, TrackerConfig};
+use kinect_to_x11::{DEPTH_HEIGHT, DEPTH_WIDTH};
+
+#[test]
+fn follows_synthetic_hand() {
+	let mut context = Context::new();
+	let mut device = context.open_default_device().unwrap();
+	let running = Arc::new(AtomicBool::new(true));
+	let mut frames = DepthFrames::start(&mut device, Arc::clone(&running)).unwrap();
+
+	let depth_size = Vec2::new(DEPTH_WIDTH as f32, DEPTH_HEIGHT as f32);
+	let mapping = Homography::scale(depth_size, Vec2::new(1920.0, 1080.0));
+	let mut tracker = HandTracker::new(TrackerConfig::default());
+	let mut clicks = ClickDetector::new(ClickConfig::default());
+	let mut recorder = Recorder::in_memory((1920, 1080));
+	for frame in frames.by_ref().take(5) {
+		let depth = frame.as_depth().unwrap();
+		// the fake device shows a disc with a radius of 30 pixels at 900 mm, moving around an ellipse in front of a wall
+		let hand = tracker.track(depth.pixels().try_into().unwrap()).unwrap();
+		assert!((hand.mean_depth - 900.0).abs() < 1.0);
+		assert!((2500..3000).contains(&hand.area));
+		let offset = (hand.centroid - depth_size / 2.0) / Vec2::new(100.0, 80.0);
+		assert!((0.9..1.1).contains(&offset.length()), "{hand:?}");
+
+		let position = mapping.apply(hand.centroid).unwrap();
+		recorder
+			.move_to(
+				az::saturating_cast(position.x),
+				az::saturating_cast(position.y),
+			)
+			.unwrap();
+		output::apply_gesture(
+			&mut recorder,
+			Button::Left,
+			clicks.update(frame.timestamp(), hand.mean_depth),
+		)
+		.unwrap();
+	}
+
+	// the hand stays at the same depth, so it only moves the pointer
+	let events = recorder.events();
+	assert_eq!(events.len(), 5);
+	for event in events {
+		assert!(
+			matches!(
+				event,
+				Event::Move {
+					x: 0..=1919,
+					y: 0..=1079
+				}
+			),
+			"{event:?}"
+		);
+	}
+
+	running.store(false, Ordering::SeqCst);
+	assert!(frames.next().is_none());
+	drop(frames);
+	device.stop().unwrap();
+}
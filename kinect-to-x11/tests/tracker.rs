@@ -0,0 +1,100 @@
+//! Tests of the hand tracker on synthetic depth frames.
+
+use glam::{UVec2, Vec2};
+use kinect_to_x11::tracker::{BoundingBox, DepthFrame, HandTracker, TrackerConfig};
+use kinect_to_x11::{DEPTH_HEIGHT, DEPTH_WIDTH};
+
+/// A frame with nothing in it, which is boxed because it is too large for the stack.
+fn empty_frame() -> Box<DepthFrame> {
+	vec![0.0; DEPTH_WIDTH * DEPTH_HEIGHT]
+		.into_boxed_slice()
+		.try_into()
+		.unwrap()
+}
+
+/// Fill the rectangle from `min` to `max`, inclusive, with `depth`.
+fn fill(frame: &mut DepthFrame, min: (usize, usize), max: (usize, usize), depth: f32) {
+	for y in min.1..=max.1 {
+		frame[y * DEPTH_WIDTH + min.0..=y * DEPTH_WIDTH + max.0].fill(depth);
+	}
+}
+
+#[test]
+fn finds_nothing_in_empty_frame() {
+	let mut tracker = HandTracker::new(TrackerConfig::default());
+	assert_eq!(tracker.track(&empty_frame()), None);
+}
+
+#[test]
+fn reports_blob_properties() {
+	let mut frame = empty_frame();
+	fill(&mut frame, (100, 50), (109, 59), 700.0);
+	fill(&mut frame, (110, 50), (119, 59), 720.0);
+
+	let mut tracker = HandTracker::new(TrackerConfig::default());
+	let hand = tracker.track(&frame).unwrap();
+	assert_eq!(hand.area, 200);
+	assert_eq!(
+		hand.bounding_box,
+		BoundingBox {
+			min: UVec2::new(100, 50),
+			max: UVec2::new(119, 59),
+		}
+	);
+	assert!(hand.centroid.abs_diff_eq(Vec2::new(109.5, 54.5), 1e-4));
+	assert!((hand.mean_depth - 710.0).abs() < 1e-3);
+}
+
+#[test]
+fn prefers_nearest_blob() {
+	let mut frame = empty_frame();
+	fill(&mut frame, (10, 10), (39, 39), 1000.0);
+	fill(&mut frame, (300, 200), (329, 229), 800.0);
+
+	let mut tracker = HandTracker::new(TrackerConfig::default());
+	let hand = tracker.track(&frame).unwrap();
+	assert_eq!(hand.bounding_box.min, UVec2::new(300, 200));
+	assert_eq!(hand.mean_depth, 800.0);
+}
+
+#[test]
+fn ignores_blobs_outside_depth_band() {
+	let mut frame = empty_frame();
+	fill(&mut frame, (10, 10), (39, 39), 400.0);
+	fill(&mut frame, (100, 10), (129, 39), 1600.0);
+	fill(&mut frame, (300, 200), (329, 229), 1200.0);
+
+	let config = TrackerConfig::default();
+	let mut tracker = HandTracker::new(config);
+	assert_eq!(tracker.track(&frame).unwrap().mean_depth, 1200.0);
+
+	tracker.set_config(TrackerConfig {
+		max_depth: 1100.0,
+		..config
+	});
+	assert_eq!(tracker.track(&frame), None);
+}
+
+#[test]
+fn skips_small_blobs() {
+	let mut frame = empty_frame();
+	// 100 pixels, fewer than the default minimum area
+	fill(&mut frame, (10, 10), (19, 19), 600.0);
+	fill(&mut frame, (300, 200), (329, 229), 900.0);
+
+	let mut tracker = HandTracker::new(TrackerConfig::default());
+	assert_eq!(tracker.track(&frame).unwrap().mean_depth, 900.0);
+}
+
+#[test]
+fn stops_at_hand_depth() {
+	let mut frame = empty_frame();
+	// an arm, with the hand at its end closer to the sensor
+	fill(&mut frame, (100, 100), (199, 119), 900.0);
+	fill(&mut frame, (200, 100), (219, 119), 750.0);
+
+	let mut tracker = HandTracker::new(TrackerConfig::default());
+	let hand = tracker.track(&frame).unwrap();
+	assert_eq!(hand.area, 400);
+	assert_eq!(hand.bounding_box.min, UVec2::new(200, 100));
+}
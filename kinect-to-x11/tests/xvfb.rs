@@ -0,0 +1,88 @@
+//! Tests of the whole program against a virtual X server, using the fake devices of the `mock` feature.
+//!
+//! These need `Xvfb` and are ignored by default; run them with `cargo test --features mock -- --ignored`.
+
+#![cfg(feature = "mock")]
+
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use kinect_to_x11::{DEPTH_HEIGHT, DEPTH_WIDTH};
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::ConnectionExt;
+
+const SCREEN_WIDTH: u16 = 1280;
+const SCREEN_HEIGHT: u16 = 1024;
+
+/// Kills the process when dropped, so that it does not outlive a failed test.
+struct KillOnDrop(Child);
+
+impl Drop for KillOnDrop {
+	fn drop(&mut self) {
+		let _ = self.0.kill();
+		let _ = self.0.wait();
+	}
+}
+
+/// Start a virtual X server and return it along with its display name.
+fn start_xvfb() -> (KillOnDrop, String) {
+	let mut xvfb = Command::new("Xvfb")
+		.args(["-displayfd", "1", "-nolisten", "tcp", "-screen", "0"])
+		.arg(format!("{SCREEN_WIDTH}x{SCREEN_HEIGHT}x24"))
+		.stdout(Stdio::piped())
+		.stderr(Stdio::null())
+		.spawn()
+		.expect("could not start Xvfb");
+	// Xvfb writes the display number once it is ready to accept connections
+	let mut display = String::new();
+	BufReader::new(xvfb.stdout.take().unwrap())
+		.read_line(&mut display)
+		.unwrap();
+	(KillOnDrop(xvfb), format!(":{}", display.trim()))
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn moves_pointer() {
+	let (_xvfb, display) = start_xvfb();
+	let directory = std::env::temp_dir().join(format!("kinect-to-x11-xvfb-{}", std::process::id()));
+	std::fs::create_dir_all(&directory).unwrap();
+
+	// without a configuration or calibration, the whole depth image is mapped onto the screen
+	let mut program = KillOnDrop(
+		Command::new(env!("CARGO_BIN_EXE_kinect-to-x11"))
+			.env("DISPLAY", &display)
+			.arg("--config")
+			.arg(directory.join("config.toml"))
+			.args(["run", "--output", "xtest", "--calibration"])
+			.arg(directory.join("calibration.toml"))
+			.spawn()
+			.unwrap(),
+	);
+	std::thread::sleep(Duration::from_secs(2));
+
+	let (connection, screen) = x11rb::connect(Some(&display)).unwrap();
+	let root = connection.setup().roots[screen].root;
+	let pointer = connection.query_pointer(root).unwrap().reply().unwrap();
+
+	// the synthetic object moves around an ellipse with radii of 100 and 80 pixels about the center of the depth image
+	let x = (f32::from(pointer.root_x) / f32::from(SCREEN_WIDTH) - 0.5) * DEPTH_WIDTH as f32 / 100.0;
+	let y = (f32::from(pointer.root_y) / f32::from(SCREEN_HEIGHT) - 0.5) * DEPTH_HEIGHT as f32 / 80.0;
+	let radius = x.hypot(y);
+	assert!(
+		(0.8..=1.2).contains(&radius),
+		"pointer is at ({}, {})",
+		pointer.root_x,
+		pointer.root_y
+	);
+
+	let status = Command::new("kill")
+		.arg("-INT")
+		.arg(program.0.id().to_string())
+		.status()
+		.unwrap();
+	assert!(status.success());
+	assert!(program.0.wait().unwrap().success());
+	std::fs::remove_dir_all(&directory).unwrap();
+}